@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use crate::runtime::ledger::LedgerConfig;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -23,6 +24,117 @@ pub enum Commands {
 
     /// Analyze contract and generate gas optimization suggestions
     Optimize(OptimizeArgs),
+
+    /// Run contract functions and report which code they executed
+    Coverage(CoverageArgs),
+}
+
+/// Authorization mode for contract invocations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthMode {
+    /// Every `require_auth` call succeeds
+    MockAll,
+    /// Record authorizations like transaction simulation does
+    Recording,
+    /// Grant only the authorizations listed in --auth-file
+    Explicit,
+}
+
+/// Output format of a coverage report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CoverageFormat {
+    /// lcov tracefile, as read by genhtml and most editors
+    #[default]
+    Lcov,
+    /// Cobertura XML, as read by most CI services
+    Cobertura,
+}
+
+/// Ledger context options shared by commands that execute contracts
+#[derive(Args, Debug, Clone, Default)]
+pub struct LedgerArgs {
+    /// Ledger sequence number
+    #[arg(long)]
+    pub ledger_sequence: Option<u32>,
+
+    /// Ledger close time as a Unix timestamp
+    #[arg(long)]
+    pub ledger_timestamp: Option<u64>,
+
+    /// Ledger protocol version, among those the host supports
+    #[arg(long)]
+    pub protocol_version: Option<u32>,
+
+    /// Network passphrase used to derive the network ID
+    #[arg(long)]
+    pub network_passphrase: Option<String>,
+
+    /// Base reserve in stroops
+    #[arg(long)]
+    pub base_reserve: Option<u32>,
+
+    /// Minimum TTL of new persistent entries, in ledgers
+    #[arg(long)]
+    pub min_persistent_ttl: Option<u32>,
+
+    /// Minimum TTL of new temporary entries, in ledgers
+    #[arg(long)]
+    pub min_temporary_ttl: Option<u32>,
+
+    /// Maximum TTL of any entry, in ledgers
+    #[arg(long)]
+    pub max_entry_ttl: Option<u32>,
+}
+
+impl LedgerArgs {
+    /// Convert the options into a ledger config
+    pub fn to_config(&self) -> LedgerConfig {
+        LedgerConfig {
+            sequence_number: self.ledger_sequence,
+            timestamp: self.ledger_timestamp,
+            protocol_version: self.protocol_version,
+            network_passphrase: self.network_passphrase.clone(),
+            base_reserve: self.base_reserve,
+            min_persistent_entry_ttl: self.min_persistent_ttl,
+            min_temp_entry_ttl: self.min_temporary_ttl,
+            max_entry_ttl: self.max_entry_ttl,
+        }
+    }
+}
+
+/// Options describing the session a contract is executed in
+#[derive(Args, Debug, Clone, Default)]
+pub struct SessionArgs {
+    /// Alias for the contract, usable as an address in arguments (default: WASM file name)
+    #[arg(long)]
+    pub alias: Option<String>,
+
+    /// Constructor arguments as JSON, for contracts with a constructor
+    #[arg(long)]
+    pub constructor_args: Option<String>,
+
+    /// Ledger snapshot JSON to load as the starting state
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// Run the WASM as the code of this existing contract from the snapshot
+    #[arg(long, requires = "snapshot", conflicts_with = "constructor_args")]
+    pub contract_id: Option<String>,
+
+    /// Authorization mode for `require_auth` calls
+    #[arg(long, value_enum)]
+    pub auth: Option<AuthMode>,
+
+    /// JSON file listing granted authorizations (for --auth explicit)
+    #[arg(long, required_if_eq("auth", "explicit"))]
+    pub auth_file: Option<PathBuf>,
+
+    /// Session config file (TOML)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub ledger: LedgerArgs,
 }
 
 #[derive(Parser)]
@@ -39,14 +151,38 @@ pub struct RunArgs {
     #[arg(short, long)]
     pub args: Option<String>,
 
-    /// Initial storage state as JSON object
+    /// Initial storage entries as JSON array of {key, value, durability, ttl}
     #[arg(short, long)]
     pub storage: Option<String>,
 
-    /// Set breakpoint at function name
+    /// Set breakpoint at function name, host operation (`host:<name>`,
+    /// `auth:<address>`, `event:<topic>`, `call:<contract>[::<function>]`),
+    /// source line (`src/lib.rs:42`) or failure (`catch:error`,
+    /// `catch:panic`, `catch:budget`), optionally with `if <condition>`
     #[arg(short, long)]
     pub breakpoint: Vec<String>,
 
+    /// Print a message where a function is entered, as
+    /// `<function> [if <condition>]: <message>` with `{expressions}`
+    #[arg(short, long)]
+    pub log: Vec<String>,
+
+    /// Also print the return value as base64 XDR
+    #[arg(long)]
+    pub show_xdr: bool,
+
+    /// Write the ledger state after execution to a snapshot JSON file
+    #[arg(long)]
+    pub export_snapshot: Option<PathBuf>,
+
+    /// Ignore the breakpoints and watches saved for the contract in
+    /// .soroban-debug/session.toml
+    #[arg(long)]
+    pub no_session: bool,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+
     /// Enable verbose output
     #[arg(short, long)]
     pub verbose: bool,
@@ -58,6 +194,17 @@ pub struct InteractiveArgs {
     #[arg(short, long)]
     pub contract: PathBuf,
 
+    /// Write the ledger state to a snapshot JSON file on exit
+    #[arg(long)]
+    pub export_snapshot: Option<PathBuf>,
+
+    /// Neither restore nor save the session in .soroban-debug/session.toml
+    #[arg(long)]
+    pub no_session: bool,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+
     /// Enable verbose output
     #[arg(short, long)]
     pub verbose: bool,
@@ -96,7 +243,40 @@ pub struct OptimizeArgs {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Initial storage state as JSON object
+    /// Initial storage entries as JSON array of {key, value, durability, ttl}
     #[arg(short, long)]
     pub storage: Option<String>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+#[derive(Parser)]
+pub struct CoverageArgs {
+    /// Path to the contract WASM file
+    #[arg(short, long)]
+    pub contract: PathBuf,
+
+    /// Function to call; give it several times to call functions in order
+    #[arg(short, long, required = true)]
+    pub function: Vec<String>,
+
+    /// Function arguments as JSON array, given once for every call or once per call
+    #[arg(short, long)]
+    pub args: Vec<String>,
+
+    /// Output file for the coverage report (default: stdout)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Format of the coverage report
+    #[arg(long, value_enum, default_value_t)]
+    pub format: CoverageFormat,
+
+    /// Initial storage entries as JSON array of {key, value, durability, ttl}
+    #[arg(short, long)]
+    pub storage: Option<String>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
 }
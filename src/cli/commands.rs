@@ -1,10 +1,27 @@
-use crate::cli::args::{InspectArgs, InteractiveArgs, OptimizeArgs, RunArgs};
-use crate::debugger::engine::DebuggerEngine;
-use crate::runtime::executor::ContractExecutor;
+use crate::cli::args::{
+    AuthMode, CoverageArgs, CoverageFormat, InspectArgs, InteractiveArgs, LedgerArgs, OptimizeArgs,
+    RunArgs, SessionArgs,
+};
+use crate::cli::config::SessionConfig;
+use crate::cli::saved_session::{wasm_hash, SavedSession, SavedSessions, SESSION_FILE};
+use crate::debugger::breakpoint::{Breakpoint, BreakpointKind};
+use crate::debugger::engine::{DebugContext, DebuggerEngine};
+use crate::debugger::observer::StdoutObserver;
+use crate::debugger::stepper::StepGranularity;
+use crate::debugger::watch::Watch;
+use crate::debugger::worker::EngineWorker;
+use crate::profiler::coverage::CoverageCollector;
+use crate::runtime::auth::{parse_auth_entries, AuthConfig};
+use crate::runtime::contracts::ContractRegistry;
+use crate::runtime::executor::{ContractExecutor, DEFAULT_ALIAS};
+use crate::runtime::ledger::LedgerConfig;
+use crate::runtime::snapshot::{load_snapshot, save_snapshot};
 use crate::ui::tui::DebuggerUI;
-use crate::Result;
+use crate::{DebuggerError, Result};
 use anyhow::Context;
 use std::fs;
+use std::path::Path;
+use std::rc::Rc;
 
 /// Execute the run command
 pub fn run(args: RunArgs) -> Result<()> {
@@ -30,6 +47,52 @@ pub fn run(args: RunArgs) -> Result<()> {
         None
     };
 
+    let saved = if args.no_session {
+        None
+    } else {
+        load_saved_session(&args.contract, &wasm_hash(&wasm_bytes))?
+    };
+    let (saved_breakpoints, saved_watches) = match &saved {
+        Some((session, _)) => (session.parse_breakpoints(), session.parse_watches()),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let breakpoints = args
+        .breakpoint
+        .iter()
+        .map(|spec| spec.parse::<Breakpoint>())
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let logpoints = args
+        .log
+        .iter()
+        .map(|spec| Breakpoint::logpoint(spec))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // Breakpoints on source lines need to see every instruction, those on
+    // host operations and watches every host function call, and others the
+    // function calls. Without any, the contract runs as built.
+    let kinds: Vec<&BreakpointKind> = breakpoints
+        .iter()
+        .chain(&logpoints)
+        .chain(&saved_breakpoints)
+        .map(|b| &b.kind)
+        .collect();
+    let hooks = if kinds
+        .iter()
+        .any(|k| matches!(k, BreakpointKind::Source { .. }))
+    {
+        Hooks::Stepping
+    } else if !saved_watches.is_empty()
+        || kinds
+            .iter()
+            .any(|k| !matches!(k, BreakpointKind::Function(_)))
+    {
+        Hooks::HostCalls
+    } else if !kinds.is_empty() {
+        Hooks::Calls
+    } else {
+        Hooks::None
+    };
+
     println!("\nStarting debugger...");
     println!("Function: {}", args.function);
     if let Some(ref args) = parsed_args {
@@ -37,22 +100,71 @@ pub fn run(args: RunArgs) -> Result<()> {
     }
 
     // Create executor
-    let mut executor = ContractExecutor::new(wasm_bytes)?;
+    let mut executor = build_executor(&args.contract, &wasm_bytes, &args.session, hooks)?;
 
     // Set up initial storage if provided
     if let Some(storage) = initial_storage {
         executor.set_initial_storage(storage)?;
     }
 
+    if let Some(auth) = load_auth_config(args.session.auth, args.session.auth_file.as_deref())? {
+        executor.set_auth(auth)?;
+    }
+
     // Create debugger engine
-    let mut engine = DebuggerEngine::new(executor, args.breakpoint);
+    let mut engine = DebuggerEngine::new(executor, vec![])?;
+    engine.add_observer(Rc::new(StdoutObserver::new().with_pauses()))?;
+    for breakpoint in breakpoints.into_iter().chain(logpoints) {
+        if let BreakpointKind::Source { file, line } = &breakpoint.kind {
+            if engine
+                .executor()
+                .contracts()
+                .resolve_line(file, *line)
+                .is_empty()
+            {
+                eprintln!(
+                    "Warning: No code found at {}:{}; is the contract built with debug info?",
+                    file, line
+                );
+            }
+        }
+        engine.breakpoints_mut().insert(breakpoint);
+    }
+    if let Some((_, stale)) = saved {
+        restore_session(&mut engine, saved_breakpoints, saved_watches, stale);
+    }
 
     // Execute with debugging
     println!("\n--- Execution Start ---\n");
     let result = engine.execute(&args.function, parsed_args.as_deref())?;
     println!("\n--- Execution Complete ---\n");
 
-    println!("Result: {:?}", result);
+    println!("Result: {}", result.pretty());
+    println!(
+        "Cost ({}): {} CPU instructions, {} memory bytes",
+        result.contract, result.cpu_instructions, result.memory_bytes
+    );
+    if args.show_xdr {
+        println!("Result XDR: {}", result.to_xdr_base64()?);
+    }
+
+    if !result.events.is_empty() {
+        println!("\nEvents:");
+        for event in &result.events {
+            println!("  {}", event);
+        }
+    }
+
+    if !result.auths.is_empty() {
+        println!("\nAuthorizations:");
+        for auth in &result.auths {
+            print!("{}", auth);
+        }
+    }
+
+    if let Some(path) = &args.export_snapshot {
+        export_snapshot(engine.executor(), path)?;
+    }
 
     Ok(())
 }
@@ -67,22 +179,138 @@ pub fn interactive(args: InteractiveArgs) -> Result<()> {
 
     println!("Contract loaded successfully ({} bytes)", wasm_bytes.len());
 
-    // Create executor
-    let executor = ContractExecutor::new(wasm_bytes)?;
+    let hash = wasm_hash(&wasm_bytes);
+    let saved = if args.no_session {
+        None
+    } else {
+        load_saved_session(&args.contract, &hash)?
+    };
+    let save_as = (!args.no_session).then(|| args.contract.clone());
+
+    // The engine and its contract environment live on their own thread, so
+    // calls can pause there while the UI takes commands
+    let export = args.export_snapshot.clone();
+    let worker = EngineWorker::spawn(move || {
+        let mut executor =
+            build_executor(&args.contract, &wasm_bytes, &args.session, Hooks::Stepping)?;
+
+        if let Some(auth) = load_auth_config(args.session.auth, args.session.auth_file.as_deref())?
+        {
+            executor.set_auth(auth)?;
+        }
 
-    // Create debugger engine
-    let engine = DebuggerEngine::new(executor, vec![]);
+        let mut engine = DebuggerEngine::new(executor, vec![])?;
+        if let Some((session, stale)) = saved {
+            let (breakpoints, watches) = (session.parse_breakpoints(), session.parse_watches());
+            restore_session(&mut engine, breakpoints, watches, stale);
+            if let Some(granularity) = session.step_granularity() {
+                engine.set_step_granularity(granularity);
+            }
+        }
+        Ok(engine)
+    })?;
 
     // Start interactive UI
     println!("\nStarting interactive mode...");
     println!("Type 'help' for available commands\n");
 
-    let mut ui = DebuggerUI::new(engine)?;
+    let mut ui = DebuggerUI::new(worker)?;
     ui.run()?;
 
+    if let Some(wasm) = save_as {
+        let session = ui.worker().with(move |ctx| session_of(ctx, wasm))?;
+        save_session(&hash, session)?;
+    }
+
+    if let Some(path) = export {
+        ui.worker().with(move |ctx| match ctx.engine() {
+            Some(engine) => export_snapshot(engine.executor(), &path),
+            None => Ok(()),
+        })??;
+    }
+
     Ok(())
 }
 
+/// The session saved in the project for a contract, if there is one, and
+/// whether it was saved for another build of the contract
+fn load_saved_session(wasm: &Path, hash: &str) -> Result<Option<(SavedSession, bool)>> {
+    let sessions = SavedSessions::load(Path::new(SESSION_FILE))?;
+    Ok(sessions
+        .find(wasm, hash)
+        .map(|(session, stale)| (session.clone(), stale)))
+}
+
+/// Set a saved session's breakpoints and watches on an engine. For a session
+/// saved for another build, breakpoints at functions or lines the contracts
+/// no longer have are flagged.
+fn restore_session(
+    engine: &mut DebuggerEngine,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Watch>,
+    stale: bool,
+) {
+    println!("Restoring saved session from {}", SESSION_FILE);
+    if stale {
+        println!("Warning: The session was saved for another build of the contract; its breakpoints may be stale");
+    }
+    for breakpoint in breakpoints {
+        let reason = stale
+            .then(|| stale_reason(engine.executor().contracts(), &breakpoint.kind))
+            .flatten();
+        match reason {
+            Some(reason) => println!("  stale breakpoint: {} ({})", breakpoint, reason),
+            None => println!("  breakpoint: {}", breakpoint),
+        }
+        engine.breakpoints_mut().insert(breakpoint);
+    }
+    for watch in watches {
+        println!("  watch: {}", watch);
+        engine.watches_mut().insert(watch);
+    }
+}
+
+/// Why a breakpoint can no longer be hit in the registered contracts, if it
+/// cannot
+fn stale_reason(contracts: &ContractRegistry, kind: &BreakpointKind) -> Option<&'static str> {
+    match kind {
+        BreakpointKind::Function(name) if !contracts.iter().any(|c| c.has_function(name)) => {
+            Some("no such function")
+        }
+        BreakpointKind::Source { file, line } if contracts.resolve_line(file, *line).is_empty() => {
+            Some("no code at this line")
+        }
+        _ => None,
+    }
+}
+
+/// What an interactive session has set up, to save for its contract
+fn session_of(ctx: &mut dyn DebugContext, wasm: std::path::PathBuf) -> SavedSession {
+    let granularity = ctx.step_granularity();
+    let watches = ctx.watches_mut().iter().map(|w| w.to_string()).collect();
+    let (logpoints, breakpoints): (Vec<_>, Vec<_>) =
+        ctx.breakpoints_mut().iter().partition(|b| b.log.is_some());
+    SavedSession {
+        wasm,
+        breakpoints: breakpoints.iter().map(|b| b.to_string()).collect(),
+        logpoints: logpoints.iter().map(|b| b.to_string()).collect(),
+        watches,
+        step_mode: (granularity != StepGranularity::default()).then(|| granularity.to_string()),
+    }
+}
+
+/// Save a contract's session in the project, without creating the session
+/// file for an empty one
+fn save_session(hash: &str, session: SavedSession) -> Result<()> {
+    let path = Path::new(SESSION_FILE);
+    let mut sessions = SavedSessions::load(path)?;
+    if session.is_empty() && !path.exists() {
+        return Ok(());
+    }
+    sessions.insert(hash, session);
+    sessions.save(path)
+}
+
 /// Execute the inspect command
 pub fn inspect(args: InspectArgs) -> Result<()> {
     println!("Inspecting contract: {:?}", args.contract);
@@ -126,6 +354,134 @@ fn parse_storage(json: &str) -> Result<String> {
     Ok(json.to_string())
 }
 
+/// Command-line ledger options take precedence over the config file
+fn ledger_config(args: &LedgerArgs, session: &SessionConfig) -> LedgerConfig {
+    let mut ledger = args.to_config();
+    ledger.merge(&session.ledger);
+    ledger
+}
+
+/// What contracts registered by [`build_executor`] report as they run
+enum Hooks {
+    /// Nothing: contracts run as built, and their costs are exact
+    None,
+    /// Function calls only
+    Calls,
+    /// Basic blocks too
+    Coverage,
+    /// Host function calls too
+    HostCalls,
+    /// Every instruction too
+    Stepping,
+}
+
+/// Create an executor with the session's snapshot, accounts, assets and
+/// contracts loaded ahead of the contract being debugged, which becomes the
+/// invocation target. The contracts are instrumented as `hooks` asks.
+fn build_executor(
+    path: &Path,
+    wasm: &[u8],
+    args: &SessionArgs,
+    hooks: Hooks,
+) -> Result<ContractExecutor> {
+    let session = SessionConfig::load(args.config.as_deref())?;
+    let snapshot = args.snapshot.as_deref().map(load_snapshot).transpose()?;
+    if let Some(snapshot) = &snapshot {
+        println!(
+            "Loaded snapshot with {} ledger entries",
+            snapshot.ledger_entries.len()
+        );
+    }
+    let mut executor = ContractExecutor::empty(&ledger_config(&args.ledger, &session), snapshot)?;
+    match hooks {
+        Hooks::None => {}
+        Hooks::Calls => executor.enable_tracing()?,
+        Hooks::Coverage => executor.enable_coverage()?,
+        Hooks::HostCalls => executor.enable_host_calls()?,
+        Hooks::Stepping => executor.enable_stepping()?,
+    }
+
+    for name in &session.accounts {
+        let id = executor.add_account(name)?;
+        println!("Account {} is {}", name, id);
+    }
+
+    for asset in &session.assets {
+        let id = executor.register_asset(asset)?;
+        println!("Registered asset {} at {}", asset.alias, id);
+    }
+
+    for contract in &session.contracts {
+        let bytes = fs::read(&contract.wasm)
+            .with_context(|| format!("Failed to read WASM file: {:?}", contract.wasm))?;
+        let id = executor.register_contract(
+            &contract.alias,
+            &bytes,
+            contract.address.as_deref(),
+            contract.constructor_args.as_ref(),
+        )?;
+        println!("Registered {} at {}", contract.alias, id);
+    }
+
+    let alias = match &args.alias {
+        Some(alias) => alias.clone(),
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| DEFAULT_ALIAS.to_string()),
+    };
+    if let Some(contract_id) = &args.contract_id {
+        executor.attach_contract(&alias, wasm, contract_id)?;
+        println!("Running {} as {}", alias, contract_id);
+    } else {
+        let constructor_args = args
+            .constructor_args
+            .as_deref()
+            .map(|json| {
+                serde_json::from_str::<serde_json::Value>(json)
+                    .with_context(|| format!("Invalid JSON constructor arguments: {}", json))
+            })
+            .transpose()?;
+        executor.register_contract(&alias, wasm, None, constructor_args.as_ref())?;
+    }
+    executor.select_contract(&alias)?;
+
+    // Balances may go to any contract, so they are minted last
+    for asset in &session.assets {
+        executor.fund_asset(asset)?;
+    }
+
+    Ok(executor)
+}
+
+/// Build the authorization config from `--auth` and `--auth-file`
+fn load_auth_config(mode: Option<AuthMode>, file: Option<&Path>) -> Result<Option<AuthConfig>> {
+    let config = match mode {
+        None => None,
+        Some(AuthMode::MockAll) => Some(AuthConfig::MockAll),
+        Some(AuthMode::Recording) => Some(AuthConfig::Recording),
+        Some(AuthMode::Explicit) => {
+            let path = file.context("--auth explicit requires --auth-file")?;
+            let json = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read auth file: {:?}", path))?;
+            Some(AuthConfig::Explicit(parse_auth_entries(&json)?))
+        }
+    };
+    Ok(config)
+}
+
+/// Write the executor's ledger state to a snapshot file
+fn export_snapshot(executor: &ContractExecutor, path: &Path) -> Result<()> {
+    let snapshot = executor.ledger_snapshot();
+    save_snapshot(&snapshot, path)?;
+    println!(
+        "\nWrote {} ledger entries to {:?}",
+        snapshot.ledger_entries.len(),
+        path
+    );
+    Ok(())
+}
+
 /// Execute the optimize command
 pub fn optimize(args: OptimizeArgs) -> Result<()> {
     println!(
@@ -145,13 +501,18 @@ pub fn optimize(args: OptimizeArgs) -> Result<()> {
         args.function.clone()
     };
 
-    let mut executor = ContractExecutor::new(wasm_bytes)?;
+    // Suggestions are based on the costs of the contract as built
+    let mut executor = build_executor(&args.contract, &wasm_bytes, &args.session, Hooks::None)?;
 
     if let Some(storage_json) = &args.storage {
         let storage = parse_storage(storage_json)?;
         executor.set_initial_storage(storage)?;
     }
 
+    if let Some(auth) = load_auth_config(args.session.auth, args.session.auth_file.as_deref())? {
+        executor.set_auth(auth)?;
+    }
+
     let mut optimizer = crate::profiler::analyzer::GasOptimizer::new(executor);
 
     println!("\nAnalyzing {} function(s)...", functions_to_analyze.len());
@@ -189,3 +550,79 @@ pub fn optimize(args: OptimizeArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Execute the coverage command
+pub fn coverage(args: CoverageArgs) -> Result<()> {
+    println!("Measuring coverage of contract: {:?}", args.contract);
+
+    let wasm_bytes = fs::read(&args.contract)
+        .with_context(|| format!("Failed to read WASM file: {:?}", args.contract))?;
+
+    println!("Contract loaded successfully ({} bytes)", wasm_bytes.len());
+
+    let call_args: Vec<Option<&str>> = match args.args.len() {
+        0 => vec![None; args.function.len()],
+        1 => vec![Some(args.args[0].as_str()); args.function.len()],
+        n if n == args.function.len() => args.args.iter().map(|a| Some(a.as_str())).collect(),
+        n => {
+            return Err(DebuggerError::InvalidArguments(format!(
+                "Got {} --args for {} calls; give them once for every call or once per call",
+                n,
+                args.function.len()
+            ))
+            .into())
+        }
+    };
+    for json in call_args.iter().flatten() {
+        parse_args(json)?;
+    }
+
+    let mut executor = build_executor(&args.contract, &wasm_bytes, &args.session, Hooks::Coverage)?;
+
+    if let Some(storage_json) = &args.storage {
+        let storage = parse_storage(storage_json)?;
+        executor.set_initial_storage(storage)?;
+    }
+
+    if let Some(auth) = load_auth_config(args.session.auth, args.session.auth_file.as_deref())? {
+        executor.set_auth(auth)?;
+    }
+
+    let mut engine = DebuggerEngine::new(executor, vec![])?;
+    let mut collector = CoverageCollector::new();
+
+    println!("\nRunning {} call(s)...", args.function.len());
+    for (function, call_args) in args.function.iter().zip(call_args) {
+        match engine.execute(function, call_args) {
+            Ok(result) => {
+                println!("  {} => {}", function, result);
+                collector.record(&result);
+            }
+            Err(e) => eprintln!("  Warning: Call to {} failed: {}", function, e),
+        }
+    }
+
+    let report = collector.report(engine.executor().contracts());
+    let (found, hit) = report.line_totals();
+    println!(
+        "\nCovered {} of {} lines in {} file(s) over {} call(s)",
+        hit,
+        found,
+        report.files.len(),
+        collector.invocations()
+    );
+
+    let output = match args.format {
+        CoverageFormat::Lcov => report.to_lcov(),
+        CoverageFormat::Cobertura => report.to_cobertura(),
+    };
+    if let Some(output_path) = &args.output {
+        fs::write(output_path, &output)
+            .with_context(|| format!("Failed to write report to: {:?}", output_path))?;
+        println!("Coverage report written to: {:?}", output_path);
+    } else {
+        println!("\n{}", output);
+    }
+
+    Ok(())
+}
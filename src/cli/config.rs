@@ -0,0 +1,130 @@
+use crate::runtime::assets::AssetConfig;
+use crate::runtime::ledger::LedgerConfig;
+use crate::Result;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Session configuration loaded from a TOML file passed with `--config`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Ledger context for the session
+    #[serde(default)]
+    pub ledger: LedgerConfig,
+    /// Named accounts, usable as addresses in arguments and asset balances
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    /// Stellar Asset Contracts deployed before any contract
+    #[serde(default)]
+    pub assets: Vec<AssetConfig>,
+    /// Additional contracts registered before the one being debugged
+    #[serde(default)]
+    pub contracts: Vec<ContractEntry>,
+}
+
+/// A contract to register in the session, from a `[[contracts]]` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContractEntry {
+    /// Name usable in place of the contract address in arguments
+    pub alias: String,
+    /// Path to the WASM file, relative to the config file
+    pub wasm: PathBuf,
+    /// Fixed contract address; generated when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Constructor arguments, as for `--args`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constructor_args: Option<Json>,
+}
+
+impl SessionConfig {
+    /// Load a session config, or the defaults when no file is given
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        let mut config: Self =
+            toml::from_str(&text).with_context(|| format!("Invalid config file: {:?}", path))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for contract in &mut config.contracts {
+            contract.wasm = base.join(&contract.wasm);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ledger_section() {
+        let config: SessionConfig = toml::from_str(
+            r#"
+            [ledger]
+            sequence_number = 1000
+            timestamp = 1700000000
+            network_passphrase = "Test SDF Network ; September 2015"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.ledger.sequence_number, Some(1000));
+        assert_eq!(config.ledger.timestamp, Some(1_700_000_000));
+        assert!(config.ledger.max_entry_ttl.is_none());
+        assert!(toml::from_str::<SessionConfig>("[ledger]\nsequence = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_contracts() {
+        let config: SessionConfig = toml::from_str(
+            r#"
+            [[contracts]]
+            alias = "token"
+            wasm = "token.wasm"
+
+            [[contracts]]
+            alias = "amm"
+            wasm = "amm.wasm"
+            address = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM"
+            constructor_args = ["token", 30]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.contracts.len(), 2);
+        assert!(config.contracts[0].address.is_none());
+        assert_eq!(
+            config.contracts[1].constructor_args,
+            Some(serde_json::json!(["token", 30]))
+        );
+    }
+
+    #[test]
+    fn test_parse_accounts_and_assets() {
+        let config: SessionConfig = toml::from_str(
+            r#"
+            accounts = ["alice", "bob"]
+
+            [[assets]]
+            alias = "usdc"
+            asset = "USDC:alice"
+            balances = { alice = 1000, bob = "250" }
+            authorized = { bob = false }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.accounts, vec!["alice", "bob"]);
+        assert_eq!(config.assets[0].balances.len(), 2);
+        assert_eq!(config.assets[0].authorized.get("bob"), Some(&false));
+        assert!(config.assets[0].admin.is_none());
+    }
+}
@@ -1,4 +1,11 @@
 pub mod args;
 pub mod commands;
+pub mod config;
+pub mod saved_session;
 
-pub use args::{Cli, Commands, InspectArgs, InteractiveArgs, OptimizeArgs, RunArgs};
+pub use args::{
+    AuthMode, Cli, Commands, CoverageArgs, CoverageFormat, InspectArgs, InteractiveArgs,
+    LedgerArgs, OptimizeArgs, RunArgs, SessionArgs,
+};
+pub use config::{ContractEntry, SessionConfig};
+pub use saved_session::{SavedSession, SavedSessions};
@@ -0,0 +1,195 @@
+use crate::debugger::breakpoint::Breakpoint;
+use crate::debugger::stepper::StepGranularity;
+use crate::debugger::watch::Watch;
+use crate::Result;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Where interactive sessions are saved, relative to the project directory
+pub const SESSION_FILE: &str = ".soroban-debug/session.toml";
+
+/// Sessions saved in a project, by the SHA-256 of the contract's WASM in hex
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedSessions {
+    #[serde(default)]
+    pub contracts: BTreeMap<String, SavedSession>,
+}
+
+/// What an interactive session set up for a contract: breakpoints with
+/// their conditions, logpoints, watches and options, as typed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedSession {
+    /// WASM file the session debugged
+    pub wasm: PathBuf,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakpoints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logpoints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watches: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_mode: Option<String>,
+}
+
+impl SavedSessions {
+    /// Load the saved sessions, or none if the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session file: {:?}", path))?;
+        let sessions =
+            toml::from_str(&text).with_context(|| format!("Invalid session file: {:?}", path))?;
+        Ok(sessions)
+    }
+
+    /// Write the sessions, creating the file's directory if needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        }
+        let text = toml::to_string_pretty(self)?;
+        fs::write(path, text).with_context(|| format!("Failed to write session file: {:?}", path))
+    }
+
+    /// The session saved for a contract, and whether it was saved for
+    /// another build of it: one from the same file with another hash
+    pub fn find(&self, wasm: &Path, hash: &str) -> Option<(&SavedSession, bool)> {
+        if let Some(session) = self.contracts.get(hash) {
+            return Some((session, false));
+        }
+        self.contracts
+            .values()
+            .find(|session| same_file(&session.wasm, wasm))
+            .map(|session| (session, true))
+    }
+
+    /// Save the session of a contract, replacing those of other builds of
+    /// it. An empty session is removed.
+    pub fn insert(&mut self, hash: &str, session: SavedSession) {
+        self.contracts
+            .retain(|key, saved| key != hash && !same_file(&saved.wasm, &session.wasm));
+        if !session.is_empty() {
+            self.contracts.insert(hash.to_string(), session);
+        }
+    }
+}
+
+impl SavedSession {
+    /// Whether the session has nothing to restore
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+            && self.logpoints.is_empty()
+            && self.watches.is_empty()
+            && self.step_mode.is_none()
+    }
+
+    /// Breakpoints and logpoints, leaving out any that no longer parse
+    pub fn parse_breakpoints(&self) -> Vec<Breakpoint> {
+        let breakpoints = self.breakpoints.iter().map(|s| (s, s.parse()));
+        let logpoints = self.logpoints.iter().map(|s| (s, Breakpoint::logpoint(s)));
+        breakpoints
+            .chain(logpoints)
+            .filter_map(|(s, parsed)| {
+                parsed
+                    .map_err(|e| warn!("Ignoring saved breakpoint '{}': {}", s, e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Watches, leaving out any that no longer parse
+    pub fn parse_watches(&self) -> Vec<Watch> {
+        self.watches
+            .iter()
+            .filter_map(|s| {
+                s.parse()
+                    .map_err(|e| warn!("Ignoring saved watch '{}': {}", s, e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Step mode, if one was saved and is still known
+    pub fn step_granularity(&self) -> Option<StepGranularity> {
+        self.step_mode.as_deref().and_then(|mode| mode.parse().ok())
+    }
+}
+
+/// SHA-256 of a WASM module in hex, which saved sessions are keyed by
+pub fn wasm_hash(wasm: &[u8]) -> String {
+    Sha256::digest(wasm)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Whether two paths name the same file, comparing them as given when
+/// either does not exist
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_saved_session() {
+        let mut sessions = SavedSessions::default();
+        let session = SavedSession {
+            wasm: PathBuf::from("missing/token.wasm"),
+            breakpoints: vec!["transfer if args.amount > 10".to_string()],
+            watches: vec!["persistent Balance(alice)".to_string()],
+            ..SavedSession::default()
+        };
+        sessions.insert("aa", session.clone());
+
+        let text = toml::to_string_pretty(&sessions).unwrap();
+        let sessions: SavedSessions = toml::from_str(&text).unwrap();
+        assert_eq!(
+            sessions.find(Path::new("missing/token.wasm"), "aa"),
+            Some((&session, false))
+        );
+        assert_eq!(
+            sessions.find(Path::new("missing/token.wasm"), "bb"),
+            Some((&session, true))
+        );
+        assert!(sessions.find(Path::new("missing/amm.wasm"), "bb").is_none());
+        assert_eq!(session.parse_breakpoints().len(), 1);
+        assert_eq!(session.parse_watches().len(), 1);
+
+        // Saving a rebuilt contract replaces its old session
+        let mut sessions = sessions;
+        sessions.insert("bb", session.clone());
+        assert_eq!(sessions.contracts.keys().collect::<Vec<_>>(), ["bb"]);
+        sessions.insert(
+            "bb",
+            SavedSession {
+                wasm: session.wasm.clone(),
+                ..SavedSession::default()
+            },
+        );
+        assert!(sessions.contracts.is_empty());
+    }
+
+    #[test]
+    fn test_wasm_hash() {
+        assert_eq!(
+            wasm_hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -1,36 +1,401 @@
-use std::collections::HashSet;
+use crate::debugger::condition::{Condition, Message};
+use crate::runtime::contracts::ContractRegistry;
+use crate::runtime::host_functions::{HostFunction, HOST_FUNCTIONS};
+use crate::utils::source_map::SourceLocation;
+use crate::DebuggerError;
+use soroban_sdk::xdr::ScVal;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a breakpoint applies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointKind {
+    /// Entry of a contract function, by name
+    Function(String),
+    /// Any call to a host function, by its name in the host interface
+    /// (`host:put_contract_data`)
+    Host(String),
+    /// Authorization required from an address or alias (`auth:alice`)
+    Auth(String),
+    /// Publication of an event with a topic, by symbol or string
+    /// (`event:transfer`)
+    Event(String),
+    /// Call into another contract, by address or alias (`*` for any), and
+    /// optionally one of its functions (`call:token::transfer`)
+    Call {
+        contract: String,
+        function: Option<String>,
+    },
+    /// Start of a source line, in a file given by its path or trailing
+    /// components of it (`src/lib.rs:142`), for contracts built with debug
+    /// info
+    Source { file: String, line: usize },
+    /// The invocation about to fail in some way (`catch:error`)
+    Catch(Failure),
+}
+
+/// Ways an invocation fails that catch breakpoints pause before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// A contract returning an error, or failing with one
+    Error,
+    /// A contract trapping, which is how Rust contracts panic
+    Panic,
+    /// CPU instructions or memory exceeding the budget limits
+    Budget,
+}
+
+impl FromStr for Failure {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Failure::Error),
+            "panic" => Ok(Failure::Panic),
+            "budget" => Ok(Failure::Budget),
+            _ => Err(DebuggerError::BreakpointError(format!(
+                "unknown failure '{}' (expected error, panic or budget)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Failure::Error => "error",
+            Failure::Panic => "panic",
+            Failure::Budget => "budget",
+        })
+    }
+}
+
+impl BreakpointKind {
+    /// Whether a host function call, with its arguments, hits the breakpoint
+    pub fn matches_host_call(
+        &self,
+        host: &HostFunction,
+        args: &[ScVal],
+        contracts: &ContractRegistry,
+    ) -> bool {
+        let is_address = |target: &str, arg: Option<&ScVal>| match arg {
+            Some(ScVal::Address(address)) => {
+                target == "*" || contracts.resolve(target).as_ref() == Some(address)
+            }
+            _ => false,
+        };
+        match self {
+            BreakpointKind::Function(_)
+            | BreakpointKind::Source { .. }
+            | BreakpointKind::Catch(_) => false,
+            BreakpointKind::Host(name) => host.name == name,
+            BreakpointKind::Auth(address) => {
+                matches!(host.name, "require_auth" | "require_auth_for_args")
+                    && is_address(address, args.first())
+            }
+            BreakpointKind::Event(topic) => {
+                host.name == "contract_event"
+                    && matches!(args.first(), Some(ScVal::Vec(Some(topics)))
+                        if topics.iter().any(|t| name_of(t).as_ref() == Some(topic)))
+            }
+            BreakpointKind::Call { contract, function } => {
+                matches!(host.name, "call" | "try_call")
+                    && is_address(contract, args.first())
+                    && function
+                        .as_ref()
+                        .is_none_or(|f| args.get(1).and_then(name_of).as_ref() == Some(f))
+            }
+        }
+    }
+}
+
+impl BreakpointKind {
+    /// Whether execution reaching the start of a source line hits the
+    /// breakpoint
+    pub fn matches_line(&self, location: &SourceLocation) -> bool {
+        match self {
+            BreakpointKind::Source { file, line } => location.is_at(file, *line),
+            _ => false,
+        }
+    }
+}
+
+/// Text of a symbol or string value
+fn name_of(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Symbol(s) => Some(s.to_utf8_string_lossy()),
+        ScVal::String(s) => Some(s.to_utf8_string_lossy()),
+        _ => None,
+    }
+}
+
+impl FromStr for BreakpointKind {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let error = |message: String| DebuggerError::BreakpointError(message);
+        if let Some((file, line)) = s.rsplit_once(':') {
+            if let (false, Ok(line)) = (file.is_empty(), line.parse::<usize>()) {
+                return Ok(BreakpointKind::Source {
+                    file: file.to_string(),
+                    line,
+                });
+            }
+        }
+        let Some((kind, target)) = s.split_once(':') else {
+            return Ok(BreakpointKind::Function(s.to_string()));
+        };
+        if target.is_empty() {
+            return Err(error(format!("missing target after '{}:'", kind)));
+        }
+        Ok(match kind {
+            "host" => {
+                if !HOST_FUNCTIONS.iter().any(|f| f.name == target) {
+                    return Err(error(format!("unknown host function '{}'", target)));
+                }
+                BreakpointKind::Host(target.to_string())
+            }
+            "auth" => BreakpointKind::Auth(target.to_string()),
+            "catch" => BreakpointKind::Catch(target.parse()?),
+            "event" => BreakpointKind::Event(target.to_string()),
+            "call" => match target.split_once("::") {
+                Some((contract, function)) if !contract.is_empty() && !function.is_empty() => {
+                    BreakpointKind::Call {
+                        contract: contract.to_string(),
+                        function: Some(function.to_string()),
+                    }
+                }
+                Some(_) => {
+                    return Err(error(format!(
+                        "expected 'call:<contract>[::<function>]', got '{}'",
+                        s
+                    )))
+                }
+                None => BreakpointKind::Call {
+                    contract: target.to_string(),
+                    function: None,
+                },
+            },
+            _ => {
+                return Err(error(format!(
+                "unknown breakpoint kind '{}' (expected host, auth, event, call, catch or <file>:<line>)",
+                kind
+            )))
+            }
+        })
+    }
+}
+
+impl fmt::Display for BreakpointKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakpointKind::Function(name) => write!(f, "{}", name),
+            BreakpointKind::Host(name) => write!(f, "host:{}", name),
+            BreakpointKind::Auth(address) => write!(f, "auth:{}", address),
+            BreakpointKind::Event(topic) => write!(f, "event:{}", topic),
+            BreakpointKind::Call { contract, function } => {
+                write!(f, "call:{}", contract)?;
+                if let Some(function) = function {
+                    write!(f, "::{}", function)?;
+                }
+                Ok(())
+            }
+            BreakpointKind::Source { file, line } => write!(f, "{}:{}", file, line),
+            BreakpointKind::Catch(failure) => write!(f, "catch:{}", failure),
+        }
+    }
+}
+
+/// A breakpoint at a function or host operation, pausing only where its
+/// condition holds if it has one. A logpoint prints its message there instead
+/// of pausing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+    pub condition: Option<Condition>,
+    pub log: Option<Message>,
+    /// Hits to let pass before pausing or logging
+    pub ignore: u32,
+    /// Times the breakpoint was reached with the condition holding
+    pub hits: u32,
+}
+
+impl Breakpoint {
+    /// A breakpoint at a function
+    pub fn new(function: &str) -> Self {
+        Self::of(BreakpointKind::Function(function.to_string()))
+    }
+
+    pub fn of(kind: BreakpointKind) -> Self {
+        Self {
+            kind,
+            condition: None,
+            log: None,
+            ignore: 0,
+            hits: 0,
+        }
+    }
+
+    /// Parse a logpoint, `<target> [if <condition>]: <message>`
+    pub fn logpoint(s: &str) -> Result<Self, DebuggerError> {
+        // The first colon followed by a space and outside a condition's
+        // strings ends the breakpoint; others belong to its target
+        let mut quoted = false;
+        let colon = s.char_indices().find(|&(i, c)| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ':' && !quoted && s[i + 1..].chars().next().is_none_or(char::is_whitespace)
+        });
+        let Some((colon, _)) = colon else {
+            return Err(DebuggerError::BreakpointError(format!(
+                "expected '<function>: <message>' in '{}'",
+                s
+            )));
+        };
+        let mut breakpoint: Breakpoint = s[..colon].parse()?;
+        breakpoint.log = Some(s[colon + 1..].parse()?);
+        Ok(breakpoint)
+    }
+
+    /// Name the breakpoint is listed and removed by
+    pub fn key(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// Whether the latest hit is one of those to let pass
+    pub fn ignored(&self) -> bool {
+        self.hits <= self.ignore
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = DebuggerError;
+
+    /// Parse `<target>` or `<target> if <condition>`, where the target is a
+    /// function name or a kind-prefixed host operation such as `host:<name>`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let (target, condition) = match s.split_once(char::is_whitespace) {
+            Some((target, rest)) => match rest.trim_start().strip_prefix("if") {
+                Some(condition) if condition.starts_with(char::is_whitespace) => {
+                    (target, Some(condition.parse::<Condition>()?))
+                }
+                _ => {
+                    return Err(DebuggerError::BreakpointError(format!(
+                        "expected 'if <condition>' after '{}'",
+                        target
+                    )))
+                }
+            },
+            None => (s, None),
+        };
+        if target.is_empty() {
+            return Err(DebuggerError::BreakpointError(
+                "missing function name".to_string(),
+            ));
+        }
+        Ok(Self {
+            condition,
+            ..Self::of(target.parse()?)
+        })
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        if let Some(log) = &self.log {
+            write!(f, ": {}", log)?;
+        }
+        Ok(())
+    }
+}
 
 /// Manages breakpoints during debugging
 pub struct BreakpointManager {
-    breakpoints: HashSet<String>,
+    breakpoints: BTreeMap<String, Breakpoint>,
 }
 
 impl BreakpointManager {
     /// Create a new breakpoint manager
     pub fn new() -> Self {
         Self {
-            breakpoints: HashSet::new(),
+            breakpoints: BTreeMap::new(),
         }
     }
 
     /// Add a breakpoint at a function name
     pub fn add(&mut self, function: &str) {
-        self.breakpoints.insert(function.to_string());
+        self.insert(Breakpoint::new(function));
+    }
+
+    /// Add a breakpoint, replacing any other at its target
+    pub fn insert(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.insert(breakpoint.key(), breakpoint);
     }
 
-    /// Remove a breakpoint
-    pub fn remove(&mut self, function: &str) -> bool {
-        self.breakpoints.remove(function)
+    /// Remove a breakpoint by its function name or kind-prefixed target
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.breakpoints.remove(key).is_some()
     }
 
-    /// Check if execution should break at this function
+    /// The breakpoint at a function, or a kind-prefixed target, if any
+    pub fn get(&self, key: &str) -> Option<&Breakpoint> {
+        self.breakpoints.get(key)
+    }
+
+    /// All breakpoints, by key
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.values()
+    }
+
+    /// Check if there is a breakpoint at this function. Its condition is
+    /// checked where the function is entered.
     pub fn should_break(&self, function: &str) -> bool {
-        self.breakpoints.contains(function)
+        self.breakpoints.contains_key(function)
     }
 
-    /// List all breakpoints
+    /// Count a hit of a breakpoint, returning it
+    pub fn hit(&mut self, key: &str) -> Option<&Breakpoint> {
+        let breakpoint = self.breakpoints.get_mut(key)?;
+        breakpoint.hits += 1;
+        Some(breakpoint)
+    }
+
+    /// Let the next hits of a breakpoint pass, counting from those it
+    /// already had
+    pub fn set_ignore(&mut self, key: &str, count: u32) -> bool {
+        match self.breakpoints.get_mut(key) {
+            Some(breakpoint) => {
+                breakpoint.ignore = breakpoint.hits + count;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// List all breakpoints with their hit counts
     pub fn list(&self) -> Vec<String> {
-        self.breakpoints.iter().cloned().collect()
+        self.breakpoints
+            .values()
+            .map(|b| {
+                let kind = if b.log.is_some() { "logpoint, " } else { "" };
+                let mut entry = format!("{} ({}hits: {})", b, kind, b.hits);
+                if b.ignore > b.hits {
+                    entry.insert_str(
+                        entry.len() - 1,
+                        &format!(", ignoring next {}", b.ignore - b.hits),
+                    );
+                }
+                entry
+            })
+            .collect()
     }
 
     /// Clear all breakpoints
@@ -58,6 +423,7 @@ impl Default for BreakpointManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use soroban_sdk::xdr::{Hash, ScAddress};
 
     #[test]
     fn test_add_breakpoint() {
@@ -82,7 +448,90 @@ mod tests {
         manager.add("mint");
         let list = manager.list();
         assert_eq!(list.len(), 2);
-        assert!(list.contains(&"transfer".to_string()));
-        assert!(list.contains(&"mint".to_string()));
+        assert!(list.contains(&"transfer (hits: 0)".to_string()));
+        assert!(list.contains(&"mint (hits: 0)".to_string()));
+    }
+
+    #[test]
+    fn test_parse_breakpoint() {
+        let bp: Breakpoint = "transfer if args.amount > 1000".parse().unwrap();
+        assert_eq!(bp.kind, BreakpointKind::Function("transfer".to_string()));
+        assert!(bp.condition.is_some());
+        assert_eq!(bp.to_string(), "transfer if args.amount > 1000");
+
+        let bp: Breakpoint = "mint".parse().unwrap();
+        assert_eq!(bp, Breakpoint::new("mint"));
+        assert!("mint when x".parse::<Breakpoint>().is_err());
+        assert!("mint if".parse::<Breakpoint>().is_err());
+
+        let bp: Breakpoint = "catch:error if args.code == 3".parse().unwrap();
+        assert_eq!(bp.kind, BreakpointKind::Catch(Failure::Error));
+        assert_eq!(bp.key(), "catch:error");
+        assert!("catch:overflow".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn test_hits_and_logpoints() {
+        let mut manager = BreakpointManager::new();
+        manager.insert(
+            Breakpoint::logpoint("transfer if args.to != \"a:b\": sent {args.amount}").unwrap(),
+        );
+        assert!(manager.set_ignore("transfer", 1));
+        assert!(manager.hit("transfer").unwrap().ignored());
+        assert!(!manager.hit("transfer").unwrap().ignored());
+        assert_eq!(
+            manager.list(),
+            vec!["transfer if args.to != \"a:b\": sent {args.amount} (logpoint, hits: 2)"]
+        );
+        manager.set_ignore("transfer", 3);
+        assert!(manager.list()[0].ends_with("(logpoint, hits: 2, ignoring next 3)"));
+        assert!(Breakpoint::logpoint("transfer").is_err());
+    }
+
+    #[test]
+    fn test_host_breakpoints() {
+        let host = |name: &str| HOST_FUNCTIONS.iter().find(|f| f.name == name).unwrap();
+        let contracts = ContractRegistry::new();
+        let token = ScAddress::Contract(Hash([7; 32]));
+        let address = ScVal::Address(token.clone());
+        let symbol = |s: &str| ScVal::Symbol(s.try_into().unwrap());
+
+        let bp: Breakpoint = "host:put_contract_data if args.t == \"persistent\""
+            .parse()
+            .unwrap();
+        assert_eq!(bp.key(), "host:put_contract_data");
+        assert!(bp
+            .kind
+            .matches_host_call(host("put_contract_data"), &[], &contracts));
+        assert!(!bp
+            .kind
+            .matches_host_call(host("get_contract_data"), &[], &contracts));
+
+        let event: BreakpointKind = "event:transfer".parse().unwrap();
+        let topics = ScVal::Vec(Some(vec![symbol("transfer")].try_into().unwrap()));
+        assert!(event.matches_host_call(host("contract_event"), &[topics], &contracts));
+
+        let call: BreakpointKind = format!("call:{}::transfer", token).parse().unwrap();
+        let args = [address.clone(), symbol("transfer")];
+        assert!(call.matches_host_call(host("call"), &args, &contracts));
+        assert!(!call.matches_host_call(host("call"), &[address, symbol("mint")], &contracts));
+        assert_eq!(call.to_string(), format!("call:{}::transfer", token));
+
+        assert!("host:no_such_function".parse::<BreakpointKind>().is_err());
+        assert!("watch:x".parse::<BreakpointKind>().is_err());
+        assert!("call:token::".parse::<BreakpointKind>().is_err());
+
+        let source: BreakpointKind = "src/lib.rs:42".parse().unwrap();
+        let at = |file: &str, line| SourceLocation {
+            file: file.to_string(),
+            line,
+            column: 5,
+        };
+        assert!(source.matches_line(&at("/work/token/src/lib.rs", 42)));
+        assert!(!source.matches_line(&at("/work/token/src/lib.rs", 43)));
+        assert!(!source.matches_line(&at("/work/token/xsrc/lib.rs", 42)));
+        assert_eq!(source.to_string(), "src/lib.rs:42");
+        let logpoint = Breakpoint::logpoint("call:*::transfer: {args.func}").unwrap();
+        assert_eq!(logpoint.key(), "call:*::transfer");
     }
 }
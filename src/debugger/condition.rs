@@ -0,0 +1,845 @@
+use crate::inspector::budget::BudgetInfo;
+use crate::runtime::storage::Durability;
+use crate::{DebuggerError, Result};
+use serde_json::Value as Json;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a condition can read where its breakpoint was reached
+pub trait Scope {
+    /// Decoded arguments of the function being entered, in order, named by
+    /// the contract spec or by position
+    fn args(&self) -> &[(String, Json)];
+
+    /// Budget consumed so far
+    fn budget(&self) -> BudgetInfo;
+
+    /// Decoded value stored under a key by the contract being entered, if
+    /// any. Without a durability, every kind of storage is searched.
+    fn storage(&self, durability: Option<Durability>, key: &Json) -> Result<Option<Json>>;
+
+    /// Strkey of a session alias, such as a named account
+    fn address(&self, alias: &str) -> Option<String>;
+}
+
+/// A breakpoint condition in the debugger's expression language.
+///
+/// Expressions compare arguments (`args.amount`, `args[0]`), stored values
+/// (`storage[Balance(alice)]`, `storage.instance[Counter]`) and the budget
+/// (`budget.cpu`, `budget.memory`) with literals, combined with `&&`, `||`
+/// and `!`. Storage keys are written like Rust values: a contract enum
+/// variant with its fields in parentheses, or a plain name, number or string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Json),
+    /// A quoted string, which compares as text even if it holds digits
+    Text(String),
+    Args(Vec<Segment>),
+    Budget(BudgetField),
+    Storage(Option<Durability>, Json, Vec<Segment>),
+    Not(Box<Expr>),
+    Compare(Box<Expr>, Op, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BudgetField {
+    Cpu,
+    Memory,
+    CpuLimit,
+    MemoryLimit,
+}
+
+impl BudgetField {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cpu" => Some(BudgetField::Cpu),
+            "memory" | "mem" => Some(BudgetField::Memory),
+            "cpu_limit" => Some(BudgetField::CpuLimit),
+            "memory_limit" | "mem_limit" => Some(BudgetField::MemoryLimit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds in a scope
+    pub fn evaluate(&self, scope: &dyn Scope) -> Result<bool> {
+        Ok(truthy(&eval(&self.expr, scope)?))
+    }
+}
+
+impl FromStr for Condition {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(&format!("unexpected {}", token)));
+        }
+        Ok(Self {
+            source: s.trim().to_string(),
+            expr,
+        })
+    }
+}
+
+/// A storage key written as in conditions, such as `Balance(alice)`, as the
+/// JSON the contract spec encodes
+pub fn parse_key(s: &str) -> std::result::Result<Json, DebuggerError> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let key = parser.key()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error(&format!("unexpected {}", token)));
+    }
+    Ok(key)
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A logpoint message: text with expressions in braces, such as
+/// `sent {args.amount} to {args.to}`. `{{` and `}}` stand for braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Value(Expr),
+}
+
+impl Message {
+    /// The message with its expressions evaluated in a scope. Strings are
+    /// shown without quotes, other values as JSON, and expressions that
+    /// cannot be evaluated as the reason.
+    pub fn render(&self, scope: &dyn Scope) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Value(expr) => match eval(expr, scope) {
+                    Ok(Json::String(s)) => out.push_str(&s),
+                    Ok(value) => out.push_str(&value.to_string()),
+                    Err(e) => out.push_str(&format!("<{}>", e)),
+                },
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Message {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let error = |message: &str| {
+            DebuggerError::BreakpointError(format!("{} in message '{}'", message, s))
+        };
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut source = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => source.push(c),
+                            None => return Err(error("unterminated '{'")),
+                        }
+                    }
+                    let condition: Condition = source.parse()?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Value(condition.expr));
+                }
+                '}' => return Err(error("unmatched '}'")),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self {
+            source: s.trim().to_string(),
+            parts,
+        })
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Dot,
+    Comma,
+    Open(char),
+    Close(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Number(s) => write!(f, "'{}'", s),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Op(op) => write!(f, "'{}'", op.symbol()),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Dot => write!(f, "'.'"),
+            Token::Comma => write!(f, "','"),
+            Token::Open(c) | Token::Close(c) => write!(f, "'{}'", c),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> std::result::Result<Vec<Token>, DebuggerError> {
+    let error = |message: String| DebuggerError::BreakpointError(message);
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('!', _) => (Token::Not, 1),
+            ('.', _) => (Token::Dot, 1),
+            (',', _) => (Token::Comma, 1),
+            ('(' | '[', _) => (Token::Open(c), 1),
+            (')' | ']', _) => (Token::Close(c), 1),
+            ('"', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .ok_or_else(|| error(format!("unterminated string in '{}'", s)))?;
+                let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Str(text), end + 2)
+            }
+            (c, next)
+                if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let len = 1 + chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || **c == '_' || **c == '.')
+                    .count();
+                let text: String = chars[i..i + len].iter().filter(|&&c| c != '_').collect();
+                (Token::Number(text), len)
+            }
+            (c, _) if c.is_alphanumeric() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                (Token::Ident(chars[i..i + len].iter().collect()), len)
+            }
+            (c, _) => return Err(error(format!("unexpected '{}' in '{}'", c, s))),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> std::result::Result<(), DebuggerError> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", token)))
+        }
+    }
+
+    fn error(&self, message: &str) -> DebuggerError {
+        let at = match self.peek() {
+            Some(token) => format!("at {}", token),
+            None => "at end of condition".to_string(),
+        };
+        DebuggerError::BreakpointError(format!("{} {}", message, at))
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr, DebuggerError> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, DebuggerError> {
+        let mut expr = self.not()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> std::result::Result<Expr, DebuggerError> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        let left = self.operand()?;
+        if let Some(&Token::Op(op)) = self.peek() {
+            self.pos += 1;
+            let right = self.operand()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> std::result::Result<Expr, DebuggerError> {
+        match self.next() {
+            Some(Token::Open('(')) => {
+                let expr = self.or()?;
+                self.expect(Token::Close(')'))?;
+                Ok(expr)
+            }
+            Some(Token::Number(n)) => Ok(Expr::Literal(number(&n)?)),
+            Some(Token::Str(s)) => Ok(Expr::Text(s)),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Json::Bool(true))),
+                "false" => Ok(Expr::Literal(Json::Bool(false))),
+                "null" => Ok(Expr::Literal(Json::Null)),
+                "args" => Ok(Expr::Args(self.segments()?)),
+                "budget" => {
+                    self.expect(Token::Dot)?;
+                    match self.next() {
+                        Some(Token::Ident(name)) => match BudgetField::parse(&name) {
+                            Some(field) => Ok(Expr::Budget(field)),
+                            None => Err(DebuggerError::BreakpointError(format!(
+                                "unknown budget field '{}' (expected cpu, memory, cpu_limit or memory_limit)",
+                                name
+                            ))),
+                        },
+                        _ => Err(self.error("expected a budget field")),
+                    }
+                }
+                "storage" => {
+                    let durability = if self.eat(&Token::Dot) {
+                        match self.next() {
+                            Some(Token::Ident(d)) => Some(d.parse::<Durability>()?),
+                            _ => return Err(self.error("expected a durability")),
+                        }
+                    } else {
+                        None
+                    };
+                    self.expect(Token::Open('['))?;
+                    let key = self.key()?;
+                    self.expect(Token::Close(']'))?;
+                    Ok(Expr::Storage(durability, key, self.segments()?))
+                }
+                _ => Err(DebuggerError::BreakpointError(format!(
+                    "unknown name '{}' (expected args, storage or budget)",
+                    name
+                ))),
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+
+    /// Field and index accesses following a value
+    fn segments(&mut self) -> std::result::Result<Vec<Segment>, DebuggerError> {
+        let mut segments = Vec::new();
+        loop {
+            if self.eat(&Token::Dot) {
+                match self.next() {
+                    Some(Token::Ident(field) | Token::Number(field)) => {
+                        segments.push(Segment::Field(field))
+                    }
+                    _ => return Err(self.error("expected a field name")),
+                }
+            } else if self.eat(&Token::Open('[')) {
+                match self.next() {
+                    Some(Token::Number(n)) => {
+                        let index = n
+                            .parse()
+                            .map_err(|_| self.error(&format!("invalid index {}", n)))?;
+                        segments.push(Segment::Index(index));
+                    }
+                    Some(Token::Str(field)) => segments.push(Segment::Field(field)),
+                    _ => return Err(self.error("expected an index")),
+                }
+                self.expect(Token::Close(']'))?;
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    /// A storage key: `Variant(fields..)`, a name, a number or a string, as
+    /// the JSON the contract spec encodes
+    fn key(&mut self) -> std::result::Result<Json, DebuggerError> {
+        match self.next() {
+            Some(Token::Number(n)) => number(&n),
+            Some(Token::Str(s)) => Ok(Json::String(s)),
+            Some(Token::Ident(name)) => {
+                if !self.eat(&Token::Open('(')) {
+                    return Ok(Json::String(name));
+                }
+                let mut fields = Vec::new();
+                if !self.eat(&Token::Close(')')) {
+                    loop {
+                        fields.push(self.key()?);
+                        if self.eat(&Token::Close(')')) {
+                            break;
+                        }
+                        self.expect(Token::Comma)?;
+                    }
+                }
+                let value = match fields.len() {
+                    1 => fields.remove(0),
+                    _ => Json::Array(fields),
+                };
+                Ok(Json::Object([(name, value)].into_iter().collect()))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a storage key"))
+            }
+        }
+    }
+}
+
+fn number(text: &str) -> std::result::Result<Json, DebuggerError> {
+    // Numbers beyond 64 bits stay strings, like decoded 128-bit values
+    if let Ok(n) = text.parse::<i64>() {
+        return Ok(Json::from(n));
+    }
+    if let Ok(n) = text.parse::<u64>() {
+        return Ok(Json::from(n));
+    }
+    if text.parse::<i128>().is_ok() || text.parse::<u128>().is_ok() {
+        return Ok(Json::String(text.to_string()));
+    }
+    text.parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(Json::Number)
+        .ok_or_else(|| DebuggerError::BreakpointError(format!("invalid number '{}'", text)))
+}
+
+fn eval(expr: &Expr, scope: &dyn Scope) -> Result<Json> {
+    Ok(match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Text(text) => Json::String(text.clone()),
+        Expr::Args(segments) => {
+            let args = scope.args();
+            let (first, rest) = match segments.split_first() {
+                Some((first, rest)) => (first, rest),
+                None => {
+                    return Ok(Json::Array(
+                        args.iter().map(|(_, value)| value.clone()).collect(),
+                    ))
+                }
+            };
+            let arg = match first {
+                Segment::Field(name) => args.iter().find(|(n, _)| n == name),
+                Segment::Index(index) => args.get(*index),
+            };
+            match arg {
+                Some((_, value)) => access(value, rest),
+                None => {
+                    let names: Vec<&str> = args.iter().map(|(name, _)| name.as_str()).collect();
+                    return Err(DebuggerError::BreakpointError(format!(
+                        "no argument {} (arguments: {})",
+                        segment_name(first),
+                        names.join(", ")
+                    ))
+                    .into());
+                }
+            }
+        }
+        Expr::Budget(field) => {
+            let budget = scope.budget();
+            Json::from(match field {
+                BudgetField::Cpu => budget.cpu_instructions,
+                BudgetField::Memory => budget.memory_bytes,
+                BudgetField::CpuLimit => budget.cpu_limit,
+                BudgetField::MemoryLimit => budget.memory_limit,
+            })
+        }
+        Expr::Storage(durability, key, segments) => match scope.storage(*durability, key)? {
+            Some(value) => access(&value, segments),
+            None => Json::Null,
+        },
+        Expr::Not(inner) => Json::Bool(!truthy(&eval(inner, scope)?)),
+        Expr::And(left, right) => {
+            Json::Bool(truthy(&eval(left, scope)?) && truthy(&eval(right, scope)?))
+        }
+        Expr::Or(left, right) => {
+            Json::Bool(truthy(&eval(left, scope)?) || truthy(&eval(right, scope)?))
+        }
+        Expr::Compare(left, op, right) => {
+            let text = matches!(**left, Expr::Text(_)) || matches!(**right, Expr::Text(_));
+            let (left, right) = (eval(left, scope)?, eval(right, scope)?);
+            Json::Bool(compare(&left, *op, &right, text, scope)?)
+        }
+    })
+}
+
+fn segment_name(segment: &Segment) -> String {
+    match segment {
+        Segment::Field(name) => format!("'{}'", name),
+        Segment::Index(index) => index.to_string(),
+    }
+}
+
+/// Fields of objects and items of arrays; anything missing is null
+fn access(value: &Json, segments: &[Segment]) -> Json {
+    let mut value = value;
+    for segment in segments {
+        let next = match (segment, value) {
+            (Segment::Field(name), Json::Object(map)) => map.get(name),
+            (Segment::Index(index), Json::Array(items)) => items.get(*index),
+            (Segment::Field(name), Json::Array(items)) => {
+                name.parse::<usize>().ok().and_then(|i| items.get(i))
+            }
+            _ => None,
+        };
+        match next {
+            Some(next) => value = next,
+            None => return Json::Null,
+        }
+    }
+    value.clone()
+}
+
+fn truthy(value: &Json) -> bool {
+    match value {
+        Json::Null => false,
+        Json::Bool(b) => *b,
+        _ => true,
+    }
+}
+
+/// A number, from a JSON number or a decimal string such as a decoded
+/// 128-bit value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Int(i128),
+    /// Only integers above `i128::MAX`
+    UInt(u128),
+    Float(f64),
+}
+
+impl Number {
+    fn of(value: &Json) -> Option<Self> {
+        let text = match value {
+            Json::Number(n) => n.to_string(),
+            Json::String(s) => s.clone(),
+            _ => return None,
+        };
+        if let Ok(n) = text.parse::<i128>() {
+            return Some(Number::Int(n));
+        }
+        if let Ok(n) = text.parse::<u128>() {
+            return Some(Number::UInt(n));
+        }
+        match value {
+            Json::Number(n) => n.as_f64().map(Number::Float),
+            _ => None,
+        }
+    }
+
+    fn cmp(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            (Number::UInt(a), Number::UInt(b)) => Some(a.cmp(&b)),
+            (Number::Int(_), Number::UInt(_)) => Some(Ordering::Less),
+            (Number::UInt(_), Number::Int(_)) => Some(Ordering::Greater),
+            (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::UInt(n) => n as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+/// Compare two values: as numbers if both are, unless `text` says one was
+/// quoted, otherwise as strings with aliases resolved
+fn compare(left: &Json, op: Op, right: &Json, text: bool, scope: &dyn Scope) -> Result<bool> {
+    let numbers = match text {
+        true => (None, None),
+        false => (Number::of(left), Number::of(right)),
+    };
+    let ordering = match numbers {
+        (Some(a), Some(b)) => a.cmp(b),
+        _ => match (left, right) {
+            (Json::String(a), Json::String(b)) => {
+                // Aliases stand for the addresses they name
+                let a = scope.address(a).unwrap_or_else(|| a.clone());
+                let b = scope.address(b).unwrap_or_else(|| b.clone());
+                Some(a.cmp(&b))
+            }
+            _ if matches!(op, Op::Eq | Op::Ne) => {
+                return Ok((left == right) == (op == Op::Eq));
+            }
+            // Nothing is more or less than a missing value
+            (Json::Null, _) | (_, Json::Null) => return Ok(false),
+            _ => None,
+        },
+    };
+    let Some(ordering) = ordering else {
+        return Err(DebuggerError::BreakpointError(format!(
+            "cannot compare {} with {}",
+            left, right
+        ))
+        .into());
+    };
+    Ok(match op {
+        Op::Eq => ordering == Ordering::Equal,
+        Op::Ne => ordering != Ordering::Equal,
+        Op::Lt => ordering == Ordering::Less,
+        Op::Le => ordering != Ordering::Greater,
+        Op::Gt => ordering == Ordering::Greater,
+        Op::Ge => ordering != Ordering::Less,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct TestScope {
+        args: Vec<(String, Json)>,
+    }
+
+    impl Scope for TestScope {
+        fn args(&self) -> &[(String, Json)] {
+            &self.args
+        }
+
+        fn budget(&self) -> BudgetInfo {
+            BudgetInfo {
+                cpu_instructions: 6_000_000,
+                cpu_limit: 100_000_000,
+                memory_bytes: 1000,
+                memory_limit: 40_000_000,
+            }
+        }
+
+        fn storage(&self, durability: Option<Durability>, key: &Json) -> Result<Option<Json>> {
+            Ok(match (durability, key) {
+                (None | Some(Durability::Persistent), key)
+                    if *key == json!({"Balance": "alice"}) =>
+                {
+                    Some(json!("0"))
+                }
+                (None | Some(Durability::Instance), key) if *key == json!("Counter") => {
+                    Some(json!(3))
+                }
+                _ => None,
+            })
+        }
+
+        fn address(&self, alias: &str) -> Option<String> {
+            (alias == "alice").then(|| "GALICE".to_string())
+        }
+    }
+
+    fn scope() -> TestScope {
+        TestScope {
+            args: vec![
+                ("from".to_string(), json!("GALICE")),
+                (
+                    "amount".to_string(),
+                    json!("170141183460469231731687303715884105727"),
+                ),
+                ("memo".to_string(), json!({"id": 7, "tags": ["a", "b"]})),
+            ],
+        }
+    }
+
+    fn check(condition: &str) -> bool {
+        condition
+            .parse::<Condition>()
+            .unwrap()
+            .evaluate(&scope())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_conditions() {
+        assert!(check("args.amount > 1000"));
+        assert!(check("args[1] >= 1_000 && args.memo.id == 7"));
+        assert!(check("args.memo.tags[1] == \"b\""));
+        assert!(check("args.from == \"alice\""));
+        assert!(check("storage[Balance(alice)] == 0"));
+        assert!(check("storage.instance[Counter] < 4"));
+        assert!(!check("storage.temporary[Counter] == 3"));
+        assert!(check("storage[Missing] == null"));
+        assert!(!check("storage[Missing] >= 0"));
+        assert!(check("budget.cpu > 5_000_000 || false"));
+        assert!(check("!(budget.memory > 5000)"));
+    }
+
+    #[test]
+    fn test_compare_big_integers() {
+        // Decoded 128-bit values are strings, and compare by value
+        let scope = TestScope {
+            args: vec![
+                ("a".to_string(), json!("99999999999999999999")),
+                ("b".to_string(), json!("100000000000000000000")),
+                ("c".to_string(), json!("-100000000000000000000")),
+                ("d".to_string(), json!(u128::MAX.to_string())),
+            ],
+        };
+        let check = |condition: &str| {
+            condition
+                .parse::<Condition>()
+                .unwrap()
+                .evaluate(&scope)
+                .unwrap()
+        };
+        assert!(check("args.a < args.b"));
+        assert!(check("args.c < args.a"));
+        assert!(check("args.d > args.b && args.c < args.d"));
+        assert!(check("args.b > 18446744073709551615"));
+        assert!(check("args.b == 100_000_000_000_000_000_000"));
+        assert!(check("args.a < 100000000000000000000"));
+        assert!(check("args.d == 340282366920938463463374607431768211455"));
+        assert!(check("340282366920938463463374607431768211455 > 1"));
+        // Quoted strings compare as text
+        assert!(check("args.b < \"99999999999999999999\""));
+        assert!(!check("args.b == \"100_000_000_000_000_000_000\""));
+    }
+
+    #[test]
+    fn test_condition_errors() {
+        for invalid in [
+            "args.amount >",
+            "balance > 3",
+            "storage[Balance(alice]",
+            "storage.forever[Counter]",
+            "args.amount > 1 2",
+            "budget.instructions > 5",
+        ] {
+            assert!(invalid.parse::<Condition>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_messages() {
+        let message: Message = "memo {args.memo.id} from {args.from}: {storage[Counter]} {{x}}"
+            .parse()
+            .unwrap();
+        assert_eq!(message.render(&scope()), "memo 7 from GALICE: 3 {x}");
+        let message: Message = "{args.missing}".parse().unwrap();
+        assert!(message.render(&scope()).contains("no argument 'missing'"));
+        assert!("{args.amount".parse::<Message>().is_err());
+        assert!("amount}".parse::<Message>().is_err());
+        assert!("{}".parse::<Message>().is_err());
+    }
+}
@@ -1,92 +1,1562 @@
-use crate::debugger::breakpoint::BreakpointManager;
+use crate::debugger::breakpoint::{Breakpoint, BreakpointKind, BreakpointManager, Failure};
+use crate::debugger::condition::Scope;
+use crate::debugger::observer::DebugObserver;
 use crate::debugger::state::DebugState;
-use crate::runtime::executor::ContractExecutor;
-use crate::Result;
+use crate::debugger::stepper::{StepGranularity, StepKind, Stepper};
+use crate::debugger::watch::WatchManager;
+use crate::debugger::worker::{Command, Event};
+use crate::inspector::BudgetInfo;
+use crate::runtime::contracts::ContractRegistry;
+use crate::runtime::events::ContractEvent;
+use crate::runtime::executor::{ContractExecutor, InvocationResult};
+use crate::runtime::host_functions::{self, HostFunction};
+use crate::runtime::instrumentation::Hook;
+use crate::runtime::probe::{BudgetMeter, HookEvent, HookHandler};
+use crate::runtime::storage::{self, AccessKind, Durability, StorageAccess};
+use crate::runtime::values::ValueCodec;
+use crate::utils::source_map::SourceLocation;
+use crate::utils::spec::ContractSpec;
+use crate::{DebuggerError, Result};
+use serde_json::Value as Json;
+use soroban_env_host::storage::StorageMap;
+use soroban_env_host::Host;
+use soroban_sdk::xdr::{ScAddress, ScError, ScVal};
+use soroban_sdk::Env;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender};
 use tracing::info;
 
-/// Core debugging engine that orchestrates execution and debugging
-pub struct DebuggerEngine {
-    executor: ContractExecutor,
+/// Where a paused execution is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionPoint {
+    pub contract: String,
+    pub function: String,
+    /// Offset of the next instruction in the original WASM module, or of the
+    /// last one before a host call, when known
+    pub offset: Option<usize>,
+    /// The host function about to be called, when stopped before one
+    pub host_call: Option<HostCall>,
+    /// Source line of the offset, for contracts built with debug info
+    pub source: Option<SourceLocation>,
+    /// Whether the function was just entered
+    pub entry: bool,
+}
+
+impl fmt::Display for ExecutionPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.contract, self.function)?;
+        match (self.offset, &self.host_call) {
+            (Some(offset), _) => write!(f, " at offset 0x{:x}", offset)?,
+            (None, None) if self.entry => write!(f, " (entry)")?,
+            (None, _) => {}
+        }
+        if let Some(source) = &self.source {
+            write!(f, " ({})", source)?;
+        }
+        if let Some(call) = &self.host_call {
+            write!(f, ", calling {}", call)?;
+        }
+        Ok(())
+    }
+}
+
+/// A call to a host function, with its decoded arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostCall {
+    pub function: &'static str,
+    /// Parameter names and argument values as JSON; plain integer arguments
+    /// are not reported and show as `_`
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl HostCall {
+    fn new(host: &HostFunction, args: &[ScVal]) -> Self {
+        let args = host_args(host, args)
+            .into_iter()
+            .map(|(name, arg)| {
+                let value = match arg {
+                    HostArg::Raw => "_".to_string(),
+                    HostArg::StorageType(Json::String(name)) => name,
+                    HostArg::StorageType(value) | HostArg::Value(value) => value.to_string(),
+                };
+                (name, value)
+            })
+            .collect();
+        Self {
+            function: host.name,
+            args,
+        }
+    }
+}
+
+/// An argument of a host function call, decoded without a spec
+enum HostArg {
+    /// A plain integer, which is not a value
+    Raw,
+    /// A storage type, by name if it has one
+    StorageType(Json),
+    Value(Json),
+}
+
+/// Arguments of a host function call by parameter name
+fn host_args(host: &HostFunction, args: &[ScVal]) -> Vec<(&'static str, HostArg)> {
+    let spec = ContractSpec::default();
+    let codec = ValueCodec::new(&spec);
+    host.params
+        .iter()
+        .zip(args)
+        .enumerate()
+        .map(|(i, (&(name, ty), arg))| {
+            let value = match (ty, arg) {
+                _ if host.is_raw(i) => HostArg::Raw,
+                ("StorageType", ScVal::U32(t)) => HostArg::StorageType(match t {
+                    0 => Json::from("temporary"),
+                    1 => Json::from("persistent"),
+                    2 => Json::from("instance"),
+                    _ => Json::from(*t),
+                }),
+                _ => HostArg::Value(codec.to_json_untyped(arg)),
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+impl fmt::Display for HostCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        write!(f, "{}({})", self.function, args.join(", "))
+    }
+}
+
+/// Why a running invocation paused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PauseReason {
+    /// A function with a breakpoint was entered
+    Breakpoint,
+    /// A requested step ended
+    Step,
+    /// A catch breakpoint stopped the invocation before it fails, as
+    /// described
+    Failure(String),
+    /// A watched storage key is about to be accessed, as described
+    Watch(String),
+}
+
+impl fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseReason::Breakpoint => write!(f, "breakpoint"),
+            PauseReason::Step => write!(f, "step"),
+            PauseReason::Failure(failure) => write!(f, "caught {}", failure),
+            PauseReason::Watch(access) => write!(f, "watch: {}", access),
+        }
+    }
+}
+
+/// What commands can inspect and change, whether an invocation is paused or
+/// not
+pub trait DebugContext {
+    fn breakpoints_mut(&mut self) -> &mut BreakpointManager;
+
+    fn watches_mut(&mut self) -> &mut WatchManager;
+
+    fn state(&self) -> &DebugState;
+
+    /// Whether an invocation is paused
+    fn is_paused(&self) -> bool;
+
+    /// Where the paused invocation is, or the recorded point being looked at
+    fn current_point(&self) -> Option<ExecutionPoint>;
+
+    /// Functions the paused invocation is in, outermost first, with the
+    /// innermost at the current point
+    fn call_stack(&self) -> Vec<ExecutionPoint>;
+
+    fn step_granularity(&self) -> StepGranularity;
+
+    fn set_step_granularity(&mut self, granularity: StepGranularity);
+
+    /// The host, with the budget and storage as they are now
+    fn host(&self) -> &Host;
+
+    /// Budget used at the current point
+    fn budget(&self) -> BudgetInfo;
+
+    /// Entries a contract stores at the current point, by durability, key
+    /// and value
+    fn storage(&self, contract: &ScAddress) -> Result<Vec<(Durability, ScVal, ScVal)>>;
+
+    fn contracts(&self) -> &ContractRegistry;
+
+    /// The whole engine, unless an invocation is paused and holds it
+    fn engine(&mut self) -> Option<&mut DebuggerEngine>;
+}
+
+/// Breakpoints, stepping and the position of the running invocation, which
+/// hooks work on while the executor is busy
+#[derive(Default)]
+struct Control {
     breakpoints: BreakpointManager,
+    watches: WatchManager,
     state: DebugState,
+    stepper: Stepper,
+    /// Function of the running or last invocation
+    function: Option<String>,
+    /// Position of the first function entry, which is the called function
+    entry: Option<usize>,
+    /// Source line each active call last reached, by call depth, while
+    /// there are breakpoints
+    lines: Vec<Option<SourceLocation>>,
+    /// How the invocation ran out of budget, for `catch:budget` breakpoints
+    /// to stop at once it has
+    exhausted: Option<Caught>,
+    history: History,
     paused: bool,
+    aborted: bool,
+}
+
+/// What a served invocation recorded at its hook calls, to go back to them
+/// while it is paused
+#[derive(Default)]
+struct History {
+    /// CPU instructions and memory bytes used at each hook call
+    budgets: Vec<(u64, u64)>,
+    /// Storage before each host call, by position. Only host calls change
+    /// storage, so a position sees what the next of these does.
+    storage: Vec<(usize, StorageMap)>,
+    /// Positions breakpoints paused at, and why
+    breaks: Vec<(usize, PauseReason)>,
+    /// Earlier position looked at instead of where the invocation is
+    view: Option<usize>,
+}
+
+/// How a hook call shows that the invocation is about to fail
+struct Caught {
+    failure: Failure,
+    description: String,
+    /// What conditions on the catch breakpoint see as arguments
+    args: Vec<(String, Json)>,
+}
+
+impl Control {
+    /// Position of the point looked at: a recorded one while replaying, else
+    /// where the invocation is paused
+    fn viewed(&self) -> Option<usize> {
+        self.history.view.or(self.stepper.position())
+    }
+
+    /// Look at a recorded position, which is replaying unless it is where
+    /// the invocation is paused, and describe it
+    fn view(&mut self, contracts: &ContractRegistry, position: usize) -> ExecutionPoint {
+        self.history.view = (self.stepper.position() != Some(position)).then_some(position);
+        let point = self.describe(contracts, position);
+        let event = &self.stepper.timeline()[position];
+        let entry = match event.hook {
+            Hook::Enter(_) => Some(event),
+            _ => self.stepper.frame_of(position).0,
+        };
+        let args = entry
+            .map(|entry| decode_args(contracts, entry, &point.function))
+            .unwrap_or_default();
+        self.state.set_frame(
+            event.contract.clone(),
+            self.stepper.depth_at(position),
+            args,
+        );
+        self.state.set_current_function(point.function.clone());
+        self.state.set_step(position);
+        point
+    }
+
+    /// Where a command moves the view of a paused invocation within its
+    /// history, and why it would stop there. Commands going forward past the
+    /// paused position give none, after leaving the stepper to go on from
+    /// the view.
+    fn travel(
+        &mut self,
+        command: &Command,
+    ) -> std::result::Result<Option<(usize, PauseReason)>, String> {
+        let (Some(view), Some(live)) = (self.viewed(), self.stepper.position()) else {
+            return Ok(None);
+        };
+        let found = match *command {
+            Command::Step(kind) if view < live => self
+                .stepper
+                .next_stop(view, kind, live)
+                .map(|p| (p, PauseReason::Step)),
+            Command::Continue if view < live => self
+                .history
+                .breaks
+                .iter()
+                .find(|(p, _)| *p > view && *p <= live)
+                .cloned(),
+            Command::Back => {
+                let position = self
+                    .stepper
+                    .previous_stop(view)
+                    .ok_or("At the start of the recorded history")?;
+                return Ok(Some((position, PauseReason::Step)));
+            }
+            Command::ReverseContinue => {
+                let found = self.history.breaks.iter().rev().find(|(p, _)| *p < view);
+                return found
+                    .cloned()
+                    .map(Some)
+                    .ok_or_else(|| "No breakpoint paused before this point".to_string());
+            }
+            Command::Goto(position) if position <= live => {
+                return Ok(Some((position, PauseReason::Step)))
+            }
+            Command::Goto(position) => {
+                return Err(format!(
+                    "Step {} is not recorded yet; the call is at step {}",
+                    position, live
+                ))
+            }
+            _ => return Ok(None),
+        };
+        if found.is_none() {
+            // Go on from the view as if execution were there
+            self.history.view = None;
+            self.stepper.stop_at(view);
+        }
+        Ok(found)
+    }
+
+    /// Where the hook call at a position of the timeline is
+    fn describe(&self, contracts: &ContractRegistry, position: usize) -> ExecutionPoint {
+        let timeline = self.stepper.timeline();
+        let event = &timeline[position];
+        let (entry, instruction) = match event.hook {
+            // Host calls, returns and traps happen in the function that made
+            // them, after its last instruction
+            Hook::Host(_) | Hook::Exit(_) | Hook::Trap(_) => self.stepper.frame_of(position),
+            _ => (None, Some(event)),
+        };
+        let contract = contracts.by_address(&event.contract);
+        let step = instruction.and_then(|e| match e.hook {
+            Hook::Step(id) => contract.and_then(|c| c.steps().get(id as usize)),
+            _ => None,
+        });
+        let index = match (entry.unwrap_or(event).hook, step) {
+            (Hook::Enter(index) | Hook::Exit(index), _) => Some(index),
+            (_, Some(step)) => Some(step.function),
+            _ => None,
+        };
+        let function = match (contract, index) {
+            (Some(contract), Some(index)) => contract.function_name(index),
+            _ => "(unknown)".to_string(),
+        };
+        let host_call = match event.hook {
+            Hook::Host(index) => {
+                host_functions::get(index).map(|host| HostCall::new(host, &event.args))
+            }
+            _ => None,
+        };
+        let source = contract
+            .and_then(|c| c.source_map())
+            .zip(step)
+            .and_then(|(map, step)| map.get_location(step.offset));
+        ExecutionPoint {
+            contract: contracts.display_name(&event.contract),
+            function,
+            offset: step.map(|s| s.offset),
+            host_call,
+            source,
+            entry: matches!(event.hook, Hook::Enter(_)),
+        }
+    }
+
+    /// Functions active at a position of the timeline, outermost first. The
+    /// innermost is described where the hook call is, the others by name.
+    fn call_stack(&self, contracts: &ContractRegistry, position: usize) -> Vec<ExecutionPoint> {
+        let timeline = self.stepper.timeline();
+        let mut frames = vec![self.describe(contracts, position)];
+        // The last entry at each shallower depth is the call still active
+        let mut depth = self.stepper.depth_at(position).saturating_sub(1);
+        for index in (0..position).rev() {
+            if depth == 0 {
+                break;
+            }
+            if matches!(timeline[index].hook, Hook::Enter(_))
+                && self.stepper.depth_at(index) == depth
+            {
+                let mut frame = self.describe(contracts, index);
+                frame.entry = false;
+                frames.push(frame);
+                depth -= 1;
+            }
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// The breakpoint a hook call reaches, if any, with the name of the
+    /// function it enters or the host function it calls. The first entry is
+    /// the called function, whatever its name in the module.
+    fn breakpoint_at(
+        &mut self,
+        contracts: &ContractRegistry,
+        position: usize,
+    ) -> Option<(String, Breakpoint)> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        let event = &self.stepper.timeline()[position];
+        match event.hook {
+            Hook::Enter(_) => {
+                self.lines.truncate(self.stepper.depth_at(position));
+                let called = self
+                    .function
+                    .as_deref()
+                    .filter(|_| self.entry == Some(position));
+                let function = match called {
+                    Some(function) if self.breakpoints.should_break(function) => {
+                        function.to_string()
+                    }
+                    _ => self.describe(contracts, position).function,
+                };
+                let breakpoint = self.breakpoints.get(&function)?.clone();
+                Some((function, breakpoint))
+            }
+            Hook::Host(index) => {
+                let host = host_functions::get(index)?;
+                let breakpoint = self
+                    .breakpoints
+                    .iter()
+                    .find(|b| b.kind.matches_host_call(host, &event.args, contracts))?
+                    .clone();
+                Some((host.name.to_string(), breakpoint))
+            }
+            Hook::Step(id) => {
+                if !self
+                    .breakpoints
+                    .iter()
+                    .any(|b| matches!(b.kind, BreakpointKind::Source { .. }))
+                {
+                    return None;
+                }
+                let contract = contracts.by_address(&event.contract)?;
+                let offset = contract.steps().get(id as usize)?.offset;
+                let location = contract.source_map()?.get_location(offset)?;
+                if !self.enters_line(position, &location) {
+                    return None;
+                }
+                let breakpoint = self
+                    .breakpoints
+                    .iter()
+                    .find(|b| b.kind.matches_line(&location))?
+                    .clone();
+                Some((self.describe(contracts, position).function, breakpoint))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction at a position moves its call onto another
+    /// source line, remembering the line it is on. Calls start on no line.
+    fn enters_line(&mut self, position: usize, location: &SourceLocation) -> bool {
+        let depth = self.stepper.depth_at(position);
+        self.lines.resize(depth + 1, None);
+        let entered = !self.lines[depth]
+            .as_ref()
+            .is_some_and(|last| last.file == location.file && last.line == location.line);
+        self.lines[depth] = Some(location.clone());
+        entered
+    }
+
+    /// How the hook call at a position shows the invocation failing, if it
+    /// does: a contract function returning an error, `fail_with_error` or a
+    /// trap
+    fn failure_at(&self, position: usize) -> Option<Caught> {
+        let event = &self.stepper.timeline()[position];
+        let error = |code: u32| Caught {
+            failure: Failure::Error,
+            description: format!("contract error {}", code),
+            args: vec![("code".to_string(), Json::from(code))],
+        };
+        match (event.hook, event.args.first()) {
+            (Hook::Exit(_), Some(ScVal::Error(ScError::Contract(code)))) => Some(error(*code)),
+            (Hook::Host(index), Some(ScVal::Error(ScError::Contract(code))))
+                if host_functions::get(index).is_some_and(|h| h.name == "fail_with_error") =>
+            {
+                Some(error(*code))
+            }
+            (Hook::Trap(_), _) => Some(Caught {
+                failure: Failure::Panic,
+                description: "panic".to_string(),
+                args: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether the hook call at a position is a host call accessing a
+    /// watched storage key, describing the access with the value stored
+    /// before it and after it, and the contract function making it
+    fn watch_at(
+        &mut self,
+        contracts: &ContractRegistry,
+        host: &Host,
+        position: usize,
+    ) -> Option<PauseReason> {
+        if self.watches.is_empty() {
+            return None;
+        }
+        let event = &self.stepper.timeline()[position];
+        let Hook::Host(index) = event.hook else {
+            return None;
+        };
+        let access = StorageAccess::of_host_call(host_functions::get(index)?, &event.args)?;
+        let default_spec = ContractSpec::default();
+        let contract = contracts.by_address(&event.contract);
+        let spec = contract.map_or(&default_spec, |c| c.spec());
+        let codec = ValueCodec::new(spec).with_aliases(contracts.aliases());
+        let watch = self.watches.iter().find(|w| {
+            w.durability.is_none_or(|d| d == access.durability)
+                && codec.to_scval_untyped(&w.key).as_ref() == Ok(&access.key)
+        })?;
+        let description = format!("{} {}", access.durability, watch.key_source());
+        let watch = watch.clone();
+
+        let show = |value: &Option<ScVal>| match value {
+            Some(value) => codec.to_json_untyped(value).to_string(),
+            None => "(none)".to_string(),
+        };
+        let frame = self.stepper.calls_since_entered(&event.contract, position);
+        let old = read_stored(host, frame, &event.contract, access.durability, &access.key)
+            .ok()
+            .flatten();
+        let values = if access.kind.changes_value() {
+            format!("{} -> {}", show(&old), show(&access.value))
+        } else {
+            show(&old)
+        };
+        // The innermost frame of the contract running one of its exported
+        // functions, rather than an SDK function it calls
+        let name = contracts.display_name(&event.contract);
+        let frames = self.call_stack(contracts, position);
+        let frame = frames
+            .iter()
+            .rev()
+            .filter(|f| f.contract == name)
+            .find(|f| spec.function(&f.function).is_some())
+            .or(frames.last())?;
+        self.watches.hit(&watch);
+        Some(PauseReason::Watch(format!(
+            "{} {} by {}::{}: {}",
+            description, access.kind, frame.contract, frame.function, values
+        )))
+    }
+
+    /// Why a breakpoint pauses the hook call at a position, if one does,
+    /// counting its hit. A condition that cannot be evaluated pauses, after
+    /// observers are told why; logpoints hand them their message and go on.
+    fn breaks_at(
+        &mut self,
+        contracts: &ContractRegistry,
+        host: &Host,
+        position: usize,
+        budget: &BudgetInfo,
+        observers: &[Rc<dyn DebugObserver>],
+    ) -> Option<PauseReason> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        let failure = self.exhausted.take().or_else(|| self.failure_at(position));
+        let caught = failure.and_then(|caught| {
+            let key = BreakpointKind::Catch(caught.failure).to_string();
+            Some((caught, self.breakpoints.get(&key)?.clone()))
+        });
+        let (function, breakpoint, reason, args) = match caught {
+            Some((caught, breakpoint)) => (
+                self.describe(contracts, position).function,
+                breakpoint,
+                PauseReason::Failure(caught.description),
+                Some(caught.args),
+            ),
+            None => {
+                let (function, breakpoint) = self.breakpoint_at(contracts, position)?;
+                (function, breakpoint, PauseReason::Breakpoint, None)
+            }
+        };
+        let event = &self.stepper.timeline()[position];
+        let mut scope = HitScope::new(contracts, host, event, &function, budget.clone());
+        if let Some(args) = args {
+            scope.args = args;
+        }
+        scope.frame = self.stepper.calls_since_entered(&event.contract, position);
+        if let Some(condition) = &breakpoint.condition {
+            let holds = condition.evaluate(&scope).unwrap_or_else(|e| {
+                for observer in observers {
+                    observer.on_condition_error(&breakpoint, &e.to_string());
+                }
+                true
+            });
+            if !holds {
+                return None;
+            }
+        }
+        match self.breakpoints.hit(&breakpoint.key()) {
+            Some(breakpoint) if breakpoint.ignored() => None,
+            Some(Breakpoint { log: Some(log), .. }) => {
+                let message = log.render(&scope);
+                for observer in observers {
+                    observer.on_log(&function, &message);
+                }
+                None
+            }
+            _ => Some(reason),
+        }
+    }
+}
+
+/// What breakpoint conditions and logpoint messages see where a function is
+/// entered or a host function called
+struct HitScope<'a> {
+    contracts: &'a ContractRegistry,
+    host: &'a Host,
+    contract: &'a ScAddress,
+    args: Vec<(String, Json)>,
+    /// Hook calls of the contract's active call so far
+    frame: &'a [HookEvent],
+    budget: BudgetInfo,
+}
+
+impl<'a> HitScope<'a> {
+    /// Decode the arguments of an entry with the function's spec, or name
+    /// them by position without one. Host calls name them after the host
+    /// function's parameters; plain integers are null.
+    fn new(
+        contracts: &'a ContractRegistry,
+        host: &'a Host,
+        event: &'a HookEvent,
+        function: &str,
+        budget: BudgetInfo,
+    ) -> Self {
+        let args = match event.hook {
+            Hook::Host(index) => host_functions::get(index)
+                .map(|host_function| host_args(host_function, &event.args))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, arg)| {
+                    let value = match arg {
+                        HostArg::Raw => Json::Null,
+                        HostArg::StorageType(value) | HostArg::Value(value) => value,
+                    };
+                    (name.to_string(), value)
+                })
+                .collect(),
+            _ => decode_args(contracts, event, function),
+        };
+        Self {
+            contracts,
+            host,
+            contract: &event.contract,
+            args,
+            frame: &[],
+            budget,
+        }
+    }
+}
+
+/// Value a contract stores under a key, as its active call sees it: instance
+/// storage changes the call made, given by its hook calls, are applied to
+/// the ledger's instance entry
+fn read_stored(
+    host: &Host,
+    frame: &[HookEvent],
+    contract: &ScAddress,
+    durability: Durability,
+    key: &ScVal,
+) -> Result<Option<ScVal>> {
+    let mut value = storage::read_entry(host, contract, durability, key)?;
+    if durability != Durability::Instance {
+        return Ok(value);
+    }
+    for event in frame.iter().filter(|e| e.contract == *contract) {
+        let Hook::Host(index) = event.hook else {
+            continue;
+        };
+        let Some(access) = host_functions::get(index)
+            .and_then(|host| StorageAccess::of_host_call(host, &event.args))
+        else {
+            continue;
+        };
+        if access.durability == Durability::Instance && access.key == *key {
+            match access.kind {
+                AccessKind::Written => value = access.value,
+                AccessKind::Removed => value = None,
+                _ => {}
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// Arguments of a function entry, decoded with the function's spec, or named
+/// by position without one
+fn decode_args(
+    contracts: &ContractRegistry,
+    event: &HookEvent,
+    function: &str,
+) -> Vec<(String, Json)> {
+    let spec = contracts.by_address(&event.contract).map(|c| c.spec());
+    let default_spec = ContractSpec::default();
+    let inputs = spec
+        .and_then(|spec| spec.function(function))
+        .map(|f| &f.inputs);
+    let codec = ValueCodec::new(spec.unwrap_or(&default_spec));
+    event
+        .args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| match inputs.and_then(|inputs| inputs.get(i)) {
+            Some(input) => (
+                input.name.to_utf8_string_lossy(),
+                codec.to_json(&input.type_, arg),
+            ),
+            None => (i.to_string(), codec.to_json_untyped(arg)),
+        })
+        .collect()
+}
+
+impl Scope for HitScope<'_> {
+    fn args(&self) -> &[(String, Json)] {
+        &self.args
+    }
+
+    fn budget(&self) -> BudgetInfo {
+        self.budget.clone()
+    }
+
+    fn storage(&self, durability: Option<Durability>, key: &Json) -> Result<Option<Json>> {
+        let default_spec = ContractSpec::default();
+        let spec = self
+            .contracts
+            .by_address(self.contract)
+            .map_or(&default_spec, |c| c.spec());
+        let codec = ValueCodec::new(spec).with_aliases(self.contracts.aliases());
+        let key = codec
+            .to_scval_untyped(key)
+            .map_err(|e| DebuggerError::BreakpointError(format!("storage key {}: {}", key, e)))?;
+        let durabilities = match durability {
+            Some(durability) => vec![durability],
+            None => vec![
+                Durability::Persistent,
+                Durability::Temporary,
+                Durability::Instance,
+            ],
+        };
+        for durability in durabilities {
+            if let Some(value) =
+                read_stored(self.host, self.frame, self.contract, durability, &key)?
+            {
+                return Ok(Some(codec.to_json_untyped(&value)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn address(&self, alias: &str) -> Option<String> {
+        self.contracts.resolve(alias).map(|a| a.to_string())
+    }
+}
+
+/// Ends of the channels a served engine talks through
+struct Channel {
+    commands: Receiver<Command>,
+    events: Sender<Event>,
+}
+
+/// Hook handler deciding where a running invocation pauses, and taking
+/// commands while it is paused
+struct Controller {
+    control: RefCell<Control>,
+    /// Contracts as registered when the invocation started
+    contracts: ContractRegistry,
+    meter: BudgetMeter,
+    channel: Option<Rc<Channel>>,
+    observers: Vec<Rc<dyn DebugObserver>>,
+}
+
+impl Controller {
+    /// Tell observers about a host function call, the storage access or
+    /// event it makes if any, and the budget used so far
+    fn report_host_call(
+        &self,
+        control: &Control,
+        position: usize,
+        index: u32,
+        budget: &BudgetInfo,
+    ) {
+        let event = &control.stepper.timeline()[position];
+        let Some(host) = host_functions::get(index) else {
+            return;
+        };
+        let point = control.describe(&self.contracts, position);
+        let call = HostCall::new(host, &event.args);
+        let access = StorageAccess::of_host_call(host, &event.args);
+        let published = match (host.name, event.args.as_slice()) {
+            ("contract_event", [ScVal::Vec(topics), data]) => {
+                let spec = ContractSpec::default();
+                let codec = ValueCodec::new(&spec);
+                Some(ContractEvent {
+                    contract: self.contracts.display_name(&event.contract),
+                    contract_id: event.contract.to_string(),
+                    topics: topics
+                        .iter()
+                        .flat_map(|topics| topics.iter())
+                        .map(|t| codec.to_json_untyped(t))
+                        .collect(),
+                    data: codec.to_json_untyped(data),
+                })
+            }
+            _ => None,
+        };
+        for observer in &self.observers {
+            observer.on_host_call(&point, &call);
+            if let Some(access) = &access {
+                observer.on_storage_access(&event.contract, access);
+            }
+            if let Some(published) = &published {
+                observer.on_event(published);
+            }
+            observer.on_budget(budget);
+        }
+    }
+}
+
+impl HookHandler for Controller {
+    fn on_hook(&self, env: &Env, event: &HookEvent) -> bool {
+        let mut control = self.control.borrow_mut();
+        if control.aborted {
+            return false;
+        }
+        let position = control.stepper.record(event.clone());
+        if matches!(event.hook, Hook::Enter(_)) && control.entry.is_none() {
+            control.entry = Some(position);
+        }
+        let budget = self.meter.read(env.host());
+        let used = (budget.cpu_instructions, budget.memory_bytes);
+        if let (Hook::Host(index), false) = (event.hook, self.observers.is_empty()) {
+            self.report_host_call(&control, position, index, &budget);
+        }
+        if self.channel.is_some() {
+            control.history.budgets.push(used);
+            if matches!(event.hook, Hook::Host(_)) {
+                if let Ok(map) = storage::snapshot(env.host()) {
+                    control.history.storage.push((position, map));
+                }
+            }
+        }
+        let reason = if control.stepper.reached(position) {
+            PauseReason::Step
+        } else if let Some(reason) = control.breaks_at(
+            &self.contracts,
+            env.host(),
+            position,
+            &budget,
+            &self.observers,
+        ) {
+            reason
+        } else if let Some(reason) = control.watch_at(&self.contracts, env.host(), position) {
+            reason
+        } else {
+            return true;
+        };
+        self.pause(&mut control, env.host(), position, reason)
+    }
+}
+
+impl Controller {
+    /// Stop at a position of the timeline, telling observers why, and take
+    /// commands until told to go on. Whether the invocation may go on.
+    fn pause(
+        &self,
+        control: &mut Control,
+        host: &Host,
+        position: usize,
+        reason: PauseReason,
+    ) -> bool {
+        control.stepper.stop_at(position);
+        let point = control.view(&self.contracts, position);
+        if reason != PauseReason::Step {
+            for observer in &self.observers {
+                observer.on_breakpoint(&reason, &point);
+            }
+        }
+
+        // Nobody to take commands, so go on once observers have seen it
+        let Some(channel) = &self.channel else {
+            control.stepper.resume();
+            return true;
+        };
+
+        control.paused = true;
+        control.state.set_pause_reason(Some(reason.clone()));
+        if reason != PauseReason::Step {
+            control.history.breaks.push((position, reason.clone()));
+        }
+        let _ = channel.events.send(Event::Paused {
+            reason,
+            point,
+            step: position,
+            replaying: false,
+        });
+        loop {
+            let command = channel.commands.recv();
+            if let Ok(command) = &command {
+                match control.travel(command) {
+                    Ok(Some((position, reason))) => {
+                        let point = control.view(&self.contracts, position);
+                        control.state.set_pause_reason(Some(reason.clone()));
+                        let _ = channel.events.send(Event::Paused {
+                            reason,
+                            point,
+                            step: position,
+                            replaying: control.history.view.is_some(),
+                        });
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(message) => {
+                        let _ = channel.events.send(Event::Rejected(message));
+                        continue;
+                    }
+                }
+            }
+            match command {
+                Ok(Command::Step(kind)) => {
+                    control.stepper.step(kind);
+                    break;
+                }
+                Ok(Command::Continue) => {
+                    control.stepper.resume();
+                    break;
+                }
+                Ok(Command::Run(f)) => f(&mut Paused {
+                    control,
+                    contracts: &self.contracts,
+                    meter: &self.meter,
+                    host,
+                }),
+                Ok(Command::Call { .. }) => {
+                    let _ = channel.events.send(Event::Rejected(
+                        "A call is already paused; continue or abort it first".to_string(),
+                    ));
+                }
+                Ok(Command::Back | Command::ReverseContinue | Command::Goto(_)) => {}
+                Ok(Command::Abort) | Err(_) => {
+                    control.aborted = true;
+                    break;
+                }
+            }
+        }
+        control.paused = false;
+        control.state.set_pause_reason(None);
+        !control.aborted
+    }
+
+    /// Stop where an invocation ran out of budget, after the host failed it,
+    /// if a `catch:budget` breakpoint is set: at the last hook call it made
+    fn exhausted(&self, host: &Host) {
+        let mut control = self.control.borrow_mut();
+        let key = BreakpointKind::Catch(Failure::Budget).to_string();
+        let Some(position) = control.stepper.timeline().len().checked_sub(1) else {
+            return;
+        };
+        if control.breakpoints.get(&key).is_none() {
+            return;
+        }
+        let budget = self.meter.read(host);
+        control.exhausted = Some(Caught {
+            failure: Failure::Budget,
+            description: budget.exceeded(),
+            args: vec![
+                ("cpu".to_string(), Json::from(budget.cpu_instructions)),
+                ("memory".to_string(), Json::from(budget.memory_bytes)),
+            ],
+        });
+        let reason = control.breaks_at(&self.contracts, host, position, &budget, &self.observers);
+        control.exhausted = None;
+        if let Some(reason) = reason {
+            self.pause(&mut control, host, position, reason);
+        }
+    }
+}
+
+/// Context of commands run while an invocation is paused
+struct Paused<'a> {
+    control: &'a mut Control,
+    contracts: &'a ContractRegistry,
+    meter: &'a BudgetMeter,
+    host: &'a Host,
+}
+
+impl DebugContext for Paused<'_> {
+    fn breakpoints_mut(&mut self) -> &mut BreakpointManager {
+        &mut self.control.breakpoints
+    }
+
+    fn watches_mut(&mut self) -> &mut WatchManager {
+        &mut self.control.watches
+    }
+
+    fn state(&self) -> &DebugState {
+        &self.control.state
+    }
+
+    fn is_paused(&self) -> bool {
+        true
+    }
+
+    fn current_point(&self) -> Option<ExecutionPoint> {
+        self.control
+            .viewed()
+            .map(|position| self.control.describe(self.contracts, position))
+    }
+
+    fn call_stack(&self) -> Vec<ExecutionPoint> {
+        self.control
+            .viewed()
+            .map(|position| self.control.call_stack(self.contracts, position))
+            .unwrap_or_default()
+    }
+
+    fn step_granularity(&self) -> StepGranularity {
+        self.control.stepper.granularity()
+    }
+
+    fn set_step_granularity(&mut self, granularity: StepGranularity) {
+        self.control.stepper.set_granularity(granularity);
+    }
+
+    fn host(&self) -> &Host {
+        self.host
+    }
+
+    fn budget(&self) -> BudgetInfo {
+        let mut budget = self.meter.read(self.host);
+        let history = &self.control.history;
+        if let Some(&(cpu, mem)) = history.view.and_then(|view| history.budgets.get(view)) {
+            budget.cpu_instructions = cpu;
+            budget.memory_bytes = mem;
+        }
+        budget
+    }
+
+    fn storage(&self, contract: &ScAddress) -> Result<Vec<(Durability, ScVal, ScVal)>> {
+        let history = &self.control.history;
+        let snapshot = history
+            .view
+            .and_then(|view| history.storage.iter().find(|(p, _)| *p >= view));
+        match snapshot {
+            Some((_, map)) => storage::contract_entries(map, contract),
+            None => storage::read_all(self.host, contract),
+        }
+    }
+
+    fn contracts(&self) -> &ContractRegistry {
+        self.contracts
+    }
+
+    fn engine(&mut self) -> Option<&mut DebuggerEngine> {
+        None
+    }
+}
+
+/// Core debugging engine that orchestrates execution and debugging.
+///
+/// On its own, the engine runs invocations to completion and reports the
+/// breakpoints they reach. Served over channels by
+/// [`EngineWorker`](crate::debugger::worker::EngineWorker), invocations pause
+/// at breakpoints and steps until told to go on.
+pub struct DebuggerEngine {
+    executor: ContractExecutor,
+    control: Control,
+    channel: Option<Rc<Channel>>,
+    observers: Vec<Rc<dyn DebugObserver>>,
 }
 
 impl DebuggerEngine {
-    /// Create a new debugger engine
-    pub fn new(executor: ContractExecutor, initial_breakpoints: Vec<String>) -> Self {
+    /// Create a new debugger engine, with initial breakpoints given as
+    /// `<target> [if <condition>]`
+    pub fn new(executor: ContractExecutor, initial_breakpoints: Vec<String>) -> Result<Self> {
         let mut breakpoints = BreakpointManager::new();
-
-        // Add initial breakpoints
         for bp in initial_breakpoints {
-            breakpoints.add(&bp);
-            info!("Breakpoint set at function: {}", bp);
+            let bp = bp.parse::<Breakpoint>()?;
+            info!("Breakpoint set: {}", bp);
+            breakpoints.insert(bp);
         }
 
-        Self {
+        Ok(Self {
             executor,
-            breakpoints,
-            state: DebugState::new(),
-            paused: false,
-        }
+            control: Control {
+                breakpoints,
+                ..Control::default()
+            },
+            channel: None,
+            observers: Vec::new(),
+        })
     }
 
     /// Execute a contract function with debugging
-    pub fn execute(&mut self, function: &str, args: Option<&str>) -> Result<String> {
+    pub fn execute(&mut self, function: &str, args: Option<&str>) -> Result<InvocationResult> {
         info!("Executing function: {}", function);
+        for observer in &self.observers {
+            observer.on_invocation_start(function, args);
+        }
 
-        // Check if we should break at function entry
-        if self.breakpoints.should_break(function) {
-            self.pause_at_function(function);
+        let mut control = std::mem::take(&mut self.control);
+        control.stepper.reset();
+        control.state.reset();
+        control.function = Some(function.to_string());
+        control.entry = None;
+        control.lines.clear();
+        control.exhausted = None;
+        control.history = History::default();
+        control.aborted = false;
+        let controller = Rc::new(Controller {
+            control: RefCell::new(control),
+            contracts: self.executor.contracts().clone(),
+            meter: self.executor.budget_meter(),
+            channel: self.channel.clone(),
+            observers: self.observers.clone(),
+        });
+        self.executor.set_hook_handler(Some(controller.clone()));
+        let result = self.executor.execute(function, args);
+        self.executor.set_hook_handler(None);
+        if let Err(e) = &result {
+            if let Some(DebuggerError::BudgetExceeded(_)) = e.downcast_ref() {
+                controller.exhausted(self.executor.host());
+            }
         }
+        self.control = controller.control.take();
 
-        // Execute the contract
-        let result = self.executor.execute(function, args)?;
+        // The invocation is over, wherever it was paused
+        self.control.stepper.resume();
+        let result = if self.control.aborted {
+            Err(DebuggerError::ExecutionError("Execution aborted".to_string()).into())
+        } else {
+            result
+        };
+        let outcome = match &result {
+            Ok(result) => Ok(result.json.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        self.control.state.finish(outcome);
+
+        // Without hooks, only the called function can be checked
+        if let Ok(result) = &result {
+            if self.control.stepper.timeline().is_empty()
+                && self.control.breakpoints.should_break(function)
+            {
+                let point = ExecutionPoint {
+                    contract: result.contract.clone(),
+                    function: function.to_string(),
+                    offset: None,
+                    host_call: None,
+                    source: None,
+                    entry: true,
+                };
+                for observer in &self.observers {
+                    observer.on_breakpoint(&PauseReason::Breakpoint, &point);
+                }
+            }
+        }
+        if !self.observers.is_empty() {
+            let budget = self.executor.budget();
+            for observer in &self.observers {
+                observer.on_budget(&budget);
+                observer.on_invocation_end(function, &result);
+            }
+        }
+        let result = result?;
 
         info!("Execution completed");
         Ok(result)
     }
 
-    /// Step through one instruction
-    pub fn step(&mut self) -> Result<()> {
-        info!("Stepping...");
-        self.paused = false;
-        // TODO: Implement actual stepping logic
-        Ok(())
+    /// Take commands and report what invocations do until the command
+    /// channel closes
+    pub fn serve(&mut self, commands: Receiver<Command>, events: Sender<Event>) {
+        let channel = Rc::new(Channel { commands, events });
+        self.channel = Some(channel.clone());
+        while let Ok(command) = channel.commands.recv() {
+            let event = match command {
+                Command::Call {
+                    function,
+                    args,
+                    step,
+                } => {
+                    if step {
+                        self.control.stepper.step(StepKind::Into);
+                    }
+                    Event::Finished(self.execute(&function, args.as_deref()))
+                }
+                Command::Run(f) => {
+                    f(self);
+                    continue;
+                }
+                Command::Step(_)
+                | Command::Continue
+                | Command::Back
+                | Command::ReverseContinue
+                | Command::Goto(_) => Event::Rejected("No call is paused".to_string()),
+                Command::Abort => continue,
+            };
+            if channel.events.send(event).is_err() {
+                break;
+            }
+        }
+        self.channel = None;
     }
 
-    /// Continue execution until next breakpoint
-    pub fn continue_execution(&mut self) -> Result<()> {
-        info!("Continuing execution...");
-        self.paused = false;
-        // TODO: Implement continue logic
-        Ok(())
+    /// What steps move over
+    pub fn step_granularity(&self) -> StepGranularity {
+        self.control.stepper.granularity()
     }
 
-    /// Pause execution at a function
-    fn pause_at_function(&mut self, function: &str) {
-        println!("\n[BREAKPOINT] Paused at function: {}", function);
-        self.paused = true;
-        self.state.set_current_function(function.to_string());
+    pub fn set_step_granularity(&mut self, granularity: StepGranularity) {
+        self.control.stepper.set_granularity(granularity);
     }
 
     /// Check if debugger is currently paused
     pub fn is_paused(&self) -> bool {
-        self.paused
+        self.control.paused
     }
 
-    /// Get current debug state
+    /// Have an observer see what invocations from now on do. If it needs
+    /// host calls, the session's contracts are instrumented to report them.
+    pub fn add_observer(&mut self, observer: Rc<dyn DebugObserver>) -> Result<()> {
+        if observer.needs_host_calls() {
+            self.executor.enable_host_calls()?;
+        }
+        self.observers.push(observer);
+        Ok(())
+    }
+
+    /// Get current debug state, including how the last invocation ended
     pub fn state(&self) -> &DebugState {
-        &self.state
+        &self.control.state
     }
 
     /// Get mutable reference to breakpoint manager
     pub fn breakpoints_mut(&mut self) -> &mut BreakpointManager {
-        &mut self.breakpoints
+        &mut self.control.breakpoints
+    }
+
+    /// Get mutable reference to the watched storage keys
+    pub fn watches_mut(&mut self) -> &mut WatchManager {
+        &mut self.control.watches
     }
 
     /// Get reference to executor
     pub fn executor(&self) -> &ContractExecutor {
         &self.executor
     }
+
+    /// Get mutable reference to executor
+    pub fn executor_mut(&mut self) -> &mut ContractExecutor {
+        &mut self.executor
+    }
+}
+
+impl DebugContext for DebuggerEngine {
+    fn breakpoints_mut(&mut self) -> &mut BreakpointManager {
+        &mut self.control.breakpoints
+    }
+
+    fn watches_mut(&mut self) -> &mut WatchManager {
+        &mut self.control.watches
+    }
+
+    fn state(&self) -> &DebugState {
+        &self.control.state
+    }
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    fn current_point(&self) -> Option<ExecutionPoint> {
+        None
+    }
+
+    fn call_stack(&self) -> Vec<ExecutionPoint> {
+        Vec::new()
+    }
+
+    fn step_granularity(&self) -> StepGranularity {
+        self.control.stepper.granularity()
+    }
+
+    fn set_step_granularity(&mut self, granularity: StepGranularity) {
+        self.control.stepper.set_granularity(granularity);
+    }
+
+    fn host(&self) -> &Host {
+        self.executor.host()
+    }
+
+    fn budget(&self) -> BudgetInfo {
+        self.executor.budget()
+    }
+
+    fn storage(&self, contract: &ScAddress) -> Result<Vec<(Durability, ScVal, ScVal)>> {
+        storage::read_all(self.executor.host(), contract)
+    }
+
+    fn contracts(&self) -> &ContractRegistry {
+        self.executor.contracts()
+    }
+
+    fn engine(&mut self) -> Option<&mut DebuggerEngine> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::auth::AuthConfig;
+    use crate::runtime::host_functions::HOST_FUNCTIONS;
+    use crate::runtime::ledger::LedgerConfig;
+
+    const TEST_CONTRACT: &[u8] = include_bytes!("../../tests/fixtures/test_contract.wasm");
+
+    /// Pause reasons reported to an observer
+    #[derive(Default)]
+    struct Pauses(RefCell<Vec<PauseReason>>);
+
+    impl DebugObserver for Pauses {
+        fn on_breakpoint(&self, reason: &PauseReason, _point: &ExecutionPoint) {
+            self.0.borrow_mut().push(reason.clone());
+        }
+    }
+
+    /// An engine running the test contract with host calls reported, as
+    /// `run` does for catch breakpoints
+    fn engine_with(breakpoint: &str) -> (DebuggerEngine, Rc<Pauses>) {
+        let mut executor = ContractExecutor::empty(&LedgerConfig::default(), None).unwrap();
+        executor.enable_host_calls().unwrap();
+        executor
+            .register_contract("contract", TEST_CONTRACT, None, None)
+            .unwrap();
+        executor.select_contract("contract").unwrap();
+        executor.add_account("alice").unwrap();
+        executor.add_account("bob").unwrap();
+        executor.set_auth(AuthConfig::MockAll).unwrap();
+        let mut engine = DebuggerEngine::new(executor, vec![]).unwrap();
+        engine.breakpoints_mut().insert(breakpoint.parse().unwrap());
+        let pauses = Rc::new(Pauses::default());
+        engine.add_observer(pauses.clone()).unwrap();
+        (engine, pauses)
+    }
+
+    #[test]
+    fn test_initial_breakpoints() {
+        let executor = || ContractExecutor::new(TEST_CONTRACT.to_vec(), None).unwrap();
+        let mut engine =
+            DebuggerEngine::new(executor(), vec!["catch:error if args.code == 1".into()]).unwrap();
+        assert_eq!(engine.breakpoints_mut().iter().count(), 1);
+        assert!(DebuggerEngine::new(executor(), vec!["catch:typo".into()]).is_err());
+    }
+
+    #[test]
+    fn test_catch_error() {
+        let (mut engine, pauses) = engine_with("catch:error");
+        engine.execute("mint", Some(r#"["alice", 5]"#)).unwrap();
+        assert!(pauses.0.borrow().is_empty());
+        let result = engine.execute("transfer", Some(r#"["alice", "bob", 10]"#));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Contract error code: 1"));
+        assert_eq!(
+            *pauses.0.borrow(),
+            [PauseReason::Failure("contract error 1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        let (mut engine, pauses) = engine_with("catch:panic");
+        assert!(engine.execute("boom", None).is_err());
+        assert_eq!(
+            *pauses.0.borrow(),
+            [PauseReason::Failure("panic".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_catch_budget() {
+        let (mut engine, pauses) = engine_with("catch:budget");
+        engine.execute("spin", Some("[1000]")).unwrap();
+        assert!(pauses.0.borrow().is_empty());
+        let result = engine.execute("spin", Some("[5000000]"));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("CPU budget exceeded"));
+        let pauses = pauses.0.borrow();
+        assert_eq!(pauses.len(), 1);
+        assert!(
+            matches!(&pauses[0], PauseReason::Failure(f) if f.starts_with("CPU budget exceeded"))
+        );
+    }
+
+    #[test]
+    fn test_catch_budget_stops_loops_without_calls() {
+        let (mut engine, pauses) = engine_with("catch:budget");
+        // The host ends the loop at the limits rather than the next hook
+        let start = std::time::Instant::now();
+        assert!(engine.execute("spin", Some("[10000000000]")).is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+        assert_eq!(pauses.0.borrow().len(), 1);
+    }
+
+    /// Every callback an observer receives, in order
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<String>>);
+
+    impl Recorder {
+        fn push(&self, entry: String) {
+            self.0.borrow_mut().push(entry);
+        }
+    }
+
+    impl DebugObserver for Recorder {
+        fn on_invocation_start(&self, function: &str, _args: Option<&str>) {
+            self.push(format!("start {function}"));
+        }
+
+        fn on_invocation_end(&self, function: &str, result: &Result<InvocationResult>) {
+            self.push(format!("end {function} ok={}", result.is_ok()));
+        }
+
+        fn on_host_call(&self, _point: &ExecutionPoint, call: &HostCall) {
+            self.push(format!("host {}", call.function));
+        }
+
+        fn on_storage_access(&self, _contract: &ScAddress, access: &StorageAccess) {
+            self.push(format!("storage {:?} {:?}", access.kind, access.durability));
+        }
+
+        fn on_event(&self, event: &ContractEvent) {
+            self.push(format!("event {}", event.topics[0]));
+        }
+
+        fn on_budget(&self, _budget: &BudgetInfo) {
+            self.push("budget".to_string());
+        }
+    }
+
+    #[test]
+    fn test_observer_callbacks() {
+        // Host calls are not instrumented until the observer asks for them
+        let mut executor = ContractExecutor::empty(&LedgerConfig::default(), None).unwrap();
+        executor
+            .register_contract("contract", TEST_CONTRACT, None, None)
+            .unwrap();
+        executor.select_contract("contract").unwrap();
+        executor.add_account("alice").unwrap();
+        executor.add_account("bob").unwrap();
+        executor.set_auth(AuthConfig::MockAll).unwrap();
+        let mut engine = DebuggerEngine::new(executor, vec![]).unwrap();
+        engine.execute("mint", Some(r#"["alice", 5]"#)).unwrap();
+
+        let recorder = Rc::new(Recorder::default());
+        engine.add_observer(recorder.clone()).unwrap();
+        engine
+            .execute("transfer", Some(r#"["alice", "bob", 3]"#))
+            .unwrap();
+        let calls = recorder.0.borrow();
+        assert_eq!(calls.first().unwrap(), "start transfer");
+        assert_eq!(calls.last().unwrap(), "end transfer ok=true");
+
+        // What each host call touched is reported right after it, and the
+        // budget after that, before the next host call
+        for (i, call) in calls.iter().enumerate() {
+            if call.starts_with("host ") {
+                let after = calls[i + 1..]
+                    .iter()
+                    .find(|c| !c.starts_with("storage ") && !c.starts_with("event "));
+                assert_eq!(after.unwrap(), "budget", "after {call}");
+            }
+        }
+        let touched: Vec<&str> = calls
+            .iter()
+            .map(String::as_str)
+            .filter(|c| *c != "budget" && *c != "host vec_new_from_linear_memory")
+            .collect();
+        assert_eq!(
+            touched,
+            [
+                "start transfer",
+                "host require_auth",
+                "host has_contract_data",
+                "storage Checked Persistent",
+                "host get_contract_data",
+                "storage Read Persistent",
+                "host has_contract_data",
+                "storage Checked Persistent",
+                "host put_contract_data",
+                "storage Written Persistent",
+                "host put_contract_data",
+                "storage Written Persistent",
+                "host contract_event",
+                "event \"transfer\"",
+                "end transfer ok=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_stored_instance_changes() {
+        let env = Env::default();
+        let contract = ScAddress::Contract(soroban_sdk::xdr::Hash([1; 32]));
+        let host_call = |name: &str, args: Vec<ScVal>| HookEvent {
+            contract: contract.clone(),
+            hook: Hook::Host(HOST_FUNCTIONS.iter().position(|h| h.name == name).unwrap() as u32),
+            args,
+        };
+        let key = ScVal::U32(7);
+        let instance = ScVal::U32(2);
+        let mut frame = vec![
+            host_call(
+                "put_contract_data",
+                vec![key.clone(), ScVal::I32(1), instance.clone()],
+            ),
+            host_call(
+                "put_contract_data",
+                vec![ScVal::U32(8), ScVal::I32(2), instance.clone()],
+            ),
+        ];
+        let read = |frame: &[HookEvent], durability| {
+            read_stored(env.host(), frame, &contract, durability, &key).unwrap()
+        };
+
+        // Writes the active call made are not in the ledger yet
+        assert_eq!(read(&frame, Durability::Instance), Some(ScVal::I32(1)));
+        assert_eq!(read(&frame, Durability::Persistent), None);
+        frame.push(host_call("del_contract_data", vec![key.clone(), instance]));
+        assert_eq!(read(&frame, Durability::Instance), None);
+
+        // Calls to host functions the debugger does not know are skipped
+        frame.push(HookEvent {
+            contract: contract.clone(),
+            hook: Hook::Host(u32::MAX),
+            args: vec![key.clone()],
+        });
+        assert_eq!(read(&frame, Durability::Instance), None);
+    }
 }
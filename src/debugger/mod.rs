@@ -1,9 +1,17 @@
 pub mod breakpoint;
+pub mod condition;
 pub mod engine;
+pub mod observer;
 pub mod state;
 pub mod stepper;
+pub mod watch;
+pub mod worker;
 
-pub use breakpoint::BreakpointManager;
-pub use engine::DebuggerEngine;
+pub use breakpoint::{Breakpoint, BreakpointKind, BreakpointManager, Failure};
+pub use condition::Condition;
+pub use engine::{DebugContext, DebuggerEngine, ExecutionPoint, HostCall, PauseReason};
+pub use observer::{DebugObserver, StdoutObserver};
 pub use state::DebugState;
-pub use stepper::Stepper;
+pub use stepper::{StepGranularity, StepKind, Stepper};
+pub use watch::{Watch, WatchManager};
+pub use worker::EngineWorker;
@@ -0,0 +1,108 @@
+use crate::debugger::breakpoint::Breakpoint;
+use crate::debugger::engine::{ExecutionPoint, HostCall, PauseReason};
+use crate::inspector::BudgetInfo;
+use crate::runtime::events::ContractEvent;
+use crate::runtime::executor::InvocationResult;
+use crate::runtime::storage::StorageAccess;
+use crate::Result;
+use soroban_sdk::xdr::ScAddress;
+
+/// Sees what the invocations of a [`DebuggerEngine`] do, for tools embedding
+/// it. Every method does nothing by default.
+///
+/// Host calls, storage accesses, events and budget updates during an
+/// invocation are reported by hooks on the contracts' host calls. Adding an
+/// observer that [needs them](Self::needs_host_calls) instruments the
+/// session's contracts with these hooks, as stepping does. Modules that cannot
+/// be rewritten report none.
+///
+/// [`DebuggerEngine`]: crate::debugger::engine::DebuggerEngine
+pub trait DebugObserver {
+    /// Whether the observer follows host calls, storage accesses, events
+    /// and budget updates during invocations
+    fn needs_host_calls(&self) -> bool {
+        true
+    }
+
+    /// An invocation of a function, with its JSON arguments, is starting
+    fn on_invocation_start(&self, _function: &str, _args: Option<&str>) {}
+
+    /// An invocation ended, returning a value or failing
+    fn on_invocation_end(&self, _function: &str, _result: &Result<InvocationResult>) {}
+
+    /// Execution stopped at a breakpoint, including catch breakpoints and
+    /// watches
+    fn on_breakpoint(&self, _reason: &PauseReason, _point: &ExecutionPoint) {}
+
+    /// A logpoint was reached in a function, or at a call to a host
+    /// function, with its message
+    fn on_log(&self, _function: &str, _message: &str) {}
+
+    /// The condition of a breakpoint could not be evaluated, so it pauses
+    /// as if the condition held
+    fn on_condition_error(&self, _breakpoint: &Breakpoint, _error: &str) {}
+
+    /// A contract is about to call a host function
+    fn on_host_call(&self, _point: &ExecutionPoint, _call: &HostCall) {}
+
+    /// A contract is about to access one of its storage entries
+    fn on_storage_access(&self, _contract: &ScAddress, _access: &StorageAccess) {}
+
+    /// A contract is publishing an event. Events are reported as they are
+    /// published, so those of calls that fail later and are rolled back are
+    /// reported too.
+    fn on_event(&self, _event: &ContractEvent) {}
+
+    /// Budget used so far by the invocation, reported at every host call and
+    /// when it ends. Like reported costs, it leaves out an estimate of what
+    /// the hooks cost.
+    fn on_budget(&self, _budget: &BudgetInfo) {}
+}
+
+/// Prints logpoint messages and condition errors, and optionally the
+/// breakpoints execution stops at, to standard output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutObserver {
+    pauses: bool,
+}
+
+impl StdoutObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also print where breakpoints stop, for runs nobody takes commands in
+    pub fn with_pauses(mut self) -> Self {
+        self.pauses = true;
+        self
+    }
+}
+
+impl DebugObserver for StdoutObserver {
+    fn needs_host_calls(&self) -> bool {
+        false
+    }
+
+    fn on_breakpoint(&self, reason: &PauseReason, point: &ExecutionPoint) {
+        if !self.pauses {
+            return;
+        }
+        match reason {
+            PauseReason::Failure(failure) => println!("\n[CATCH] {} at {}", failure, point),
+            PauseReason::Watch(access) => println!("\n[WATCH] {}", access),
+            _ if point.entry => println!("\n[BREAKPOINT] Paused at function: {}", point.function),
+            _ => println!("\n[BREAKPOINT] Paused at {}", point),
+        }
+    }
+
+    fn on_log(&self, function: &str, message: &str) {
+        println!("[LOG] {}: {}", function, message);
+    }
+
+    fn on_condition_error(&self, breakpoint: &Breakpoint, error: &str) {
+        println!(
+            "Error in condition of breakpoint at {}: {}",
+            breakpoint, error
+        );
+    }
+}
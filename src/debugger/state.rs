@@ -1,8 +1,19 @@
-/// Represents the current state of the debugger
+use crate::debugger::engine::PauseReason;
+use serde_json::Value as Json;
+use soroban_sdk::xdr::ScAddress;
+
+/// Represents the current state of the debugger: where the running
+/// invocation is paused and why, and how the last one ended
 #[derive(Debug, Clone)]
 pub struct DebugState {
     current_function: Option<String>,
     step_count: usize,
+    contract: Option<ScAddress>,
+    depth: usize,
+    args: Vec<(String, Json)>,
+    pause_reason: Option<PauseReason>,
+    last_result: Option<Json>,
+    last_error: Option<String>,
 }
 
 impl DebugState {
@@ -11,6 +22,12 @@ impl DebugState {
         Self {
             current_function: None,
             step_count: 0,
+            contract: None,
+            depth: 0,
+            args: Vec::new(),
+            pause_reason: None,
+            last_result: None,
+            last_error: None,
         }
     }
 
@@ -24,20 +41,85 @@ impl DebugState {
         self.current_function.as_deref()
     }
 
-    /// Increment step count
-    pub fn increment_step(&mut self) {
-        self.step_count += 1;
+    /// Move to a position in the timeline of the invocation's hook calls
+    pub fn set_step(&mut self, position: usize) {
+        self.step_count = position;
     }
 
-    /// Get current step count
+    /// Position of the current point in the timeline of the invocation's
+    /// hook calls, which `goto` takes
     pub fn step_count(&self) -> usize {
         self.step_count
     }
 
-    /// Reset the state
+    /// Set the call the current point is in: its contract, its depth
+    /// counting the invoked function as 1, and its decoded arguments
+    pub fn set_frame(&mut self, contract: ScAddress, depth: usize, args: Vec<(String, Json)>) {
+        self.contract = Some(contract);
+        self.depth = depth;
+        self.args = args;
+    }
+
+    /// Address of the contract the current point is in
+    pub fn contract(&self) -> Option<&ScAddress> {
+        self.contract.as_ref()
+    }
+
+    /// Call depth of the current point, or 0 outside an invocation
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Arguments of the call the current point is in, named by the contract
+    /// spec or by position
+    pub fn args(&self) -> &[(String, Json)] {
+        &self.args
+    }
+
+    /// Set why the invocation is paused, or that it is not
+    pub fn set_pause_reason(&mut self, reason: Option<PauseReason>) {
+        self.pause_reason = reason;
+    }
+
+    /// Why the invocation is paused, if it is
+    pub fn pause_reason(&self) -> Option<&PauseReason> {
+        self.pause_reason.as_ref()
+    }
+
+    /// Decoded return value of the last invocation, if it succeeded
+    pub fn last_result(&self) -> Option<&Json> {
+        self.last_result.as_ref()
+    }
+
+    /// Why the last invocation failed, if it did
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// End the invocation, keeping its return value or error
+    pub fn finish(&mut self, outcome: std::result::Result<Json, String>) {
+        self.reset();
+        match outcome {
+            Ok(value) => {
+                self.last_result = Some(value);
+                self.last_error = None;
+            }
+            Err(error) => {
+                self.last_result = None;
+                self.last_error = Some(error);
+            }
+        }
+    }
+
+    /// Reset the state of the current invocation, keeping how the last one
+    /// ended
     pub fn reset(&mut self) {
         self.current_function = None;
         self.step_count = 0;
+        self.contract = None;
+        self.depth = 0;
+        self.args.clear();
+        self.pause_reason = None;
     }
 }
 
@@ -46,3 +128,63 @@ impl Default for DebugState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use soroban_sdk::xdr::Hash;
+
+    /// A state paused at a breakpoint in `transfer`
+    fn paused() -> DebugState {
+        let mut state = DebugState::new();
+        state.set_current_function("transfer".to_string());
+        state.set_step(12);
+        state.set_frame(
+            ScAddress::Contract(Hash([1; 32])),
+            2,
+            vec![("amount".to_string(), json!(5))],
+        );
+        state.set_pause_reason(Some(PauseReason::Breakpoint));
+        state
+    }
+
+    #[test]
+    fn test_pause() {
+        let state = paused();
+        assert_eq!(state.current_function(), Some("transfer"));
+        assert_eq!(state.step_count(), 12);
+        assert_eq!(state.contract(), Some(&ScAddress::Contract(Hash([1; 32]))));
+        assert_eq!(state.depth(), 2);
+        assert_eq!(state.args(), [("amount".to_string(), json!(5))]);
+        assert_eq!(state.pause_reason(), Some(&PauseReason::Breakpoint));
+    }
+
+    #[test]
+    fn test_resume_clears_reason() {
+        let mut state = paused();
+        state.set_pause_reason(None);
+        assert_eq!(state.pause_reason(), None);
+        // The point stays until the invocation pauses again or ends
+        assert_eq!(state.current_function(), Some("transfer"));
+    }
+
+    #[test]
+    fn test_finish_records_outcome() {
+        let mut state = paused();
+        state.finish(Ok(json!(["Hello", "x"])));
+        assert_eq!(state.last_result(), Some(&json!(["Hello", "x"])));
+        assert_eq!(state.last_error(), None);
+        assert_eq!(state.pause_reason(), None);
+        assert_eq!(state.current_function(), None);
+        assert_eq!(state.depth(), 0);
+        assert!(state.args().is_empty());
+
+        // A new invocation keeps the outcome until it ends too
+        state.reset();
+        assert_eq!(state.last_result(), Some(&json!(["Hello", "x"])));
+        state.finish(Err("Contract error code: 1".to_string()));
+        assert_eq!(state.last_result(), None);
+        assert_eq!(state.last_error(), Some("Contract error code: 1"));
+    }
+}
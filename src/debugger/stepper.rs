@@ -1,32 +1,371 @@
-/// Handles step-through execution of contracts
-/// This will be implemented in Phase 2
+use crate::runtime::instrumentation::Hook;
+use crate::runtime::probe::HookEvent;
+use crate::DebuggerError;
+use soroban_sdk::xdr::ScAddress;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a single step moves over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StepGranularity {
+    /// Stop at every WASM instruction
+    #[default]
+    Instruction,
+    /// Stop at every WASM function entry
+    Function,
+    /// Stop before every call to a host function
+    HostCall,
+}
+
+impl FromStr for StepGranularity {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "instruction" | "instr" => Ok(StepGranularity::Instruction),
+            "function" | "func" => Ok(StepGranularity::Function),
+            "host" => Ok(StepGranularity::HostCall),
+            _ => Err(DebuggerError::InvalidArguments(format!(
+                "unknown step mode '{}' (expected instruction, function or host)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for StepGranularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StepGranularity::Instruction => "instruction",
+            StepGranularity::Function => "function",
+            StepGranularity::HostCall => "host",
+        })
+    }
+}
+
+/// Which way a step goes relative to calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// Stop at the next position, entering calls
+    Into,
+    /// Stop at the next position in the current function or a caller
+    Over,
+    /// Stop at the next position in a caller
+    Out,
+}
+
+/// Handles step-through execution of contracts.
+///
+/// The stepper sees the hook calls of an invocation as they happen. Function
+/// entries and exits give the call depth at each of them, so stepping over or
+/// out of a call skips everything nested in it, including calls into other
+/// contracts.
+#[derive(Debug, Clone, Default)]
 pub struct Stepper {
-    // TODO: Implement stepping logic
+    timeline: Vec<HookEvent>,
+    /// Call depth at each hook call, counting the function it is in
+    depths: Vec<usize>,
+    depth: usize,
+    granularity: StepGranularity,
+    position: Option<usize>,
+    /// Deepest call the requested step may stop in
+    target: Option<usize>,
 }
 
 impl Stepper {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
+    }
+
+    /// Forget the last invocation. A step requested before the next one
+    /// starts stops at its first position.
+    pub fn reset(&mut self) {
+        self.timeline.clear();
+        self.depths.clear();
+        self.depth = 0;
+        self.position = None;
+    }
+
+    /// Add the next hook call of the running invocation, returning its
+    /// position
+    pub fn record(&mut self, event: HookEvent) -> usize {
+        let depth = match event.hook {
+            Hook::Enter(_) => {
+                self.depth += 1;
+                self.depth
+            }
+            Hook::Exit(_) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.depth + 1
+            }
+            Hook::Block(_) | Hook::Step(_) | Hook::Host(_) | Hook::Trap(_) => self.depth,
+        };
+        self.timeline.push(event);
+        self.depths.push(depth);
+        self.timeline.len() - 1
+    }
+
+    pub fn granularity(&self) -> StepGranularity {
+        self.granularity
+    }
+
+    pub fn set_granularity(&mut self, granularity: StepGranularity) {
+        self.granularity = granularity;
+    }
+
+    /// Hook calls of the invocation so far
+    pub fn timeline(&self) -> &[HookEvent] {
+        &self.timeline
+    }
+
+    /// Index of the hook call execution is stopped at, if any
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// The hook call execution is stopped at
+    pub fn current(&self) -> Option<&HookEvent> {
+        self.timeline.get(self.position?)
+    }
+
+    /// Call depth at the current hook call; 0 outside of any function
+    pub fn depth(&self) -> usize {
+        self.position.map_or(0, |p| self.depths[p])
+    }
+
+    /// Call depth at a recorded hook call
+    pub fn depth_at(&self, position: usize) -> usize {
+        self.depths[position]
     }
 
-    /// Step into next instruction
-    pub fn step_into(&mut self) {
-        // TODO: Implement
+    /// Entry of the function a hook call is in, and the last instruction that
+    /// function reached before it
+    pub fn frame_of(&self, position: usize) -> (Option<&HookEvent>, Option<&HookEvent>) {
+        let depth = self.depths[position];
+        let mut instruction = None;
+        for index in (0..position).rev() {
+            if self.depths[index] < depth {
+                break;
+            }
+            if self.depths[index] > depth {
+                continue;
+            }
+            match self.timeline[index].hook {
+                Hook::Enter(_) => return (Some(&self.timeline[index]), instruction),
+                Hook::Step(_) if instruction.is_none() => {
+                    instruction = Some(&self.timeline[index]);
+                }
+                _ => {}
+            }
+        }
+        (None, instruction)
     }
 
-    /// Step over function call
-    pub fn step_over(&mut self) {
-        // TODO: Implement
+    /// Hook calls made since the outermost active call into a contract
+    /// started, up to a position. The host keeps the contract's instance
+    /// storage in that call's frame until it returns.
+    pub fn calls_since_entered(&self, contract: &ScAddress, position: usize) -> &[HookEvent] {
+        let timeline = &self.timeline;
+        let mut start = position;
+        // An entry starts a call rather than being in one
+        let mut depth = match timeline[position].hook {
+            Hook::Enter(_) => self.depth_at(position).saturating_sub(1),
+            _ => self.depth_at(position),
+        };
+        for index in (0..position).rev() {
+            if depth == 0 {
+                break;
+            }
+            if matches!(timeline[index].hook, Hook::Enter(_)) && self.depth_at(index) == depth {
+                if timeline[index].contract == *contract {
+                    start = index;
+                }
+                depth -= 1;
+            }
+        }
+        &timeline[start..position]
     }
 
-    /// Step out of current function
-    pub fn step_out(&mut self) {
-        // TODO: Implement
+    /// Stop at a hook call, such as a breakpoint's, ending any step
+    pub fn stop_at(&mut self, position: usize) {
+        if position < self.timeline.len() {
+            self.position = Some(position);
+            self.target = None;
+        }
+    }
+
+    /// Request a step from the current position
+    pub fn step(&mut self, kind: StepKind) {
+        self.target = Some(Self::target(kind, self.depth()));
+    }
+
+    /// Deepest call a step from a depth may stop in
+    fn target(kind: StepKind, depth: usize) -> usize {
+        match kind {
+            StepKind::Into => usize::MAX,
+            StepKind::Over => depth,
+            StepKind::Out => depth.saturating_sub(1),
+        }
+    }
+
+    /// Drop the requested step and let execution run
+    pub fn resume(&mut self) {
+        self.target = None;
+        self.position = None;
+    }
+
+    /// Whether the requested step ends at a recorded hook call
+    pub fn reached(&self, position: usize) -> bool {
+        self.target
+            .is_some_and(|target| self.stops_at(position, target))
+    }
+
+    /// Where a step from a recorded hook call would end, looking no further
+    /// than another one
+    pub fn next_stop(&self, from: usize, kind: StepKind, until: usize) -> Option<usize> {
+        let target = Self::target(kind, self.depths[from]);
+        (from + 1..=until.min(self.timeline.len() - 1)).find(|&p| self.stops_at(p, target))
+    }
+
+    /// The last hook call before a recorded one that a step into could have
+    /// stopped at
+    pub fn previous_stop(&self, from: usize) -> Option<usize> {
+        (0..from).rev().find(|&p| self.stops_at(p, usize::MAX))
+    }
+
+    fn stops_at(&self, position: usize, target: usize) -> bool {
+        let stop = matches!(
+            (self.granularity, &self.timeline[position].hook),
+            (StepGranularity::Instruction, Hook::Step(_))
+                | (StepGranularity::Function, Hook::Enter(_))
+                | (StepGranularity::HostCall, Hook::Host(_))
+        );
+        stop && self.depths[position] <= target
     }
 }
 
-impl Default for Stepper {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::xdr::{Hash, ScAddress};
+
+    /// `main` runs step 0, calls `inner` (steps 1 and 2) and runs step 3
+    const NESTED: [Hook; 8] = [
+        Hook::Enter(0),
+        Hook::Step(0),
+        Hook::Enter(1),
+        Hook::Step(1),
+        Hook::Step(2),
+        Hook::Exit(1),
+        Hook::Step(3),
+        Hook::Exit(0),
+    ];
+
+    /// Take a step, feeding hook calls after the recorded ones until it ends
+    fn step(stepper: &mut Stepper, kind: StepKind) -> Option<Hook> {
+        stepper.step(kind);
+        let contract = ScAddress::Contract(Hash([0; 32]));
+        for &hook in &NESTED[stepper.timeline().len()..] {
+            let position = stepper.record(HookEvent {
+                contract: contract.clone(),
+                hook,
+                args: Vec::new(),
+            });
+            if stepper.reached(position) {
+                stepper.stop_at(position);
+                return Some(hook);
+            }
+        }
+        stepper.resume();
+        None
+    }
+
+    #[test]
+    fn test_step_into() {
+        let mut stepper = Stepper::new();
+        let mut steps = Vec::new();
+        while let Some(hook) = step(&mut stepper, StepKind::Into) {
+            steps.push(hook);
+        }
+        assert_eq!(
+            steps,
+            vec![Hook::Step(0), Hook::Step(1), Hook::Step(2), Hook::Step(3)]
+        );
+        assert_eq!(stepper.position(), None);
+    }
+
+    #[test]
+    fn test_step_over_and_out() {
+        let mut stepper = Stepper::new();
+        step(&mut stepper, StepKind::Into);
+        assert_eq!(stepper.depth(), 1);
+        assert_eq!(step(&mut stepper, StepKind::Over), Some(Hook::Step(3)));
+
+        let mut stepper = Stepper::new();
+        step(&mut stepper, StepKind::Into);
+        step(&mut stepper, StepKind::Into);
+        assert_eq!(stepper.depth(), 2);
+        assert_eq!(step(&mut stepper, StepKind::Out), Some(Hook::Step(3)));
+        assert_eq!(step(&mut stepper, StepKind::Out), None);
+    }
+
+    #[test]
+    fn test_recorded_stops() {
+        let mut stepper = Stepper::new();
+        while step(&mut stepper, StepKind::Into).is_some() {}
+
+        // Steps 0 to 3 are at positions 1, 3, 4 and 6
+        assert_eq!(stepper.next_stop(1, StepKind::Into, 7), Some(3));
+        assert_eq!(stepper.next_stop(1, StepKind::Over, 7), Some(6));
+        assert_eq!(stepper.next_stop(1, StepKind::Over, 5), None);
+        assert_eq!(stepper.previous_stop(6), Some(4));
+        assert_eq!(stepper.previous_stop(1), None);
+    }
+
+    #[test]
+    fn test_function_granularity() {
+        let mut stepper = Stepper::new();
+        stepper.set_granularity(StepGranularity::Function);
+        assert_eq!(step(&mut stepper, StepKind::Into), Some(Hook::Enter(0)));
+        assert_eq!(step(&mut stepper, StepKind::Into), Some(Hook::Enter(1)));
+        assert_eq!(step(&mut stepper, StepKind::Into), None);
+
+        // Step 3 follows the call to `inner`, in `main`
+        let (entry, instruction) = stepper.frame_of(6);
+        assert_eq!(entry.map(|e| e.hook), Some(Hook::Enter(0)));
+        assert_eq!(instruction.map(|e| e.hook), Some(Hook::Step(0)));
+    }
+
+    #[test]
+    fn test_calls_since_entered() {
+        // `a` calls `b`, then one of its own functions
+        let (a, b) = (
+            ScAddress::Contract(Hash([1; 32])),
+            ScAddress::Contract(Hash([2; 32])),
+        );
+        let mut stepper = Stepper::new();
+        for (contract, hook) in [
+            (&a, Hook::Enter(0)),
+            (&a, Hook::Step(0)),
+            (&b, Hook::Enter(0)),
+            (&b, Hook::Step(0)),
+            (&b, Hook::Exit(0)),
+            (&a, Hook::Enter(1)),
+            (&a, Hook::Step(1)),
+            (&a, Hook::Exit(1)),
+            (&a, Hook::Exit(0)),
+        ] {
+            stepper.record(HookEvent {
+                contract: contract.clone(),
+                hook,
+                args: Vec::new(),
+            });
+        }
+        assert_eq!(stepper.calls_since_entered(&a, 6).len(), 6);
+        assert_eq!(stepper.calls_since_entered(&a, 5).len(), 5);
+        assert_eq!(stepper.calls_since_entered(&b, 3).len(), 1);
+        assert_eq!(stepper.calls_since_entered(&b, 2).len(), 0);
+        // `b` has returned
+        assert!(stepper.calls_since_entered(&b, 6).is_empty());
     }
 }
@@ -0,0 +1,142 @@
+use crate::debugger::condition;
+use crate::runtime::storage::Durability;
+use crate::DebuggerError;
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A storage key whose accesses pause execution, in one kind of storage or
+/// any (`persistent Balance(alice)`, `Counter`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watch {
+    pub durability: Option<Durability>,
+    /// The key as the JSON the contract spec encodes
+    pub key: Json,
+    /// The key as written
+    source: String,
+    /// Times an access to the key paused execution
+    pub hits: u32,
+}
+
+impl Watch {
+    /// The key as written, without the durability
+    pub fn key_source(&self) -> &str {
+        &self.source
+    }
+
+    /// What identifies the watch, however its key is written
+    fn id(&self) -> String {
+        match self.durability {
+            Some(durability) => format!("{} {}", durability, self.key),
+            None => self.key.to_string(),
+        }
+    }
+}
+
+impl FromStr for Watch {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let (durability, source) = match s.split_once(char::is_whitespace) {
+            Some((first, rest)) => match first.parse::<Durability>() {
+                Ok(durability) => (Some(durability), rest.trim()),
+                Err(_) => (None, s),
+            },
+            None => (None, s),
+        };
+        Ok(Self {
+            durability,
+            key: condition::parse_key(source)?,
+            source: source.to_string(),
+            hits: 0,
+        })
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(durability) = self.durability {
+            write!(f, "{} ", durability)?;
+        }
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Manages the storage keys watched during debugging
+#[derive(Default)]
+pub struct WatchManager {
+    watches: BTreeMap<String, Watch>,
+}
+
+impl WatchManager {
+    /// Create a new watch manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a watch, replacing any other on the same key
+    pub fn insert(&mut self, watch: Watch) {
+        self.watches.insert(watch.id(), watch);
+    }
+
+    /// Remove the watch on the same key as another
+    pub fn remove(&mut self, watch: &Watch) -> bool {
+        self.watches.remove(&watch.id()).is_some()
+    }
+
+    /// All watches, by key
+    pub fn iter(&self) -> impl Iterator<Item = &Watch> {
+        self.watches.values()
+    }
+
+    /// Count a hit of a watch
+    pub fn hit(&mut self, watch: &Watch) {
+        if let Some(watch) = self.watches.get_mut(&watch.id()) {
+            watch.hits += 1;
+        }
+    }
+
+    /// List all watches with their hit counts
+    pub fn list(&self) -> Vec<String> {
+        self.watches
+            .values()
+            .map(|w| format!("{} (hits: {})", w, w.hits))
+            .collect()
+    }
+
+    /// Check if there are any watches set
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_watch() {
+        let watch: Watch = "persistent Balance(alice)".parse().unwrap();
+        assert_eq!(watch.durability, Some(Durability::Persistent));
+        assert_eq!(watch.key, json!({"Balance": "alice"}));
+        assert_eq!(watch.to_string(), "persistent Balance(alice)");
+
+        let watch: Watch = "Allowance(alice, bob)".parse().unwrap();
+        assert_eq!(watch.durability, None);
+        assert_eq!(watch.key, json!({"Allowance": ["alice", "bob"]}));
+
+        let watch: Watch = " Counter ".parse().unwrap();
+        assert_eq!(watch.key, json!("Counter"));
+        assert!("persistent Balance(alice".parse::<Watch>().is_err());
+        assert!("Balance(alice) extra".parse::<Watch>().is_err());
+
+        let mut watches = WatchManager::new();
+        watches.insert("Allowance(alice, bob)".parse().unwrap());
+        assert!(!watches.remove(&"persistent Allowance(alice,bob)".parse().unwrap()));
+        assert!(watches.remove(&"Allowance(alice,bob)".parse().unwrap()));
+        assert!(watches.is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::debugger::engine::{DebugContext, DebuggerEngine, ExecutionPoint, PauseReason};
+use crate::debugger::stepper::StepKind;
+use crate::runtime::executor::InvocationResult;
+use crate::{DebuggerError, Result};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// Stack size of the engine thread, as much as a main thread usually gets
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Code run on the engine thread with whatever context it is in
+pub type Task = Box<dyn FnOnce(&mut dyn DebugContext) + Send>;
+
+/// Something for a served engine to do
+pub enum Command {
+    /// Invoke a function of the selected contract, pausing at its first step
+    /// if `step` is set
+    Call {
+        function: String,
+        args: Option<String>,
+        step: bool,
+    },
+    /// Go on with the paused invocation until a step ends
+    Step(StepKind),
+    /// Go on with the paused invocation until a breakpoint or its end
+    Continue,
+    /// Go back to the last point before the current one a step could stop
+    /// at, in the paused invocation's history
+    Back,
+    /// Go back to the last point before the current one a breakpoint paused
+    /// at
+    ReverseContinue,
+    /// Go to a position in the paused invocation's history
+    Goto(usize),
+    /// Fail the paused invocation
+    Abort,
+    /// Run code with the engine, or with the paused invocation's context
+    Run(Task),
+}
+
+/// What a served engine reports back
+#[derive(Debug)]
+pub enum Event {
+    /// The running invocation paused, at position `step` of its timeline,
+    /// or shows an earlier position of it while replaying
+    Paused {
+        reason: PauseReason,
+        point: ExecutionPoint,
+        step: usize,
+        replaying: bool,
+    },
+    /// The invocation ended
+    Finished(Result<InvocationResult>),
+    /// The command does not apply to what the engine is doing
+    Rejected(String),
+}
+
+/// A debugger engine running on its own thread.
+///
+/// Contract environments can't move between threads, so the engine is built
+/// on the thread that runs it. Invocations block that thread while paused,
+/// leaving the caller free to send commands.
+pub struct EngineWorker {
+    commands: Option<Sender<Command>>,
+    events: Receiver<Event>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EngineWorker {
+    /// Start a thread serving the engine `build` creates on it
+    pub fn spawn<F>(build: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<DebuggerEngine> + Send + 'static,
+    {
+        let (commands, command_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        let (ready_tx, ready) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("debugger-engine".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn(move || match build() {
+                Ok(mut engine) => {
+                    let _ = ready_tx.send(Ok(()));
+                    engine.serve(command_rx, event_tx);
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                }
+            })?;
+        let worker = Self {
+            commands: Some(commands),
+            events,
+            thread: Some(thread),
+        };
+        ready.recv().map_err(|_| stopped())??;
+        Ok(worker)
+    }
+
+    /// Send a command to the engine
+    pub fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .as_ref()
+            .and_then(|commands| commands.send(command).ok())
+            .ok_or_else(stopped)
+    }
+
+    /// Wait for the engine to report something
+    pub fn recv(&self) -> Result<Event> {
+        self.events.recv().map_err(|_| stopped())
+    }
+
+    /// Run code with the engine, or with the paused invocation's context,
+    /// and wait for its result
+    pub fn with<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut dyn DebugContext) -> R + Send + 'static,
+    {
+        let (result_tx, result) = mpsc::channel();
+        self.send(Command::Run(Box::new(move |context| {
+            let _ = result_tx.send(f(context));
+        })))?;
+        result.recv().map_err(|_| stopped())
+    }
+}
+
+impl Drop for EngineWorker {
+    fn drop(&mut self) {
+        // Closing the command channel ends the engine, aborting any paused
+        // invocation
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn stopped() -> anyhow::Error {
+    DebuggerError::ExecutionError("The debugger engine stopped".to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::executor::ContractExecutor;
+    use crate::runtime::ledger::LedgerConfig;
+    use serde_json::json;
+
+    const TEST_CONTRACT: &[u8] = include_bytes!("../../tests/fixtures/test_contract.wasm");
+
+    /// A worker serving the test contract instrumented for stepping, as
+    /// interactive sessions are, with a breakpoint on `add`
+    fn worker() -> EngineWorker {
+        EngineWorker::spawn(|| {
+            let mut executor = ContractExecutor::empty(&LedgerConfig::default(), None)?;
+            executor.enable_stepping()?;
+            executor.register_contract("contract", TEST_CONTRACT, None, None)?;
+            executor.select_contract("contract")?;
+            let mut engine = DebuggerEngine::new(executor, vec![])?;
+            engine.breakpoints_mut().insert("add".parse()?);
+            Ok(engine)
+        })
+        .unwrap()
+    }
+
+    fn call(worker: &EngineWorker, function: &str, args: &str, step: bool) {
+        worker
+            .send(Command::Call {
+                function: function.to_string(),
+                args: Some(args.to_string()),
+                step,
+            })
+            .unwrap();
+    }
+
+    /// Wait for a pause, returning its reason, point and position
+    fn paused(worker: &EngineWorker) -> (PauseReason, ExecutionPoint, usize) {
+        match worker.recv().unwrap() {
+            Event::Paused {
+                reason,
+                point,
+                step,
+                replaying: false,
+            } => (reason, point, step),
+            other => panic!("expected a pause, got {:?}", other),
+        }
+    }
+
+    fn finished(worker: &EngineWorker) -> Result<InvocationResult> {
+        match worker.recv().unwrap() {
+            Event::Finished(result) => result,
+            other => panic!("expected the call to finish, got {:?}", other),
+        }
+    }
+
+    fn pause_reason(worker: &EngineWorker) -> Option<PauseReason> {
+        worker
+            .with(|ctx| ctx.state().pause_reason().cloned())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pause_step_and_continue() {
+        let worker = worker();
+        call(&worker, "add", "[1, 2]", false);
+        let (reason, point, position) = paused(&worker);
+        assert_eq!(reason, PauseReason::Breakpoint);
+        assert_eq!(point.function, "add");
+        assert!(point.entry);
+        assert_eq!(pause_reason(&worker), Some(PauseReason::Breakpoint));
+
+        // Another call has to wait for this one
+        call(&worker, "add", "[3, 4]", false);
+        assert!(matches!(worker.recv().unwrap(), Event::Rejected(_)));
+
+        worker.send(Command::Step(StepKind::Into)).unwrap();
+        let (reason, _, next) = paused(&worker);
+        assert_eq!(reason, PauseReason::Step);
+        assert!(next > position);
+
+        worker.send(Command::Continue).unwrap();
+        assert_eq!(finished(&worker).unwrap().json, json!(3));
+        assert_eq!(pause_reason(&worker), None);
+
+        // Commands for a paused call are rejected once it ended
+        worker.send(Command::Continue).unwrap();
+        assert!(matches!(worker.recv().unwrap(), Event::Rejected(_)));
+    }
+
+    #[test]
+    fn test_abort() {
+        let worker = worker();
+        call(&worker, "hello", r#"["x"]"#, true);
+        let (reason, _, _) = paused(&worker);
+        assert_eq!(reason, PauseReason::Step);
+        worker.send(Command::Abort).unwrap();
+        let error = finished(&worker).unwrap_err();
+        assert!(error.to_string().contains("aborted"));
+
+        // The engine takes calls again
+        call(&worker, "hello", r#"["x"]"#, false);
+        assert_eq!(finished(&worker).unwrap().json, json!(["Hello", "x"]));
+    }
+
+    #[test]
+    fn test_shutdown_while_paused() {
+        let worker = worker();
+        call(&worker, "add", "[1, 2]", false);
+        paused(&worker);
+        // Dropping the worker aborts the call and joins the thread
+        drop(worker);
+    }
+
+    #[test]
+    fn test_spawn_reports_build_errors() {
+        let worker = EngineWorker::spawn(|| {
+            Err(DebuggerError::WasmLoadError("missing.wasm".to_string()).into())
+        });
+        let error = worker.err().unwrap();
+        assert!(error.to_string().contains("missing.wasm"));
+    }
+}
@@ -22,8 +22,11 @@ impl BudgetInspector {
 
     /// Display budget information
     pub fn display(host: &Host) {
-        let info = Self::get_cpu_usage(host);
+        Self::display_info(&Self::get_cpu_usage(host));
+    }
 
+    /// Display budget information taken earlier
+    pub fn display_info(info: &BudgetInfo) {
         println!("Resource Budget:");
         println!(
             "  CPU: {} / {} ({:.1}%)",
@@ -58,6 +61,23 @@ pub struct BudgetInfo {
 }
 
 impl BudgetInfo {
+    /// Which limit the usage went over, for an invocation that ran out of
+    /// budget. The VM stops short of a limit when the next block would go
+    /// over it, so this is the limit the usage is closest to.
+    pub fn exceeded(&self) -> String {
+        if self.memory_percentage() > self.cpu_percentage() {
+            format!(
+                "memory budget exceeded ({} of {} bytes)",
+                self.memory_bytes, self.memory_limit
+            )
+        } else {
+            format!(
+                "CPU budget exceeded ({} of {} instructions)",
+                self.cpu_instructions, self.cpu_limit
+            )
+        }
+    }
+
     /// Calculate CPU usage percentage
     pub fn cpu_percentage(&self) -> f64 {
         if self.cpu_limit == 0 {
@@ -1,20 +1,24 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Inspects and displays contract storage
 pub struct StorageInspector {
-    // Storage will be tracked here
-    storage: HashMap<String, String>,
+    storage: BTreeMap<String, String>,
 }
 
 impl StorageInspector {
     pub fn new() -> Self {
         Self {
-            storage: HashMap::new(),
+            storage: BTreeMap::new(),
         }
     }
 
+    /// Add an entry to display
+    pub fn insert(&mut self, key: String, value: String) {
+        self.storage.insert(key, value);
+    }
+
     /// Get all storage entries
-    pub fn get_all(&self) -> &HashMap<String, String> {
+    pub fn get_all(&self) -> &BTreeMap<String, String> {
         &self.storage
     }
 
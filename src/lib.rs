@@ -21,6 +21,9 @@ pub enum DebuggerError {
     #[error("Failed to execute contract: {0}")]
     ExecutionError(String),
 
+    #[error("Failed to execute contract: Contract execution aborted: {0}")]
+    BudgetExceeded(String),
+
     #[error("Invalid function name: {0}")]
     InvalidFunction(String),
 
@@ -32,4 +35,7 @@ pub enum DebuggerError {
 
     #[error("Storage error: {0}")]
     StorageError(String),
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
 }
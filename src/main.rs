@@ -30,6 +30,9 @@ fn main() -> Result<()> {
         Commands::Optimize(args) => {
             soroban_debugger::cli::commands::optimize(args)?;
         }
+        Commands::Coverage(args) => {
+            soroban_debugger::cli::commands::coverage(args)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,324 @@
+use crate::runtime::contracts::{ContractRegistry, RegisteredContract};
+use crate::runtime::executor::InvocationResult;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Accumulates basic block hit counts across invocations.
+///
+/// Only contracts registered after [`enable_coverage`] have their blocks
+/// instrumented.
+///
+/// [`enable_coverage`]: crate::runtime::executor::ContractExecutor::enable_coverage
+#[derive(Debug, Clone, Default)]
+pub struct CoverageCollector {
+    /// Hits by contract alias and block id
+    hits: HashMap<String, HashMap<u32, u64>>,
+    invocations: u32,
+}
+
+impl CoverageCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the blocks that ran during an invocation
+    pub fn record(&mut self, result: &InvocationResult) {
+        self.invocations += 1;
+        for block in &result.blocks {
+            *self
+                .hits
+                .entry(block.contract.clone())
+                .or_default()
+                .entry(block.block)
+                .or_default() += block.hits;
+        }
+    }
+
+    /// Number of invocations recorded
+    pub fn invocations(&self) -> u32 {
+        self.invocations
+    }
+
+    /// Build a report covering every instrumented contract, including those
+    /// that never ran
+    pub fn report(&self, contracts: &ContractRegistry) -> CoverageReport {
+        let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+        let empty = HashMap::new();
+        for contract in contracts.iter().filter(|c| !c.blocks().is_empty()) {
+            let hits = self.hits.get(contract.alias()).unwrap_or(&empty);
+            for (path, file) in contract_coverage(contract, hits) {
+                files.entry(path).or_default().merge(file);
+            }
+        }
+        CoverageReport { files }
+    }
+}
+
+/// Coverage of one contract by file. Blocks the source map resolves are
+/// attributed to source lines; the others go to a file named after the
+/// contract, with block ids as line numbers.
+fn contract_coverage(
+    contract: &RegisteredContract,
+    hits: &HashMap<u32, u64>,
+) -> BTreeMap<String, FileCoverage> {
+    let fallback = format!("{}.wasm", contract.alias());
+    let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+    let mut entries: BTreeMap<u32, (String, usize, u64)> = BTreeMap::new();
+
+    for (id, block) in contract.blocks().iter().enumerate() {
+        let count = hits.get(&(id as u32)).copied().unwrap_or(0);
+        let location = contract
+            .source_map()
+            .zip(block.offset)
+            .and_then(|(map, offset)| map.get_location(offset));
+        let (path, line) = match location {
+            Some(location) => (location.file, location.line),
+            None => (fallback.clone(), id + 1),
+        };
+        // Blocks sharing a line ran as often as the busiest of them
+        let lines = &mut files.entry(path.clone()).or_default().lines;
+        let line_hits = lines.entry(line).or_default();
+        *line_hits = (*line_hits).max(count);
+        // A function's first block is its body, which runs once per call
+        entries.entry(block.function).or_insert((path, line, count));
+    }
+
+    for (index, (path, line, count)) in entries {
+        if let Some(file) = files.get_mut(&path) {
+            file.functions.push(FunctionCoverage {
+                name: contract.function_name(index),
+                line,
+                hits: count,
+            });
+        }
+    }
+    files
+}
+
+/// Coverage of a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    pub name: String,
+    /// Line of the function's first block
+    pub line: usize,
+    /// Number of calls
+    pub hits: u64,
+}
+
+/// Coverage of a source file, or of the unmapped code of a contract
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub functions: Vec<FunctionCoverage>,
+    /// Hits by line number
+    pub lines: BTreeMap<usize, u64>,
+}
+
+impl FileCoverage {
+    /// Lines with at least one hit
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&h| h > 0).count()
+    }
+
+    fn merge(&mut self, other: FileCoverage) {
+        // Contracts built from the same source share its functions
+        for function in other.functions {
+            match self
+                .functions
+                .iter_mut()
+                .find(|f| f.name == function.name && f.line == function.line)
+            {
+                Some(existing) => existing.hits += function.hits,
+                None => self.functions.push(function),
+            }
+        }
+        for (line, hits) in other.lines {
+            *self.lines.entry(line).or_default() += hits;
+        }
+    }
+}
+
+/// Line and function coverage by file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+impl CoverageReport {
+    /// Total number of lines and lines hit
+    pub fn line_totals(&self) -> (usize, usize) {
+        self.files.values().fold((0, 0), |(found, hit), file| {
+            (found + file.lines.len(), hit + file.lines_hit())
+        })
+    }
+
+    /// Render the report as an lcov tracefile
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (path, file) in &self.files {
+            let _ = writeln!(out, "TN:");
+            let _ = writeln!(out, "SF:{}", path);
+            for function in &file.functions {
+                let _ = writeln!(out, "FN:{},{}", function.line, function.name);
+            }
+            for function in &file.functions {
+                let _ = writeln!(out, "FNDA:{},{}", function.hits, function.name);
+            }
+            let _ = writeln!(out, "FNF:{}", file.functions.len());
+            let _ = writeln!(
+                out,
+                "FNH:{}",
+                file.functions.iter().filter(|f| f.hits > 0).count()
+            );
+            for (line, hits) in &file.lines {
+                let _ = writeln!(out, "DA:{},{}", line, hits);
+            }
+            let _ = writeln!(out, "LF:{}", file.lines.len());
+            let _ = writeln!(out, "LH:{}", file.lines_hit());
+            let _ = writeln!(out, "end_of_record");
+        }
+        out
+    }
+
+    /// Render the report as Cobertura XML, with a class per file
+    pub fn to_cobertura(&self) -> String {
+        let (found, hit) = self.line_totals();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let mut out = String::new();
+        let _ = writeln!(out, r#"<?xml version="1.0" ?>"#);
+        let _ = writeln!(
+            out,
+            r#"<coverage line-rate="{}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="{}" timestamp="{}">"#,
+            rate(hit, found),
+            hit,
+            found,
+            env!("CARGO_PKG_VERSION"),
+            timestamp
+        );
+        let _ = writeln!(out, "  <sources><source>.</source></sources>");
+        let _ = writeln!(out, "  <packages>");
+        let _ = writeln!(
+            out,
+            r#"    <package name="contracts" line-rate="{}" branch-rate="0" complexity="0">"#,
+            rate(hit, found)
+        );
+        let _ = writeln!(out, "      <classes>");
+        for (path, file) in &self.files {
+            let _ = writeln!(
+                out,
+                r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                escape(path),
+                escape(path),
+                rate(file.lines_hit(), file.lines.len())
+            );
+            let _ = writeln!(out, "          <methods>");
+            for function in &file.functions {
+                let _ = writeln!(
+                    out,
+                    r#"            <method name="{}" signature="" line-rate="{}" branch-rate="0" complexity="0">"#,
+                    escape(&function.name),
+                    rate(usize::from(function.hits > 0), 1)
+                );
+                let _ = writeln!(
+                    out,
+                    r#"              <lines><line number="{}" hits="{}"/></lines>"#,
+                    function.line, function.hits
+                );
+                let _ = writeln!(out, "            </method>");
+            }
+            let _ = writeln!(out, "          </methods>");
+            let _ = writeln!(out, "          <lines>");
+            for (line, hits) in &file.lines {
+                let _ = writeln!(
+                    out,
+                    r#"            <line number="{}" hits="{}"/>"#,
+                    line, hits
+                );
+            }
+            let _ = writeln!(out, "          </lines>");
+            let _ = writeln!(out, "        </class>");
+        }
+        let _ = writeln!(out, "      </classes>");
+        let _ = writeln!(out, "    </package>");
+        let _ = writeln!(out, "  </packages>");
+        let _ = writeln!(out, "</coverage>");
+        out
+    }
+}
+
+fn rate(hit: usize, found: usize) -> String {
+    if found == 0 {
+        return "1".to_string();
+    }
+    format!("{:.4}", hit as f64 / found as f64)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> CoverageReport {
+        let mut lines = BTreeMap::new();
+        lines.insert(1, 2);
+        lines.insert(2, 0);
+        let file = FileCoverage {
+            functions: vec![FunctionCoverage {
+                name: "incr".to_string(),
+                line: 1,
+                hits: 2,
+            }],
+            lines,
+        };
+        CoverageReport {
+            files: BTreeMap::from([("counter.wasm".to_string(), file)]),
+        }
+    }
+
+    #[test]
+    fn test_lcov_output() {
+        let lcov = sample().to_lcov();
+        assert_eq!(
+            lcov,
+            "TN:\nSF:counter.wasm\nFN:1,incr\nFNDA:2,incr\nFNF:1\nFNH:1\n\
+             DA:1,2\nDA:2,0\nLF:2\nLH:1\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_sums_shared_functions() {
+        let mut file = sample().files.remove("counter.wasm").unwrap();
+        let mut other = file.clone();
+        other.functions.push(FunctionCoverage {
+            name: "reset".to_string(),
+            line: 2,
+            hits: 0,
+        });
+        file.merge(other);
+        assert_eq!(file.functions.len(), 2);
+        assert_eq!(file.functions[0].hits, 4);
+        assert_eq!(file.lines, BTreeMap::from([(1, 4), (2, 0)]));
+        assert_eq!(file.functions[1].name, "reset");
+    }
+
+    #[test]
+    fn test_cobertura_output() {
+        let report = sample();
+        assert_eq!(report.line_totals(), (2, 1));
+        let xml = report.to_cobertura();
+        assert!(xml.contains(r#"<coverage line-rate="0.5000""#));
+        assert!(xml.contains(r#"<class name="counter.wasm" filename="counter.wasm""#));
+        assert!(xml.contains(r#"<method name="incr" signature="" line-rate="1.0000""#));
+        assert!(xml.contains(r#"<line number="2" hits="0"/>"#));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod analyzer;
+pub mod coverage;
 
 pub use analyzer::{GasOptimizer, OptimizationReport, OptimizationSuggestion};
+pub use coverage::{CoverageCollector, CoverageReport};
@@ -0,0 +1,308 @@
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use soroban_env_host::budget::AsBudget;
+use soroban_env_host::HostError;
+use soroban_sdk::xdr::{
+    AccountEntry, AccountEntryExt, AccountFlags, AccountId, AlphaNum12, AlphaNum4, Asset,
+    AssetCode12, AssetCode4, ContractExecutable, ContractIdPreimage, CreateContractArgs,
+    HostFunction, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
+    LedgerKeyTrustLine, PublicKey, ScAddress, ScVal, SequenceNumber, Thresholds, TrustLineAsset,
+    TrustLineEntry, TrustLineEntryExt, TrustLineFlags, Uint256,
+};
+use soroban_sdk::{Address, Bytes, Env, TryFromVal};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// A Stellar Asset Contract to deploy, from an `[[assets]]` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetConfig {
+    /// Name usable in place of the asset contract address in arguments
+    pub alias: String,
+    /// `native`, `CODE` or `CODE:ISSUER`, where the issuer may be an account alias
+    pub asset: String,
+    /// Admin address or alias; defaults to the issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<String>,
+    /// Initial balances by holder address or alias
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub balances: BTreeMap<String, Json>,
+    /// Holders whose authorization flag is set explicitly
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub authorized: BTreeMap<String, bool>,
+    /// Flags set on the issuer account
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issuer_flags: Vec<IssuerFlag>,
+}
+
+/// Issuer account flags controlling trustline authorization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssuerFlag {
+    /// New trustlines start unauthorized
+    AuthRequired,
+    /// Authorization can be revoked with `set_authorized(id, false)`
+    AuthRevocable,
+    /// Flags can no longer be changed
+    AuthImmutable,
+    /// Balances can be clawed back by the admin
+    AuthClawbackEnabled,
+}
+
+impl IssuerFlag {
+    /// Bit mask in the account entry's flags
+    pub fn mask(self) -> u32 {
+        let flag = match self {
+            IssuerFlag::AuthRequired => AccountFlags::RequiredFlag,
+            IssuerFlag::AuthRevocable => AccountFlags::RevocableFlag,
+            IssuerFlag::AuthImmutable => AccountFlags::ImmutableFlag,
+            IssuerFlag::AuthClawbackEnabled => AccountFlags::ClawbackEnabledFlag,
+        };
+        flag as u32
+    }
+}
+
+/// The asset a Stellar Asset Contract wraps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetKind {
+    Native,
+    Issued {
+        code: String,
+        issuer: Option<String>,
+    },
+}
+
+impl FromStr for AssetKind {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("native") || s.eq_ignore_ascii_case("xlm") {
+            return Ok(AssetKind::Native);
+        }
+        let (code, issuer) = match s.split_once(':') {
+            Some((code, issuer)) => (code, Some(issuer.to_string())),
+            None => (s, None),
+        };
+        if code.is_empty() || code.len() > 12 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(DebuggerError::ConfigError(format!(
+                "Invalid asset code '{}' (expected 1-12 alphanumeric characters)",
+                code
+            )));
+        }
+        Ok(AssetKind::Issued {
+            code: code.to_string(),
+            issuer,
+        })
+    }
+}
+
+/// Build the XDR asset for an issued asset code
+pub(crate) fn issued_asset(code: &str, issuer: AccountId) -> Asset {
+    let mut bytes = code.as_bytes().to_vec();
+    if bytes.len() <= 4 {
+        bytes.resize(4, 0);
+        let mut code = [0u8; 4];
+        code.copy_from_slice(&bytes);
+        Asset::CreditAlphanum4(AlphaNum4 {
+            asset_code: AssetCode4(code),
+            issuer,
+        })
+    } else {
+        bytes.resize(12, 0);
+        let mut code = [0u8; 12];
+        code.copy_from_slice(&bytes);
+        Asset::CreditAlphanum12(AlphaNum12 {
+            asset_code: AssetCode12(code),
+            issuer,
+        })
+    }
+}
+
+/// Deterministic account for a name, so sessions can be replayed
+pub(crate) fn named_account(env: &Env, name: &str) -> AccountId {
+    let seed = format!("soroban-debug account {}", name);
+    let key = env
+        .crypto()
+        .sha256(&Bytes::from_slice(env, seed.as_bytes()))
+        .to_array();
+    AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key)))
+}
+
+/// The account behind a `G...` address
+pub(crate) fn account_id(address: &ScAddress) -> Option<AccountId> {
+    match address {
+        ScAddress::Account(account) => Some(account.clone()),
+        ScAddress::Contract(_) => None,
+    }
+}
+
+/// Parse a token amount given as a JSON number or decimal string
+pub(crate) fn parse_amount(value: &Json) -> Option<i128> {
+    match value {
+        Json::Number(n) => n
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from)),
+        Json::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Deploy the Stellar Asset Contract for an asset
+pub(crate) fn deploy_asset_contract(env: &Env, asset: Asset) -> Result<Address> {
+    let create = HostFunction::CreateContract(CreateContractArgs {
+        contract_id_preimage: ContractIdPreimage::Asset(asset),
+        executable: ContractExecutable::StellarAsset,
+    });
+    let address = env
+        .host()
+        .invoke_function(create)
+        .map_err(|e| host_error("Failed to deploy asset contract", e))?;
+    let ScVal::Address(address) = address else {
+        return Err(DebuggerError::ExecutionError(format!(
+            "Asset contract deployment returned {:?}",
+            address
+        ))
+        .into());
+    };
+    Address::try_from_val(env, &ScVal::Address(address)).map_err(|e| {
+        DebuggerError::ExecutionError(format!("Invalid asset contract address: {:?}", e)).into()
+    })
+}
+
+/// Create a classic account if it does not exist yet
+pub(crate) fn ensure_account(env: &Env, account: &AccountId) -> Result<()> {
+    update_account(env, account, |_| {})
+}
+
+/// Create or modify a classic account entry
+pub(crate) fn update_account(
+    env: &Env,
+    account: &AccountId,
+    update: impl FnOnce(&mut AccountEntry),
+) -> Result<()> {
+    let key = Rc::new(LedgerKey::Account(LedgerKeyAccount {
+        account_id: account.clone(),
+    }));
+    env.host()
+        .with_mut_storage(|storage| {
+            let budget = env.host().as_budget();
+            let mut entry = if storage.has(&key, budget)? {
+                storage.get(&key, budget)?.as_ref().clone()
+            } else {
+                LedgerEntry {
+                    data: LedgerEntryData::Account(AccountEntry {
+                        account_id: account.clone(),
+                        balance: 0,
+                        seq_num: SequenceNumber(0),
+                        num_sub_entries: 0,
+                        inflation_dest: None,
+                        flags: 0,
+                        home_domain: Default::default(),
+                        thresholds: Thresholds([1, 0, 0, 0]),
+                        signers: Default::default(),
+                        ext: AccountEntryExt::V0,
+                    }),
+                    last_modified_ledger_seq: 0,
+                    ext: LedgerEntryExt::V0,
+                }
+            };
+            if let LedgerEntryData::Account(account) = &mut entry.data {
+                update(account);
+            }
+            storage.put(&key, &Rc::new(entry), None, budget)
+        })
+        .map_err(|e| host_error("Failed to update account", e))
+}
+
+/// Create an authorized, empty trustline from an account to an issued asset
+pub(crate) fn ensure_trustline(
+    env: &Env,
+    account: &AccountId,
+    asset: &Asset,
+    clawback_enabled: bool,
+) -> Result<()> {
+    let asset = match asset {
+        Asset::Native => return Ok(()),
+        Asset::CreditAlphanum4(a) => TrustLineAsset::CreditAlphanum4(a.clone()),
+        Asset::CreditAlphanum12(a) => TrustLineAsset::CreditAlphanum12(a.clone()),
+    };
+    let key = Rc::new(LedgerKey::Trustline(LedgerKeyTrustLine {
+        account_id: account.clone(),
+        asset: asset.clone(),
+    }));
+    let mut flags = TrustLineFlags::AuthorizedFlag as u32;
+    if clawback_enabled {
+        flags |= TrustLineFlags::TrustlineClawbackEnabledFlag as u32;
+    }
+    let entry = Rc::new(LedgerEntry {
+        data: LedgerEntryData::Trustline(TrustLineEntry {
+            account_id: account.clone(),
+            asset,
+            balance: 0,
+            limit: i64::MAX,
+            flags,
+            ext: TrustLineEntryExt::V0,
+        }),
+        last_modified_ledger_seq: 0,
+        ext: LedgerEntryExt::V0,
+    });
+    env.host()
+        .with_mut_storage(|storage| {
+            let budget = env.host().as_budget();
+            if storage.has(&key, budget)? {
+                return Ok(());
+            }
+            storage.put(&key, &entry, None, budget)
+        })
+        .map_err(|e| host_error("Failed to create trustline", e))
+}
+
+fn host_error(context: &str, e: HostError) -> anyhow::Error {
+    DebuggerError::ExecutionError(format!("{}: {:?}", context, e)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asset_kind() {
+        assert_eq!("native".parse::<AssetKind>().unwrap(), AssetKind::Native);
+        assert_eq!(
+            "USDC:issuer".parse::<AssetKind>().unwrap(),
+            AssetKind::Issued {
+                code: "USDC".to_string(),
+                issuer: Some("issuer".to_string())
+            }
+        );
+        assert!("TOOLONGASSETCODE".parse::<AssetKind>().is_err());
+
+        let env = Env::default();
+        let issuer = named_account(&env, "issuer");
+        let usdc = issued_asset("USDC", issuer.clone());
+        assert!(matches!(usdc, Asset::CreditAlphanum4(_)));
+        let long = issued_asset("LONGCODE", issuer);
+        assert!(matches!(long, Asset::CreditAlphanum12(_)));
+        assert_eq!(
+            named_account(&env, "alice"),
+            named_account(&Env::default(), "alice")
+        );
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount(&serde_json::json!(-5)), Some(-5));
+        assert_eq!(
+            parse_amount(&serde_json::json!(u64::MAX)),
+            Some(u64::MAX as i128)
+        );
+        assert_eq!(
+            parse_amount(&serde_json::json!("18446744073709551616")),
+            Some(1 << 64)
+        );
+        assert_eq!(parse_amount(&serde_json::json!(1.5)), None);
+    }
+}
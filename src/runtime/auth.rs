@@ -0,0 +1,80 @@
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::fmt;
+
+/// How `require_auth` calls are satisfied during an invocation
+#[derive(Debug, Clone)]
+pub enum AuthConfig {
+    /// Every authorization succeeds, including ones not rooted at the
+    /// top-level invocation
+    MockAll,
+    /// Authorizations succeed and are recorded the way transaction
+    /// simulation does, which requires them to be rooted at the top-level call
+    Recording,
+    /// Only the listed authorizations are granted. They are signed and handed
+    /// to the host, so any other `require_auth` fails inside the invocation
+    /// and its changes are rolled back.
+    Explicit(Vec<AuthEntry>),
+}
+
+/// An address together with the invocation tree it authorized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthEntry {
+    pub address: String,
+    pub invocation: AuthInvocation,
+}
+
+/// A node of an authorized invocation tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthInvocation {
+    pub contract: String,
+    pub function: String,
+    /// Arguments passed to `require_auth_for_args`. In an explicit list,
+    /// omitting them authorizes any arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<Json>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_invocations: Vec<AuthInvocation>,
+}
+
+impl AuthInvocation {
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let args = self
+            .args
+            .as_ref()
+            .map(|args| {
+                args.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_else(|| "..".to_string());
+        writeln!(
+            f,
+            "{}└─ {}::{}({})",
+            "   ".repeat(depth),
+            self.contract,
+            self.function,
+            args
+        )?;
+        for sub in &self.sub_invocations {
+            sub.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AuthEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.address)?;
+        self.invocation.fmt_tree(f, 1)
+    }
+}
+
+/// Parse an authorization list file: a JSON array of `{address, invocation}`
+pub fn parse_auth_entries(json: &str) -> Result<Vec<AuthEntry>> {
+    serde_json::from_str(json).map_err(|e| {
+        DebuggerError::InvalidArguments(format!("Invalid authorization list: {}", e)).into()
+    })
+}
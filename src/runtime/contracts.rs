@@ -0,0 +1,225 @@
+use crate::runtime::instrumentation::{BasicBlock, InstrumentedModule, StepPoint};
+use crate::utils::source_map::SourceMap;
+use crate::utils::spec::ContractSpec;
+use soroban_sdk::xdr::ScAddress;
+use soroban_sdk::Address;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Resources consumed by top-level invocations of one contract
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContractUsage {
+    pub invocations: u32,
+    pub cpu_instructions: u64,
+    pub memory_bytes: u64,
+}
+
+/// A contract registered in the debugging session
+#[derive(Debug, Clone)]
+pub struct RegisteredContract {
+    alias: String,
+    address: Address,
+    id: ScAddress,
+    spec: ContractSpec,
+    functions: HashMap<u32, String>,
+    blocks: Vec<BasicBlock>,
+    steps: Vec<StepPoint>,
+    source_map: Option<SourceMap>,
+    usage: Cell<ContractUsage>,
+}
+
+impl RegisteredContract {
+    /// User-chosen name of the contract
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    /// Address the contract is registered at
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Strkey of the contract address
+    pub fn contract_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    /// Parsed contract spec
+    pub fn spec(&self) -> &ContractSpec {
+        &self.spec
+    }
+
+    /// Name of a WASM function by its index, or `func[N]` if it has none
+    pub fn function_name(&self, index: u32) -> String {
+        self.functions
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("func[{}]", index))
+    }
+
+    /// Whether the contract exports a function with this name, or has one in
+    /// its name section
+    pub fn has_function(&self, name: &str) -> bool {
+        self.spec.function(name).is_some() || self.functions.values().any(|f| f == name)
+    }
+
+    /// Basic blocks instrumented for coverage, by id
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Instructions instrumented for stepping, by id
+    pub fn steps(&self) -> &[StepPoint] {
+        &self.steps
+    }
+
+    /// Source locations from the contract's debug info, if it has any
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+
+    /// Resources attributed to this contract so far
+    pub fn usage(&self) -> ContractUsage {
+        self.usage.get()
+    }
+
+    /// Attribute one invocation and its cost to this contract
+    pub(crate) fn record_usage(&self, cpu_instructions: u64, memory_bytes: u64) {
+        let mut usage = self.usage.get();
+        usage.invocations += 1;
+        usage.cpu_instructions = usage.cpu_instructions.saturating_add(cpu_instructions);
+        usage.memory_bytes = usage.memory_bytes.saturating_add(memory_bytes);
+        self.usage.set(usage);
+    }
+}
+
+/// Contracts registered in a session, addressable by alias or strkey
+#[derive(Debug, Clone, Default)]
+pub struct ContractRegistry {
+    contracts: Vec<RegisteredContract>,
+    aliases: HashMap<String, ScAddress>,
+}
+
+impl ContractRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a contract. Returns its index in registration order.
+    pub(crate) fn insert(&mut self, alias: &str, address: Address, spec: ContractSpec) -> usize {
+        let id = ScAddress::from(&address);
+        self.aliases.insert(alias.to_string(), id.clone());
+        self.contracts.push(RegisteredContract {
+            alias: alias.to_string(),
+            address,
+            id,
+            spec,
+            functions: HashMap::new(),
+            blocks: Vec::new(),
+            steps: Vec::new(),
+            source_map: None,
+            usage: Cell::new(ContractUsage::default()),
+        });
+        self.contracts.len() - 1
+    }
+
+    /// Rename a contract and replace its spec, after its code was replaced
+    pub(crate) fn replace(&mut self, index: usize, alias: &str, spec: ContractSpec) {
+        let contract = &mut self.contracts[index];
+        self.aliases.remove(&contract.alias);
+        self.aliases.insert(alias.to_string(), contract.id.clone());
+        contract.alias = alias.to_string();
+        contract.spec = spec;
+    }
+
+    /// Record what instrumenting a contract's code found out about it
+    pub(crate) fn set_code(
+        &mut self,
+        index: usize,
+        module: &InstrumentedModule,
+        source_map: Option<SourceMap>,
+    ) {
+        let contract = &mut self.contracts[index];
+        contract.functions = module.function_names().clone();
+        contract.blocks = module.blocks().to_vec();
+        contract.steps = module.steps().to_vec();
+        contract.source_map = source_map;
+    }
+
+    /// Name a non-contract address, such as an account
+    pub(crate) fn insert_alias(&mut self, alias: &str, address: ScAddress) {
+        self.aliases.insert(alias.to_string(), address);
+    }
+
+    /// Check whether a name is already taken
+    pub fn contains_alias(&self, alias: &str) -> bool {
+        self.aliases.contains_key(alias)
+    }
+
+    /// Look up a contract by alias or strkey
+    pub fn get(&self, name: &str) -> Option<&RegisteredContract> {
+        self.index_of(name).map(|i| &self.contracts[i])
+    }
+
+    /// Position of a contract by alias or strkey
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.contracts
+            .iter()
+            .position(|c| c.alias == name || c.contract_id() == name)
+    }
+
+    /// Look up a contract by address
+    pub fn by_address(&self, address: &ScAddress) -> Option<&RegisteredContract> {
+        self.contracts.iter().find(|c| &c.id == address)
+    }
+
+    /// Contract at a registration index
+    pub fn at(&self, index: usize) -> Option<&RegisteredContract> {
+        self.contracts.get(index)
+    }
+
+    /// All contracts in registration order
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredContract> {
+        self.contracts.iter()
+    }
+
+    /// Module offsets where a source line starts, with the aliases of the
+    /// contracts they are in, for contracts built with debug info
+    pub fn resolve_line(&self, file: &str, line: usize) -> Vec<(&str, usize)> {
+        self.contracts
+            .iter()
+            .filter_map(|c| Some((c.alias.as_str(), c.source_map.as_ref()?)))
+            .flat_map(|(alias, map)| {
+                map.resolve(file, line)
+                    .into_iter()
+                    .map(move |offset| (alias, offset))
+            })
+            .collect()
+    }
+
+    /// Aliases that may be used in place of an address in arguments
+    pub fn aliases(&self) -> &HashMap<String, ScAddress> {
+        &self.aliases
+    }
+
+    /// Resolve an alias or strkey to an address
+    pub fn resolve(&self, name: &str) -> Option<ScAddress> {
+        self.aliases
+            .get(name)
+            .cloned()
+            .or_else(|| ScAddress::from_str(name).ok())
+    }
+
+    /// Display name for an address: its alias when known, otherwise the strkey
+    pub fn display_name(&self, address: &ScAddress) -> String {
+        if let Some(contract) = self.by_address(address) {
+            return contract.alias.clone();
+        }
+        self.aliases
+            .iter()
+            .find(|(_, a)| *a == address)
+            .map(|(alias, _)| alias.clone())
+            .unwrap_or_else(|| address.to_string())
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::fmt;
+
+/// An event published by a contract during an invocation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContractEvent {
+    /// Alias of the emitting contract, or its strkey if it has none
+    pub contract: String,
+    /// Strkey of the emitting contract
+    pub contract_id: String,
+    pub topics: Vec<Json>,
+    pub data: Json,
+}
+
+impl ContractEvent {
+    /// Check whether the event was emitted by a contract, given by alias or strkey
+    pub fn emitted_by(&self, contract: &str) -> bool {
+        self.contract == contract || self.contract_id == contract
+    }
+
+    /// Check whether any topic equals a value. Non-string topics are
+    /// compared with the value parsed as JSON.
+    pub fn has_topic(&self, topic: &str) -> bool {
+        self.topics.iter().any(|t| match t {
+            Json::String(s) => s == topic,
+            other => topic.parse::<Json>().is_ok_and(|t| &t == other),
+        })
+    }
+}
+
+impl fmt::Display for ContractEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let topics = self
+            .topics
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{}: [{}] => {}", self.contract, topics, self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_filters() {
+        let event = ContractEvent {
+            contract: "token".to_string(),
+            contract_id: "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM".to_string(),
+            topics: vec![json!("transfer"), json!(7)],
+            data: json!(100),
+        };
+        assert!(event.emitted_by("token"));
+        assert!(event.emitted_by("CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD2KM"));
+        assert!(!event.emitted_by("amm"));
+        assert!(event.has_topic("transfer"));
+        assert!(event.has_topic("7"));
+        assert!(!event.has_topic("mint"));
+        assert_eq!(event.to_string(), "token: [\"transfer\", 7] => 100");
+    }
+}
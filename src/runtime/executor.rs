@@ -1,43 +1,666 @@
+use crate::inspector::BudgetInfo;
+use crate::runtime::assets::{self, AssetConfig, AssetKind, IssuerFlag};
+use crate::runtime::auth::{AuthConfig, AuthEntry, AuthInvocation};
+use crate::runtime::contracts::{ContractRegistry, RegisteredContract};
+use crate::runtime::events::ContractEvent;
+use crate::runtime::instrumentation::{
+    BlockHits, FunctionEvent, Hook, HookKind, InstrumentedModule, Instrumenter,
+};
+use crate::runtime::ledger::LedgerConfig;
+use crate::runtime::probe::{self, BudgetMeter, HookEvent, HookHandler, Probe, ProbeState};
+use crate::runtime::snapshot::{self, LedgerSnapshot};
+use crate::runtime::storage::{parse_storage_entries, Durability, StorageEntry};
+use crate::runtime::values::ValueCodec;
+use crate::utils::source_map::SourceMap;
+use crate::utils::spec::ContractSpec;
 use crate::{DebuggerError, Result};
-use soroban_env_host::Host;
-use soroban_sdk::{Address, Env, InvokeError, Symbol, Val, Vec as SorobanVec};
+use ed25519_dalek::{Signer as _, SigningKey};
+use sha2::{Digest, Sha256};
+use soroban_env_host::{DiagnosticLevel, Env as _, Host};
+use soroban_sdk::testutils::{
+    AuthorizedFunction, AuthorizedInvocation, EnvTestConfig, Ledger as _, LedgerInfo,
+    MockAuthContract,
+};
+use soroban_sdk::token::{StellarAssetClient, StellarAssetSpec};
+use soroban_sdk::xdr::{
+    AccountId, Asset, ContractEventBody, ContractEventType, ContractExecutable, Hash,
+    HashIdPreimage, HashIdPreimageSorobanAuthorization, InvokeContractArgs, LedgerEntryData,
+    LedgerKey, Limits, ScAddress, ScBytes, ScErrorType, ScMap, ScMapEntry, ScSpecTypeDef, ScSymbol,
+    ScVal, ScVec, Signer, SignerKey, SorobanAddressCredentials, SorobanAuthorizationEntry,
+    SorobanAuthorizedFunction, SorobanAuthorizedInvocation, SorobanCredentials, Uint256, WriteXdr,
+};
+use soroban_sdk::{Address, Bytes, Env, Symbol, TryFromVal, Val, Vec as SorobanVec};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{info, warn};
 
+/// Decoded return value of a successful invocation
+#[derive(Debug, Clone)]
+pub struct InvocationResult {
+    /// Alias of the invoked contract
+    pub contract: String,
+    /// The raw return value
+    pub value: ScVal,
+    /// The return value rendered using the function's declared output type
+    pub json: serde_json::Value,
+    /// Authorizations required during the invocation
+    pub auths: Vec<AuthEntry>,
+    /// Events published during the invocation, in emission order
+    pub events: Vec<ContractEvent>,
+    /// Entries and exits of the contracts' WASM functions, in order
+    pub trace: Vec<FunctionEvent>,
+    /// Basic blocks that ran, for contracts instrumented for coverage
+    pub blocks: Vec<BlockHits>,
+    /// Every hook call of the invocation, in order
+    pub timeline: Vec<HookEvent>,
+    /// CPU instructions consumed by the invocation
+    pub cpu_instructions: u64,
+    /// Memory bytes consumed by the invocation
+    pub memory_bytes: u64,
+}
+
+impl InvocationResult {
+    /// Pretty-printed JSON form of the return value
+    pub fn pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.json).unwrap_or_else(|_| self.json.to_string())
+    }
+
+    /// Base64 XDR encoding of the return value
+    pub fn to_xdr_base64(&self) -> Result<String> {
+        self.value.to_xdr_base64(Limits::none()).map_err(|e| {
+            DebuggerError::ExecutionError(format!("Failed to encode result as XDR: {}", e)).into()
+        })
+    }
+}
+
+impl fmt::Display for InvocationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.json)
+    }
+}
+
+/// Alias given to the contract passed to [`ContractExecutor::new`]
+pub const DEFAULT_ALIAS: &str = "contract";
+
+/// An explicitly granted authorization, with its arguments encoded
+struct Grant {
+    address: ScAddress,
+    invocation: GrantedInvocation,
+}
+
+struct GrantedInvocation {
+    contract: ScAddress,
+    function: String,
+    /// Left out on a top-level invocation to authorize the arguments it is
+    /// called with
+    args: Option<Vec<ScVal>>,
+    sub_invocations: Vec<GrantedInvocation>,
+}
+
+impl GrantedInvocation {
+    /// The invocation tree as the host matches it, taking omitted arguments
+    /// from the call being made. `None` if they are omitted for another call.
+    fn to_xdr(&self, call: (&ScAddress, &str, &[ScVal])) -> Option<SorobanAuthorizedInvocation> {
+        let args = match &self.args {
+            Some(args) => args.clone(),
+            None if (&self.contract, self.function.as_str()) == (call.0, call.1) => call.2.to_vec(),
+            None => return None,
+        };
+        Some(SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                contract_address: self.contract.clone(),
+                function_name: ScSymbol(self.function.as_str().try_into().ok()?),
+                args: args.try_into().ok()?,
+            }),
+            sub_invocations: self
+                .sub_invocations
+                .iter()
+                .map(|sub| sub.to_xdr(call))
+                .collect::<Option<Vec<_>>>()?
+                .try_into()
+                .ok()?,
+        })
+    }
+}
+
+/// Key the debugger signs the explicitly granted authorizations of accounts
+/// with, once added to their signers
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&Sha256::digest(b"soroban-debug signer").into())
+}
+
+fn debugger_signer() -> SignerKey {
+    SignerKey::Ed25519(Uint256(signing_key().verifying_key().to_bytes()))
+}
+
 /// Executes Soroban contracts in a test environment
 pub struct ContractExecutor {
     env: Env,
-    contract_address: Address,
+    contracts: ContractRegistry,
+    assets: HashMap<String, Asset>,
+    target: usize,
+    auth: Option<AuthConfig>,
+    grants: Vec<Grant>,
+    /// Next nonce for the authorization entries of explicit mode
+    nonce: Cell<i64>,
+    tracing: bool,
+    coverage: bool,
+    stepping: bool,
+    host_calls: bool,
+    probe: Rc<RefCell<ProbeState>>,
+    /// Addresses of the probes registered for instrumented contracts
+    probes: HashSet<ScAddress>,
+    /// Hashes of the original code of instrumented contracts, by the hash of
+    /// the code they run
+    originals: HashMap<Hash, Hash>,
+    /// Code of the contracts registered from WASM, to instrument again when
+    /// more hooks are enabled
+    wasms: HashMap<ScAddress, Vec<u8>>,
+    /// CPU instruction and memory byte limits invocations are held to,
+    /// leaving out what their hooks use
+    budget_limits: (u64, u64),
 }
 
 impl ContractExecutor {
-    /// Create a new contract executor
-    pub fn new(wasm: Vec<u8>) -> Result<Self> {
-        info!("Initializing contract executor");
+    /// Create a new contract executor, optionally starting from a ledger snapshot
+    pub fn new(wasm: Vec<u8>, snapshot: Option<LedgerSnapshot>) -> Result<Self> {
+        Self::with_ledger(wasm, &LedgerConfig::default(), snapshot)
+    }
 
-        // Create a test environment
-        let env = Env::default();
+    /// Create a contract executor with a configured ledger context
+    pub fn with_ledger(
+        wasm: Vec<u8>,
+        ledger: &LedgerConfig,
+        snapshot: Option<LedgerSnapshot>,
+    ) -> Result<Self> {
+        let mut executor = Self::empty(ledger, snapshot)?;
+        executor.register_contract(DEFAULT_ALIAS, &wasm, None, None)?;
+        executor.select_contract(DEFAULT_ALIAS)?;
+        Ok(executor)
+    }
 
-        // Register the contract with the WASM
-        let contract_address = env.register(wasm.as_slice(), ());
+    /// Create an executor with no contracts registered beyond those in the
+    /// snapshot, which are named by their strkey
+    pub fn empty(ledger: &LedgerConfig, snapshot: Option<LedgerSnapshot>) -> Result<Self> {
+        info!("Initializing contract executor");
 
-        info!("Contract registered successfully");
+        let mut contracts = ContractRegistry::new();
+        let mut env = match snapshot {
+            Some(snapshot) => {
+                let found = snapshot::snapshot_contracts(&snapshot)?;
+                info!(
+                    "Loading {} ledger entries ({} contracts) from snapshot",
+                    snapshot.ledger_entries.len(),
+                    found.len()
+                );
+                let env = snapshot::env_from_snapshot(snapshot);
+                for contract in found {
+                    let strkey = contract.address.to_string();
+                    let address = Address::try_from_val(&env, &ScVal::Address(contract.address))
+                        .map_err(|e| {
+                            DebuggerError::StorageError(format!(
+                                "Invalid contract address {} in snapshot: {:?}",
+                                strkey, e
+                            ))
+                        })?;
+                    contracts.insert(&strkey, address, contract.spec);
+                }
+                env
+            }
+            // Create a test environment
+            None => Env::default(),
+        };
+        // The SDK would write a test snapshot for any named thread running
+        // the environment
+        env.set_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        ledger.apply(&env)?;
+        let budget = env.host().budget_cloned();
+        let budget_limits = (
+            budget.get_cpu_insns_consumed().unwrap_or(0)
+                + budget.get_cpu_insns_remaining().unwrap_or(0),
+            budget.get_mem_bytes_consumed().unwrap_or(0)
+                + budget.get_mem_bytes_remaining().unwrap_or(0),
+        );
 
         Ok(Self {
             env,
-            contract_address,
+            contracts,
+            assets: HashMap::new(),
+            target: 0,
+            auth: None,
+            grants: Vec::new(),
+            // Nonces spent by earlier sessions may be in the snapshot
+            nonce: Cell::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_nanos() as i64 & i64::MAX),
+            ),
+            tracing: false,
+            coverage: false,
+            stepping: false,
+            host_calls: false,
+            probe: Rc::default(),
+            probes: HashSet::new(),
+            originals: HashMap::new(),
+            wasms: HashMap::new(),
+            budget_limits,
+        })
+    }
+
+    /// Instrument the function entries and exits of the session's
+    /// contracts, so invocations report the calls they make. Contracts
+    /// otherwise run as built, and their costs are exact.
+    ///
+    /// The host meters each hook like a contract call. Invocations are
+    /// still held to the budget limits, and their costs reported, leaving
+    /// out an estimate of what the hooks use.
+    pub fn enable_tracing(&mut self) -> Result<()> {
+        if self.tracing {
+            return Ok(());
+        }
+        self.tracing = true;
+        self.reinstrument()
+    }
+
+    /// Instrument the basic blocks of the session's contracts, so
+    /// invocations report which ones ran. Like tracing, this leaves the
+    /// estimated cost of the hooks out of the budget.
+    pub fn enable_coverage(&mut self) -> Result<()> {
+        if self.coverage {
+            return Ok(());
+        }
+        self.coverage = true;
+        self.reinstrument()
+    }
+
+    /// Instrument every instruction of the session's contracts, and have
+    /// invocations record each one that runs.
+    ///
+    /// Like tracing, this leaves the estimated cost of the hooks out of the
+    /// budget. Hooks would flood the diagnostic events, which are turned off.
+    pub fn enable_stepping(&mut self) -> Result<()> {
+        if self.stepping {
+            return Ok(());
+        }
+        self.stepping = true;
+        self.probe.borrow_mut().stepping = true;
+        let _ = self.env.host().set_diagnostic_level(DiagnosticLevel::None);
+        self.reinstrument()
+    }
+
+    /// Instrument the host function calls of the session's contracts,
+    /// without stepping through their instructions. Like tracing, this
+    /// leaves the estimated cost of the hooks out of the budget.
+    pub fn enable_host_calls(&mut self) -> Result<()> {
+        if self.host_calls {
+            return Ok(());
+        }
+        self.host_calls = true;
+        // Host call wrappers only report in step mode
+        self.probe.borrow_mut().stepping = true;
+        self.reinstrument()
+    }
+
+    /// CPU instruction and memory byte limits invocations are held to
+    pub fn budget_limits(&self) -> (u64, u64) {
+        self.budget_limits
+    }
+
+    /// Budget the last invocation used, without what its hooks did
+    pub fn budget(&self) -> BudgetInfo {
+        self.budget_meter().read(self.env.host())
+    }
+
+    /// Reads the budget of invocations while they run
+    pub(crate) fn budget_meter(&self) -> BudgetMeter {
+        BudgetMeter {
+            state: self.probe.clone(),
+            limits: self.budget_limits,
+        }
+    }
+
+    /// Have a handler see every hook call of the invocations that follow
+    pub fn set_hook_handler(&self, handler: Option<Rc<dyn HookHandler>>) {
+        self.probe.borrow_mut().handler = handler;
+    }
+
+    /// Register a contract under an alias, optionally at a fixed address and
+    /// with constructor arguments. The first contract registered becomes the
+    /// invocation target.
+    pub fn register_contract(
+        &mut self,
+        alias: &str,
+        wasm: &[u8],
+        address: Option<&str>,
+        constructor_args: Option<&serde_json::Value>,
+    ) -> Result<String> {
+        self.check_alias_free(alias)?;
+
+        let spec = ContractSpec::from_wasm(wasm)?;
+        if spec.is_empty() {
+            warn!(
+                "Contract '{}' has no spec section; argument types will be inferred",
+                alias
+            );
+        }
+
+        let args = match constructor_args {
+            Some(json) => {
+                let constructor = spec.function("__constructor").ok_or_else(|| {
+                    DebuggerError::InvalidArguments(format!(
+                        "Contract '{}' has no constructor to pass arguments to",
+                        alias
+                    ))
+                })?;
+                let scvals = ValueCodec::new(&spec)
+                    .with_aliases(self.contracts.aliases())
+                    .encode_args(constructor, json)?;
+                self.to_vals(&scvals)?
+            }
+            None => vec![],
+        };
+        let args = SorobanVec::from_slice(&self.env, &args);
+
+        let address = match address {
+            Some(address) => self.contract_address(address)?,
+            None => snapshot::unused_contract_address(&self.env)?,
+        };
+        let module = self.instrument(alias, wasm);
+        let code = module.wasm.as_slice();
+        // The constructor already calls hooks
+        self.register_probe(&address)?;
+        let registered = self
+            .unmetered(|| {
+                catch_unwind(AssertUnwindSafe(|| {
+                    self.env.register_at(&address, code, args.clone())
+                }))
+            })
+            .map_err(|_| {
+                DebuggerError::ExecutionError(format!(
+                    "Failed to register contract '{}'; see the host error above",
+                    alias
+                ))
+            })?;
+
+        self.keep_original(&module.wasm, wasm)?;
+        self.wasms
+            .insert(ScAddress::from(&registered), wasm.to_vec());
+
+        let index = self.contracts.insert(alias, registered, spec);
+        self.contracts
+            .set_code(index, &module, SourceMap::from_wasm(wasm));
+        let contract_id = self.contracts.at(index).map(|c| c.contract_id());
+        info!("Contract '{}' registered at {:?}", alias, contract_id);
+        Ok(contract_id.unwrap_or_default())
+    }
+
+    /// Run a local WASM as the code of a contract loaded from the snapshot,
+    /// keeping its address and storage, and name it with an alias
+    pub fn attach_contract(
+        &mut self,
+        alias: &str,
+        wasm: &[u8],
+        contract_id: &str,
+    ) -> Result<String> {
+        let index = self.contracts.index_of(contract_id).ok_or_else(|| {
+            DebuggerError::InvalidArguments(format!(
+                "Contract {} is not in the snapshot",
+                contract_id
+            ))
+        })?;
+        let contract = self.contracts.at(index).ok_or_else(|| {
+            DebuggerError::ExecutionError(format!("Contract {} is not registered", contract_id))
+        })?;
+        let address = contract.address().clone();
+        let strkey = contract.contract_id();
+        if alias != contract.alias() {
+            self.check_alias_free(alias)?;
+        }
+
+        let spec = ContractSpec::from_wasm(wasm)?;
+        let module = self.instrument(alias, wasm);
+        self.replace_code(&address, &module.wasm)?;
+        self.keep_original(&module.wasm, wasm)?;
+        self.wasms.insert(ScAddress::from(&address), wasm.to_vec());
+
+        self.contracts.replace(index, alias, spec);
+        self.contracts
+            .set_code(index, &module, SourceMap::from_wasm(wasm));
+        info!("Contract {} now runs '{}'", strkey, alias);
+        Ok(strkey)
+    }
+
+    /// Create a classic account whose address is derived from its name
+    pub fn add_account(&mut self, name: &str) -> Result<String> {
+        self.check_alias_free(name)?;
+        let account = assets::named_account(&self.env, name);
+        assets::ensure_account(&self.env, &account)?;
+        let address = ScAddress::Account(account);
+        self.contracts.insert_alias(name, address.clone());
+        Ok(address.to_string())
+    }
+
+    /// Deploy the Stellar Asset Contract described by an `[[assets]]` entry
+    /// and hand it to the configured admin. Balances are applied separately
+    /// by [`fund_asset`](Self::fund_asset) so they can go to contracts
+    /// registered afterwards.
+    pub fn register_asset(&mut self, config: &AssetConfig) -> Result<String> {
+        self.check_alias_free(&config.alias)?;
+
+        let asset = match config.asset.parse::<AssetKind>()? {
+            AssetKind::Native => {
+                if config.admin.is_some() {
+                    return Err(DebuggerError::ConfigError(format!(
+                        "Asset '{}': the native asset has no admin",
+                        config.alias
+                    ))
+                    .into());
+                }
+                Asset::Native
+            }
+            AssetKind::Issued { code, issuer } => {
+                let issuer = match issuer {
+                    Some(name) => self.resolve_account(&name)?,
+                    None => assets::named_account(&self.env, &format!("{} issuer", code)),
+                };
+                let flags = config
+                    .issuer_flags
+                    .iter()
+                    .fold(0, |flags, flag| flags | flag.mask());
+                assets::update_account(&self.env, &issuer, |entry| entry.flags |= flags)?;
+                assets::issued_asset(&code, issuer)
+            }
+        };
+
+        let address = assets::deploy_asset_contract(&self.env, asset.clone())?;
+        let spec = ContractSpec::from_xdr(&StellarAssetSpec::spec_xdr())?;
+        let index = self.contracts.insert(&config.alias, address.clone(), spec);
+        self.assets.insert(config.alias.clone(), asset);
+
+        if let Some(admin) = &config.admin {
+            let admin = self.resolve_address(admin)?;
+            let client = StellarAssetClient::new(&self.env, &address);
+            let result = self.as_admin(|| client.try_set_admin(&admin))?;
+            asset_call(&config.alias, "set_admin", result)?;
+        }
+
+        let contract_id = self.contracts.at(index).map(|c| c.contract_id());
+        info!("Asset '{}' registered at {:?}", config.alias, contract_id);
+        Ok(contract_id.unwrap_or_default())
+    }
+
+    /// Mint the initial balances of an asset and apply its authorization flags
+    pub fn fund_asset(&mut self, config: &AssetConfig) -> Result<()> {
+        let asset = self.assets.get(&config.alias).cloned().ok_or_else(|| {
+            DebuggerError::ConfigError(format!("Unknown asset '{}'", config.alias))
+        })?;
+        let contract = self.contracts.get(&config.alias).ok_or_else(|| {
+            DebuggerError::ConfigError(format!("Unknown asset '{}'", config.alias))
+        })?;
+        let client = StellarAssetClient::new(&self.env, contract.address());
+
+        for (holder, amount) in &config.balances {
+            let amount = assets::parse_amount(amount).ok_or_else(|| {
+                DebuggerError::ConfigError(format!(
+                    "Asset '{}': invalid balance {} for {}",
+                    config.alias, amount, holder
+                ))
+            })?;
+            let address = self.contracts.resolve(holder).ok_or_else(|| {
+                DebuggerError::ConfigError(format!("Unknown balance holder '{}'", holder))
+            })?;
+
+            if asset == Asset::Native {
+                // Native balances live on the account entry and cannot be minted
+                let (account, balance) = assets::account_id(&address)
+                    .zip(i64::try_from(amount).ok())
+                    .ok_or_else(|| {
+                        DebuggerError::ConfigError(format!(
+                            "Asset '{}': native balances can only be set for accounts, \
+                             up to {}",
+                            config.alias,
+                            i64::MAX
+                        ))
+                    })?;
+                assets::update_account(&self.env, &account, |entry| entry.balance = balance)?;
+                continue;
+            }
+
+            if let Some(account) = assets::account_id(&address) {
+                assets::ensure_account(&self.env, &account)?;
+                if !is_issuer(&asset, &account) {
+                    let clawback = config
+                        .issuer_flags
+                        .contains(&IssuerFlag::AuthClawbackEnabled);
+                    assets::ensure_trustline(&self.env, &account, &asset, clawback)?;
+                }
+            }
+            let to = self.resolve_address(holder)?;
+            let result = self.as_admin(|| client.try_mint(&to, &amount))?;
+            asset_call(&config.alias, "mint", result)?;
+            info!("Minted {} {} to {}", amount, config.alias, holder);
+        }
+
+        for (holder, authorized) in &config.authorized {
+            if asset == Asset::Native {
+                return Err(DebuggerError::ConfigError(format!(
+                    "Asset '{}': the native asset has no authorization flags",
+                    config.alias
+                ))
+                .into());
+            }
+            let id = self.resolve_address(holder)?;
+            let result = self.as_admin(|| client.try_set_authorized(&id, authorized))?;
+            asset_call(&config.alias, "set_authorized", result)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run setup calls with every authorization granted, leaving the
+    /// session's authorization mode untouched
+    fn as_admin<T>(&self, f: impl FnOnce() -> T) -> Result<T> {
+        let host = self.env.host();
+        let map_err = |e| DebuggerError::ExecutionError(format!("Authorization setup: {:?}", e));
+        let previous = host.snapshot_auth_manager().map_err(map_err)?;
+        host.switch_to_recording_auth_inherited_from_snapshot(&previous)
+            .map_err(map_err)?;
+        let result = f();
+        host.set_auth_manager(previous).map_err(map_err)?;
+        Ok(result)
+    }
+
+    fn check_alias_free(&self, alias: &str) -> Result<()> {
+        if self.contracts.contains_alias(alias) {
+            return Err(DebuggerError::InvalidArguments(format!(
+                "Alias '{}' is already registered",
+                alias
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Resolve an alias or strkey to an SDK address
+    fn resolve_address(&self, name: &str) -> Result<Address> {
+        let address = self.contracts.resolve(name).ok_or_else(|| {
+            DebuggerError::InvalidArguments(format!("Unknown address or alias '{}'", name))
+        })?;
+        Address::try_from_val(&self.env, &ScVal::Address(address)).map_err(|e| {
+            DebuggerError::InvalidArguments(format!("Invalid address {}: {:?}", name, e)).into()
+        })
+    }
+
+    /// Resolve an alias or `G...` strkey to a classic account
+    fn resolve_account(&self, name: &str) -> Result<AccountId> {
+        self.contracts
+            .resolve(name)
+            .as_ref()
+            .and_then(assets::account_id)
+            .ok_or_else(|| {
+                DebuggerError::ConfigError(format!("'{}' is not a known account", name)).into()
+            })
+    }
+
+    /// Choose the contract that [`execute`](Self::execute) invokes
+    pub fn select_contract(&mut self, name: &str) -> Result<()> {
+        self.target = self.contracts.index_of(name).ok_or_else(|| {
+            DebuggerError::InvalidArguments(format!("Unknown contract '{}'", name))
+        })?;
+        Ok(())
+    }
+
+    /// Contracts registered in this session
+    pub fn contracts(&self) -> &ContractRegistry {
+        &self.contracts
+    }
+
+    /// The contract invoked by [`execute`](Self::execute)
+    pub fn target(&self) -> Result<&RegisteredContract> {
+        self.contracts.at(self.target).ok_or_else(|| {
+            DebuggerError::ExecutionError("No contract registered".to_string()).into()
         })
     }
 
-    /// Execute a contract function
-    pub fn execute(&self, function: &str, args: Option<&str>) -> Result<String> {
-        info!("Executing function: {}", function);
+    /// Execute a function on the selected contract
+    pub fn execute(&self, function: &str, args: Option<&str>) -> Result<InvocationResult> {
+        self.invoke(self.target()?, function, args)
+    }
+
+    /// Execute a function on a contract given by alias or address
+    pub fn execute_on(
+        &self,
+        contract: &str,
+        function: &str,
+        args: Option<&str>,
+    ) -> Result<InvocationResult> {
+        let contract = self.contracts.get(contract).ok_or_else(|| {
+            DebuggerError::InvalidArguments(format!("Unknown contract '{}'", contract))
+        })?;
+        self.invoke(contract, function, args)
+    }
+
+    fn invoke(
+        &self,
+        contract: &RegisteredContract,
+        function: &str,
+        args: Option<&str>,
+    ) -> Result<InvocationResult> {
+        info!("Executing function: {}::{}", contract.alias(), function);
 
         // Convert function name to Symbol
         let func_symbol = Symbol::new(&self.env, function);
 
-        // Parse arguments (simplified for now)
+        // Parse arguments against the function's spec
         let parsed_args = if let Some(args_json) = args {
-            self.parse_args(args_json)?
+            self.parse_args(contract, function, args_json)?
         } else {
             vec![]
         };
@@ -49,69 +672,968 @@ impl ContractExecutor {
             SorobanVec::from_slice(&self.env, &parsed_args)
         };
 
-        // Call the contract
-        // try_invoke_contract returns Result<Result<Val, ConversionError>, Result<InvokeError, InvokeError>>
-        match self.env.try_invoke_contract::<Val, InvokeError>(
-            &self.contract_address,
-            &func_symbol,
-            args_vec,
-        ) {
-            Ok(Ok(val)) => {
+        self.probe.borrow_mut().timeline.clear();
+        self.authorize(contract, function, &parsed_args)?;
+        // Hooks of the last invocation leave the limits to what it had left
+        let (cpu_limit, mem_limit) = self.budget_limits;
+        self.env
+            .cost_estimate()
+            .budget()
+            .reset_limits(cpu_limit, mem_limit);
+        self.probe.borrow_mut().budget.start(self.budget_limits);
+
+        // Call the contract through the host, which reports why a call
+        // failed where `try_invoke_contract` narrows it to an abort
+        let outcome = self.env.host().call(
+            contract.address().to_object(),
+            func_symbol.to_symbol_val(),
+            args_vec.to_object(),
+        );
+
+        // Budget metered for this top-level invocation, nested calls included
+        let budget = self.budget();
+        let (cpu, mem) = (budget.cpu_instructions, budget.memory_bytes);
+        contract.record_usage(cpu, mem);
+
+        match outcome {
+            Ok(val) => {
                 info!("Function executed successfully");
-                Ok(format!("{:?}", val))
+                let mut result = self.decode_result(contract, function, val)?;
+                result.cpu_instructions = cpu;
+                result.memory_bytes = mem;
+                Ok(result)
             }
-            Ok(Err(conv_err)) => {
-                warn!("Return value conversion failed: {:?}", conv_err);
-                Err(DebuggerError::ExecutionError(format!(
-                    "Return value conversion failed: {:?}",
-                    conv_err
-                ))
-                .into())
+            Err(e) if e.error.is_type(ScErrorType::Contract) => {
+                let code = e.error.get_code();
+                warn!("Contract returned error code: {}", code);
+                Err(DebuggerError::ExecutionError(format!("Contract error code: {}", code)).into())
             }
-            Err(Ok(inv_err)) => match inv_err {
-                InvokeError::Contract(code) => {
-                    warn!("Contract returned error code: {}", code);
-                    Err(
-                        DebuggerError::ExecutionError(format!("Contract error code: {}", code))
-                            .into(),
-                    )
-                }
-                InvokeError::Abort => {
-                    warn!("Contract execution aborted");
-                    Err(
-                        DebuggerError::ExecutionError("Contract execution aborted".to_string())
-                            .into(),
-                    )
-                }
-            },
-            Err(Err(inv_err)) => {
-                warn!("Invocation error conversion failed: {:?}", inv_err);
-                Err(DebuggerError::ExecutionError(format!(
-                    "Invocation error conversion failed: {:?}",
-                    inv_err
-                ))
+            Err(e)
+                if e.error.is_type(ScErrorType::Auth)
+                    && matches!(self.auth, Some(AuthConfig::Explicit(_))) =>
+            {
+                warn!("Authorization not granted");
+                Err(DebuggerError::ExecutionError(
+                    "Authorization not granted: the call required an authorization missing \
+                     from the list, and its changes were rolled back"
+                        .to_string(),
+                )
                 .into())
             }
+            Err(e) if e.error.is_type(ScErrorType::Budget) => {
+                warn!("Contract execution aborted: {:?}", e.error);
+                Err(DebuggerError::BudgetExceeded(budget.exceeded()).into())
+            }
+            Err(e) => {
+                warn!("Contract execution aborted: {:?}", e.error);
+                Err(DebuggerError::ExecutionError("Contract execution aborted".to_string()).into())
+            }
         }
     }
 
     /// Set initial storage state
-    pub fn set_initial_storage(&mut self, _storage_json: String) -> Result<()> {
-        // TODO: Implement storage initialization
-        info!("Setting initial storage (not yet implemented)");
+    pub fn set_initial_storage(&mut self, storage_json: String) -> Result<()> {
+        let entries = parse_storage_entries(&storage_json)?;
+        for entry in &entries {
+            self.write_storage_entry(entry)?;
+        }
+        info!("Seeded {} storage entries", entries.len());
+        Ok(())
+    }
+
+    /// Write a single typed entry into the owning contract's storage
+    fn write_storage_entry(&self, entry: &StorageEntry) -> Result<()> {
+        let contract = match &entry.contract {
+            Some(name) => self.contracts.get(name).ok_or_else(|| {
+                DebuggerError::StorageError(format!(
+                    "Unknown contract '{}' for key {}",
+                    name, entry.key
+                ))
+            })?,
+            None => self.target()?,
+        };
+        let codec = ValueCodec::new(contract.spec()).with_aliases(self.contracts.aliases());
+        let key = codec
+            .encode_typed(entry.key_type.as_deref(), &entry.key)
+            .map_err(|e| DebuggerError::StorageError(format!("key {}: {}", entry.key, e)))?;
+        let value = codec
+            .encode_typed(entry.value_type.as_deref(), &entry.value)
+            .map_err(|e| {
+                DebuggerError::StorageError(format!("value for key {}: {}", entry.key, e))
+            })?;
+
+        let to_val = |scval| {
+            Val::try_from_val(&self.env, scval).map_err(|e| {
+                DebuggerError::StorageError(format!("Failed to convert {:?}: {:?}", scval, e))
+            })
+        };
+        let key = to_val(&key)?;
+        let value = to_val(&value)?;
+
+        let max_ttl = self.env.storage().max_ttl();
+        if let Some(ttl) = entry.ttl.filter(|ttl| *ttl > max_ttl) {
+            return Err(DebuggerError::StorageError(format!(
+                "TTL {} for key {} exceeds the maximum of {} ledgers",
+                ttl, entry.key, max_ttl
+            ))
+            .into());
+        }
+
+        self.env.as_contract(contract.address(), || {
+            let storage = self.env.storage();
+            match entry.durability {
+                Durability::Instance => {
+                    storage.instance().set(&key, &value);
+                    if let Some(ttl) = entry.ttl {
+                        storage.instance().extend_ttl(ttl, ttl);
+                    }
+                }
+                Durability::Persistent => {
+                    storage.persistent().set(&key, &value);
+                    if let Some(ttl) = entry.ttl {
+                        storage.persistent().extend_ttl(&key, ttl, ttl);
+                    }
+                }
+                Durability::Temporary => {
+                    storage.temporary().set(&key, &value);
+                    if let Some(ttl) = entry.ttl {
+                        storage.temporary().extend_ttl(&key, ttl, ttl);
+                    }
+                }
+            }
+        });
+
+        info!(
+            "Seeded {} storage entry {} for '{}'",
+            entry.durability,
+            entry.key,
+            contract.alias()
+        );
         Ok(())
     }
 
+    /// Select how `require_auth` calls are satisfied
+    pub fn set_auth(&mut self, config: AuthConfig) -> Result<()> {
+        self.grants.clear();
+        let config = match config {
+            AuthConfig::MockAll => {
+                self.env.mock_all_auths_allowing_non_root_auth();
+                AuthConfig::MockAll
+            }
+            AuthConfig::Recording => {
+                self.env.mock_all_auths();
+                AuthConfig::Recording
+            }
+            AuthConfig::Explicit(entries) => {
+                // The granted entries are signed and handed to the host
+                // before each invocation, which fails any other `require_auth`
+                for entry in &entries {
+                    let grant = self.grant(entry)?;
+                    self.add_signer(&grant.address)?;
+                    self.grants.push(grant);
+                }
+                let entries = entries
+                    .into_iter()
+                    .map(|mut entry| {
+                        if let Some(address) = self.contracts.resolve(&entry.address) {
+                            entry.address = self.contracts.display_name(&address);
+                        }
+                        self.normalize_auth_invocation(&mut entry.invocation)?;
+                        Ok(entry)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                AuthConfig::Explicit(entries)
+            }
+        };
+        info!("Authorization mode: {:?}", config);
+        self.auth = Some(config);
+        Ok(())
+    }
+
+    /// Current ledger info
+    pub fn ledger_info(&self) -> LedgerInfo {
+        self.env.ledger().get()
+    }
+
+    /// Move the ledger clock forward
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.env.ledger().with_mut(|info| {
+            info.timestamp = info.timestamp.saturating_add(seconds);
+        });
+    }
+
+    /// Close `ledgers` ledgers, bumping the sequence number
+    pub fn advance_sequence(&mut self, ledgers: u32) {
+        self.env.ledger().with_mut(|info| {
+            info.sequence_number = info.sequence_number.saturating_add(ledgers);
+        });
+    }
+
+    /// Current ledger info and every entry in storage, including those
+    /// loaded from a snapshot and not touched since. Instrumented contracts
+    /// are exported with their original code.
+    pub fn ledger_snapshot(&self) -> LedgerSnapshot {
+        let mut snapshot = self.env.to_ledger_snapshot();
+        // Leave out the signer added to accounts with granted authorizations
+        let signer = debugger_signer();
+        for (_, (entry, _)) in snapshot.ledger_entries.iter_mut() {
+            // Instances run their original code rather than the hooked one
+            if let LedgerEntryData::ContractData(data) = &mut entry.data {
+                if let ScVal::ContractInstance(instance) = &mut data.val {
+                    if let ContractExecutable::Wasm(hash) = &mut instance.executable {
+                        if let Some(original) = self.originals.get(hash) {
+                            *hash = original.clone();
+                        }
+                    }
+                }
+            }
+            if let LedgerEntryData::Account(account) = &mut entry.data {
+                if account.signers.iter().any(|s| s.key == signer) {
+                    let signers: Vec<Signer> = account
+                        .signers
+                        .iter()
+                        .filter(|s| s.key != signer)
+                        .cloned()
+                        .collect();
+                    account.signers = signers.try_into().unwrap_or_default();
+                }
+            }
+        }
+        // Leave out the probes, the empty code they run and the hooked code
+        snapshot
+            .ledger_entries
+            .retain(|(key, (entry, _))| match key.as_ref() {
+                LedgerKey::ContractData(data) => !self.probes.contains(&data.contract),
+                LedgerKey::ContractCode(code) if self.originals.contains_key(&code.hash) => false,
+                LedgerKey::ContractCode(_) => match &entry.data {
+                    LedgerEntryData::ContractCode(code) => !code.code.is_empty(),
+                    _ => true,
+                },
+                _ => true,
+            });
+        snapshot
+    }
+
     /// Get the host instance
     pub fn host(&self) -> &Host {
         self.env.host()
     }
 
+    /// Convert a return value to `ScVal` and render it using the spec
+    fn decode_result(
+        &self,
+        contract: &RegisteredContract,
+        function: &str,
+        val: Val,
+    ) -> Result<InvocationResult> {
+        let value = ScVal::try_from_val(&self.env, &val).map_err(|e| {
+            DebuggerError::ExecutionError(format!("Failed to decode return value: {:?}", e))
+        })?;
+
+        let output_type = contract
+            .spec()
+            .function(function)
+            .and_then(|f| f.outputs.first().cloned())
+            .unwrap_or(ScSpecTypeDef::Val);
+        let json = ValueCodec::new(contract.spec()).to_json(&output_type, &value);
+        let events = self.contract_events()?;
+        let timeline = std::mem::take(&mut self.probe.borrow_mut().timeline);
+        let (trace, blocks) = self.summarize(&timeline);
+
+        Ok(InvocationResult {
+            contract: contract.alias().to_string(),
+            value,
+            json,
+            auths: self.recorded_auths(),
+            events,
+            trace,
+            blocks,
+            timeline,
+            cpu_instructions: 0,
+            memory_bytes: 0,
+        })
+    }
+
+    /// Events the contracts published during the last invocation, leaving
+    /// out those of calls that failed and were rolled back
+    fn contract_events(&self) -> Result<Vec<ContractEvent>> {
+        let events = self.env.host().get_events().map_err(|e| {
+            DebuggerError::ExecutionError(format!("Failed to read events: {:?}", e))
+        })?;
+        let empty = ContractSpec::default();
+        let codec = ValueCodec::new(&empty);
+
+        let mut contract_events = Vec::new();
+        for e in events.0.iter() {
+            if e.event.type_ != ContractEventType::Contract || e.failed_call {
+                continue;
+            }
+            let Some(id) = e.event.contract_id.clone() else {
+                continue;
+            };
+            let contract = ScAddress::Contract(id);
+            let ContractEventBody::V0(body) = &e.event.body;
+            contract_events.push(ContractEvent {
+                contract: self.contracts.display_name(&contract),
+                contract_id: contract.to_string(),
+                topics: body
+                    .topics
+                    .iter()
+                    .map(|t| codec.to_json_untyped(t))
+                    .collect(),
+                data: codec.to_json_untyped(&body.data),
+            });
+        }
+        Ok(contract_events)
+    }
+
+    /// Function entries and exits, and how often each basic block ran,
+    /// from the hook calls of an invocation
+    fn summarize(&self, timeline: &[HookEvent]) -> (Vec<FunctionEvent>, Vec<BlockHits>) {
+        let mut trace = Vec::new();
+        let mut blocks: HashMap<(String, u32), u64> = HashMap::new();
+        for event in timeline {
+            let (kind, index) = match event.hook {
+                Hook::Enter(index) => (HookKind::Enter, index),
+                Hook::Exit(index) => (HookKind::Exit, index),
+                Hook::Block(block) => {
+                    let contract = self.contracts.display_name(&event.contract);
+                    *blocks.entry((contract, block)).or_default() += 1;
+                    continue;
+                }
+                Hook::Step(_) | Hook::Host(_) | Hook::Trap(_) => continue,
+            };
+            let registered = self.contracts.by_address(&event.contract);
+            trace.push(FunctionEvent {
+                contract: self.contracts.display_name(&event.contract),
+                function: registered
+                    .map(|c| c.function_name(index))
+                    .unwrap_or_else(|| format!("func[{}]", index)),
+                index,
+                kind,
+            });
+        }
+        let mut blocks: Vec<BlockHits> = blocks
+            .into_iter()
+            .map(|((contract, block), hits)| BlockHits {
+                contract,
+                block,
+                hits,
+            })
+            .collect();
+        blocks.sort_by(|a, b| (&a.contract, a.block).cmp(&(&b.contract, b.block)));
+        (trace, blocks)
+    }
+
+    /// Register the probe receiving the hook calls of a contract
+    fn register_probe(&mut self, contract: &Address) -> Result<()> {
+        let address = probe::probe_address(&self.env, contract);
+        if !self.instrumenting() || self.probes.contains(&ScAddress::from(&address)) {
+            return Ok(());
+        }
+        let probe = Probe::new(ScAddress::from(contract), self.probe.clone());
+        catch_unwind(AssertUnwindSafe(|| {
+            self.env.register_at(&address, probe, ())
+        }))
+        .map_err(|_| {
+            DebuggerError::ExecutionError(
+                "Failed to register the debugger probe; see the host error above".to_string(),
+            )
+        })?;
+        self.probes.insert(ScAddress::from(&address));
+        Ok(())
+    }
+
+    /// Run session setup outside the budget of invocations. The host meters
+    /// uploads too, and a contract built with debug info can cost more to
+    /// parse than an invocation may spend.
+    fn unmetered<T>(&self, f: impl FnOnce() -> T) -> T {
+        let mut budget = self.env.cost_estimate().budget();
+        budget.reset_unlimited();
+        let result = f();
+        let (cpu, mem) = self.budget_limits;
+        budget.reset_limits(cpu, mem);
+        result
+    }
+
+    /// Run other code at a registered contract's address, keeping its
+    /// storage, with a probe for any hooks in it
+    fn replace_code(&mut self, address: &Address, code: &[u8]) -> Result<()> {
+        self.register_probe(address)?;
+        let env = &self.env;
+        self.unmetered(|| {
+            catch_unwind(AssertUnwindSafe(|| {
+                let hash = env
+                    .deployer()
+                    .upload_contract_wasm(Bytes::from_slice(env, code));
+                env.as_contract(address, || {
+                    env.deployer().update_current_contract_wasm(hash)
+                });
+            }))
+        })
+        .map_err(|_| {
+            DebuggerError::ExecutionError(format!(
+                "Failed to replace the code of contract {}; see the host error above",
+                ScAddress::from(address)
+            ))
+        })?;
+        Ok(())
+    }
+
+    /// Instrument the contracts registered from WASM so far again, with the
+    /// hooks enabled now
+    fn reinstrument(&mut self) -> Result<()> {
+        let wasms: Vec<(ScAddress, Vec<u8>)> = self
+            .wasms
+            .iter()
+            .map(|(id, wasm)| (id.clone(), wasm.clone()))
+            .collect();
+        for (id, wasm) in wasms {
+            let Some(index) = self.contracts.index_of(&id.to_string()) else {
+                continue;
+            };
+            let Some(contract) = self.contracts.at(index) else {
+                continue;
+            };
+            let (alias, address) = (contract.alias().to_string(), contract.address().clone());
+            let module = self.instrument(&alias, &wasm);
+            self.replace_code(&address, &module.wasm)?;
+            self.keep_original(&module.wasm, &wasm)?;
+            self.contracts
+                .set_code(index, &module, SourceMap::from_wasm(&wasm));
+        }
+        Ok(())
+    }
+
+    /// Upload the original code of an instrumented contract as well, so
+    /// exported snapshots can run it in place of the hooked code
+    fn keep_original(&mut self, instrumented: &[u8], wasm: &[u8]) -> Result<()> {
+        if instrumented == wasm {
+            return Ok(());
+        }
+        let env = &self.env;
+        let hash = self
+            .unmetered(|| {
+                catch_unwind(AssertUnwindSafe(|| {
+                    env.deployer()
+                        .upload_contract_wasm(Bytes::from_slice(env, wasm))
+                }))
+            })
+            .map_err(|_| {
+                DebuggerError::ExecutionError(
+                    "Failed to upload the original contract code; see the host error above"
+                        .to_string(),
+                )
+            })?;
+        self.originals.insert(
+            Hash(Sha256::digest(instrumented).into()),
+            Hash(hash.to_array()),
+        );
+        Ok(())
+    }
+
+    /// Whether contracts are instrumented with hooks at all
+    fn instrumenting(&self) -> bool {
+        self.tracing || self.coverage || self.stepping || self.host_calls
+    }
+
+    /// Instrument a contract as enabled, falling back to the original code
+    /// if the module cannot be rewritten
+    fn instrument(&self, alias: &str, wasm: &[u8]) -> InstrumentedModule {
+        if !self.instrumenting() {
+            return InstrumentedModule::original(wasm);
+        }
+        let mut instrumenter = Instrumenter::new();
+        if self.coverage {
+            instrumenter = instrumenter.with_coverage();
+        }
+        if self.stepping {
+            instrumenter = instrumenter.with_stepping();
+        }
+        if self.host_calls {
+            instrumenter = instrumenter.with_host_calls();
+        }
+        instrumenter.instrument(wasm).unwrap_or_else(|e| {
+            warn!(
+                "Could not instrument contract '{}', function calls will not be traced: {}",
+                alias, e
+            );
+            InstrumentedModule::original(wasm)
+        })
+    }
+
+    /// Authorization trees recorded during the last invocation
+    fn recorded_auths(&self) -> Vec<AuthEntry> {
+        self.env
+            .auths()
+            .iter()
+            .map(|(address, invocation)| AuthEntry {
+                address: self.contracts.display_name(&ScAddress::from(address)),
+                invocation: self.convert_invocation(invocation),
+            })
+            .collect()
+    }
+
+    fn convert_invocation(&self, invocation: &AuthorizedInvocation) -> AuthInvocation {
+        let (contract, function, args) = match &invocation.function {
+            AuthorizedFunction::Contract((contract, function, args)) => {
+                let contract = self.contracts.display_name(&ScAddress::from(contract));
+                let function = function.to_string();
+                let args: Vec<ScVal> = args
+                    .iter()
+                    .filter_map(|arg| ScVal::try_from_val(&self.env, &arg).ok())
+                    .collect();
+                let args = self.render_call_args(&contract, &function, &args);
+                (contract, function, Some(args))
+            }
+            AuthorizedFunction::CreateContractHostFn(_)
+            | AuthorizedFunction::CreateContractV2HostFn(_) => {
+                ("host".to_string(), "create_contract".to_string(), None)
+            }
+        };
+
+        AuthInvocation {
+            contract,
+            function,
+            args,
+            sub_invocations: invocation
+                .sub_invocations
+                .iter()
+                .map(|sub| self.convert_invocation(sub))
+                .collect(),
+        }
+    }
+
+    /// Render call arguments, using parameter types when the callee's spec is known
+    fn render_call_args(
+        &self,
+        contract: &str,
+        function: &str,
+        args: &[ScVal],
+    ) -> Vec<serde_json::Value> {
+        let empty = ContractSpec::default();
+        let spec = self.contracts.get(contract).map_or(&empty, |c| c.spec());
+        let codec = ValueCodec::new(spec);
+        let inputs = spec
+            .function(function)
+            .map(|f| f.inputs.as_slice())
+            .filter(|inputs| inputs.len() == args.len());
+
+        match inputs {
+            Some(inputs) => inputs
+                .iter()
+                .zip(args)
+                .map(|(input, arg)| codec.to_json(&input.type_, arg))
+                .collect(),
+            None => args.iter().map(|arg| codec.to_json_untyped(arg)).collect(),
+        }
+    }
+
+    /// Bring explicitly granted entries into the form recorded ones are rendered in
+    fn normalize_auth_invocation(&self, invocation: &mut AuthInvocation) -> Result<()> {
+        if let Some(contract) = self.contracts.get(&invocation.contract) {
+            invocation.contract = contract.alias().to_string();
+        }
+        if let Some(scvals) = self.auth_args(invocation)? {
+            invocation.args =
+                Some(self.render_call_args(&invocation.contract, &invocation.function, &scvals));
+        }
+        for sub in &mut invocation.sub_invocations {
+            self.normalize_auth_invocation(sub)?;
+        }
+        Ok(())
+    }
+
+    /// Encode the arguments of a granted invocation, using the callee's spec
+    /// when it is known
+    fn auth_args(&self, invocation: &AuthInvocation) -> Result<Option<Vec<ScVal>>> {
+        let Some(args) = &invocation.args else {
+            return Ok(None);
+        };
+        let empty = ContractSpec::default();
+        let spec = self
+            .contracts
+            .get(&invocation.contract)
+            .map_or(&empty, |c| c.spec());
+        let codec = ValueCodec::new(spec).with_aliases(self.contracts.aliases());
+        let json = serde_json::Value::Array(args.clone());
+        let scvals = match spec.function(&invocation.function) {
+            Some(spec_fn) => codec.encode_args(spec_fn, &json)?,
+            None => codec.infer_args(&json)?,
+        };
+        Ok(Some(scvals))
+    }
+
+    fn grant(&self, entry: &AuthEntry) -> Result<Grant> {
+        let address = self.contracts.resolve(&entry.address).ok_or_else(|| {
+            DebuggerError::InvalidArguments(format!(
+                "Unknown address or alias '{}' in authorization list",
+                entry.address
+            ))
+        })?;
+        Ok(Grant {
+            address,
+            invocation: self.granted_invocation(&entry.invocation, true)?,
+        })
+    }
+
+    fn granted_invocation(
+        &self,
+        invocation: &AuthInvocation,
+        top_level: bool,
+    ) -> Result<GrantedInvocation> {
+        let contract = self
+            .contracts
+            .resolve(&invocation.contract)
+            .ok_or_else(|| {
+                DebuggerError::InvalidArguments(format!(
+                    "Unknown contract '{}' in authorization list",
+                    invocation.contract
+                ))
+            })?;
+        let args = self.auth_args(invocation)?;
+        if args.is_none() && !top_level {
+            return Err(DebuggerError::InvalidArguments(format!(
+                "Authorization of {}::{} must list its args; only top-level invocations \
+                 can leave them out",
+                invocation.contract, invocation.function
+            ))
+            .into());
+        }
+        Ok(GrantedInvocation {
+            contract,
+            function: invocation.function.clone(),
+            args,
+            sub_invocations: invocation
+                .sub_invocations
+                .iter()
+                .map(|sub| self.granted_invocation(sub, false))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Let the debugger sign for an address granted authorizations: accounts
+    /// get its key as a signer, and unknown contracts an account contract
+    /// approving anything, as `Env::mock_auths` does
+    fn add_signer(&self, address: &ScAddress) -> Result<()> {
+        match address {
+            ScAddress::Account(account) => {
+                let key = debugger_signer();
+                assets::update_account(&self.env, account, |entry| {
+                    if entry.signers.iter().any(|s| s.key == key) {
+                        return;
+                    }
+                    let mut signers = entry.signers.to_vec();
+                    signers.push(Signer { key, weight: 255 });
+                    if let Ok(signers) = signers.try_into() {
+                        entry.signers = signers;
+                    }
+                })
+            }
+            ScAddress::Contract(_) if self.contracts.by_address(address).is_none() => {
+                let address = Address::try_from_val(&self.env, &ScVal::Address(address.clone()))
+                    .map_err(|e| {
+                        DebuggerError::InvalidArguments(format!(
+                            "Invalid address {}: {:?}",
+                            address, e
+                        ))
+                    })?;
+                self.env.register_at(&address, MockAuthContract, ());
+                Ok(())
+            }
+            ScAddress::Contract(_) => Ok(()),
+        }
+    }
+
+    /// In explicit mode, hand the host the granted authorizations that apply
+    /// to a call, signed, so any other `require_auth` fails inside it
+    fn authorize(&self, contract: &RegisteredContract, function: &str, args: &[Val]) -> Result<()> {
+        if !matches!(self.auth, Some(AuthConfig::Explicit(_))) {
+            return Ok(());
+        }
+        let args = args
+            .iter()
+            .map(|arg| ScVal::try_from_val(&self.env, arg))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| {
+                DebuggerError::InvalidArguments(format!("Failed to convert arguments: {:?}", e))
+            })?;
+        let address = ScAddress::from(contract.address());
+        let entries = self
+            .grants
+            .iter()
+            .filter_map(|grant| {
+                let invocation = grant.invocation.to_xdr((&address, function, &args))?;
+                Some(self.sign(&grant.address, invocation))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.env.set_auths(&entries);
+        Ok(())
+    }
+
+    /// Authorization entry for an invocation tree, signed by the debugger's
+    /// key for accounts
+    fn sign(
+        &self,
+        address: &ScAddress,
+        invocation: SorobanAuthorizedInvocation,
+    ) -> Result<SorobanAuthorizationEntry> {
+        let nonce = self.nonce.get();
+        self.nonce.set(nonce.wrapping_add(1) & i64::MAX);
+        let expiration = self.env.ledger().sequence() + self.env.storage().max_ttl();
+        let xdr_err =
+            |e| DebuggerError::ExecutionError(format!("Failed to sign authorization: {}", e));
+        let signature = match address {
+            ScAddress::Account(_) => {
+                let preimage =
+                    HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+                        network_id: Hash(self.env.ledger().network_id().to_array()),
+                        nonce,
+                        signature_expiration_ledger: expiration,
+                        invocation: invocation.clone(),
+                    });
+                let payload = Sha256::digest(preimage.to_xdr(Limits::none()).map_err(xdr_err)?);
+                let key = signing_key();
+                let signature = key.sign(&payload).to_bytes();
+                let field = |name: &str, bytes: &[u8]| -> std::result::Result<ScMapEntry, _> {
+                    Ok(ScMapEntry {
+                        key: ScVal::Symbol(ScSymbol(name.try_into()?)),
+                        val: ScVal::Bytes(ScBytes(bytes.try_into()?)),
+                    })
+                };
+                let signature = ScMap(
+                    vec![
+                        field("public_key", &key.verifying_key().to_bytes()).map_err(xdr_err)?,
+                        field("signature", &signature).map_err(xdr_err)?,
+                    ]
+                    .try_into()
+                    .map_err(xdr_err)?,
+                );
+                let signatures = vec![ScVal::Map(Some(signature))];
+                ScVal::Vec(Some(ScVec(signatures.try_into().map_err(xdr_err)?)))
+            }
+            ScAddress::Contract(_) => ScVal::Void,
+        };
+        Ok(SorobanAuthorizationEntry {
+            credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+                address: address.clone(),
+                nonce,
+                signature_expiration_ledger: expiration,
+                signature,
+            }),
+            root_invocation: invocation,
+        })
+    }
+
+    /// Strkey of the selected contract
+    pub fn contract_id(&self) -> Result<String> {
+        Ok(self.target()?.contract_id())
+    }
+
+    /// Parse a fixed contract address
+    fn contract_address(&self, strkey: &str) -> Result<Address> {
+        match ScAddress::from_str(strkey) {
+            Ok(address @ ScAddress::Contract(_)) => {
+                Address::try_from_val(&self.env, &ScVal::Address(address)).map_err(|e| {
+                    DebuggerError::InvalidArguments(format!(
+                        "Invalid contract address {}: {:?}",
+                        strkey, e
+                    ))
+                    .into()
+                })
+            }
+            _ => Err(DebuggerError::InvalidArguments(format!(
+                "'{}' is not a contract address (expected a C... strkey)",
+                strkey
+            ))
+            .into()),
+        }
+    }
+
     /// Parse JSON arguments into contract values
-    fn parse_args(&self, _args_json: &str) -> Result<Vec<Val>> {
-        // TODO: Implement proper argument parsing
-        // For now, return empty vec
-        info!("Argument parsing not yet implemented");
-        Ok(vec![])
+    fn parse_args(
+        &self,
+        contract: &RegisteredContract,
+        function: &str,
+        args_json: &str,
+    ) -> Result<Vec<Val>> {
+        let json: serde_json::Value = serde_json::from_str(args_json).map_err(|e| {
+            DebuggerError::InvalidArguments(format!("Invalid JSON arguments: {}", e))
+        })?;
+
+        let codec = ValueCodec::new(contract.spec()).with_aliases(self.contracts.aliases());
+        let scvals = match contract.spec().function(function) {
+            Some(spec_fn) => codec.encode_args(spec_fn, &json)?,
+            None => {
+                warn!(
+                    "No spec for function '{}', inferring argument types",
+                    function
+                );
+                codec.infer_args(&json)?
+            }
+        };
+
+        self.to_vals(&scvals)
+    }
+
+    fn to_vals(&self, scvals: &[ScVal]) -> Result<Vec<Val>> {
+        scvals
+            .iter()
+            .map(|scval| {
+                Val::try_from_val(&self.env, scval).map_err(|e| {
+                    DebuggerError::InvalidArguments(format!(
+                        "Failed to convert argument {:?}: {:?}",
+                        scval, e
+                    ))
+                    .into()
+                })
+            })
+            .collect()
+    }
+}
+
+fn is_issuer(asset: &Asset, account: &AccountId) -> bool {
+    match asset {
+        Asset::Native => false,
+        Asset::CreditAlphanum4(a) => &a.issuer == account,
+        Asset::CreditAlphanum12(a) => &a.issuer == account,
+    }
+}
+
+/// Flatten the result of a setup call on an asset contract
+fn asset_call<T, C: fmt::Debug, E: fmt::Debug>(
+    alias: &str,
+    function: &str,
+    result: std::result::Result<std::result::Result<T, C>, E>,
+) -> Result<T> {
+    match result {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(DebuggerError::ExecutionError(format!(
+            "Asset '{}': {} returned an unexpected value: {:?}",
+            alias, function, e
+        ))
+        .into()),
+        Err(e) => Err(DebuggerError::ExecutionError(format!(
+            "Asset '{}': {} failed: {:?}",
+            alias, function, e
+        ))
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::auth::parse_auth_entries;
+    use serde_json::json;
+
+    const TEST_CONTRACT: &[u8] = include_bytes!("../../tests/fixtures/test_contract.wasm");
+
+    #[test]
+    fn test_explicit_auth_rolls_back_denied_calls() {
+        let mut executor = ContractExecutor::new(TEST_CONTRACT.to_vec(), None).unwrap();
+        executor.add_account("alice").unwrap();
+        executor.add_account("bob").unwrap();
+        executor.execute("mint", Some(r#"["alice", 100]"#)).unwrap();
+        let granted = parse_auth_entries(
+            r#"[{"address": "alice", "invocation": {"contract": "contract",
+                 "function": "transfer", "args": ["alice", "bob", 60]}}]"#,
+        )
+        .unwrap();
+        executor.set_auth(AuthConfig::Explicit(granted)).unwrap();
+        let balance = |id: &str| {
+            let args = format!(r#"["{}"]"#, id);
+            executor.execute("balance", Some(&args)).unwrap().json
+        };
+
+        // The granted transfer goes through, and is reported as authorized
+        let result = executor
+            .execute("transfer", Some(r#"["alice", "bob", 60]"#))
+            .unwrap();
+        assert_eq!(result.auths.len(), 1);
+        assert_eq!(result.auths[0].address, "alice");
+        assert_eq!(balance("alice"), json!(40));
+
+        // Other arguments are not granted, and the call changes nothing
+        assert!(executor
+            .execute("transfer", Some(r#"["alice", "bob", 30]"#))
+            .is_err());
+        assert_eq!(balance("alice"), json!(40));
+        assert_eq!(balance("bob"), json!(60));
+
+        // The debugger's signer is left out of exported accounts
+        let signer = debugger_signer();
+        assert!(executor.ledger_snapshot().ledger_entries.iter().all(
+            |(_, (entry, _))| match &entry.data {
+                LedgerEntryData::Account(account) => {
+                    account.signers.iter().all(|s| s.key != signer)
+                }
+                _ => true,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_ledger_snapshot_exports_original_code() {
+        let mut executor = ContractExecutor::empty(&LedgerConfig::default(), None).unwrap();
+        executor.enable_tracing().unwrap();
+        let contract = executor
+            .register_contract("contract", TEST_CONTRACT, None, None)
+            .unwrap();
+        executor.select_contract("contract").unwrap();
+        let alice = executor.add_account("alice").unwrap();
+        executor.execute("mint", Some(r#"["alice", 100]"#)).unwrap();
+
+        let snapshot = executor.ledger_snapshot();
+        let hash = Hash(Sha256::digest(TEST_CONTRACT).into());
+        for (key, (entry, _)) in &snapshot.ledger_entries {
+            match (key.as_ref(), &entry.data) {
+                (LedgerKey::ContractCode(_), LedgerEntryData::ContractCode(code)) => {
+                    assert_eq!(code.hash, hash);
+                    assert_eq!(code.code.as_slice(), TEST_CONTRACT);
+                }
+                (_, LedgerEntryData::ContractData(data)) => {
+                    if let ScVal::ContractInstance(instance) = &data.val {
+                        assert_eq!(instance.executable, ContractExecutable::Wasm(hash.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The exported contract runs without the probes
+        let mut restored =
+            ContractExecutor::empty(&LedgerConfig::default(), Some(snapshot)).unwrap();
+        restored.select_contract(&contract).unwrap();
+        let args = format!(r#"["{}"]"#, alice);
+        let result = restored.execute("balance", Some(&args)).unwrap();
+        assert_eq!(result.json, json!(100));
+    }
+
+    #[test]
+    fn test_instrumented_invocations_keep_budget_limits() {
+        let plain = ContractExecutor::new(TEST_CONTRACT.to_vec(), None).unwrap();
+        let cost = plain
+            .execute("spin", Some("[1000]"))
+            .unwrap()
+            .cpu_instructions;
+        let mut traced = ContractExecutor::new(TEST_CONTRACT.to_vec(), None).unwrap();
+        traced.enable_tracing().unwrap();
+        let traced_cost = traced
+            .execute("spin", Some("[1000]"))
+            .unwrap()
+            .cpu_instructions;
+        assert!(
+            traced_cost.abs_diff(cost) < cost / 4,
+            "{} vs {}",
+            traced_cost,
+            cost
+        );
+
+        // A call over the limits fails the same way with hooks
+        for executor in [&plain, &traced] {
+            let err = executor.execute("spin", Some("[10000000]")).unwrap_err();
+            assert!(err.to_string().contains("CPU budget exceeded"), "{}", err);
+        }
     }
 }
@@ -0,0 +1,90 @@
+use soroban_env_common::call_macro_with_all_host_functions;
+
+/// A function the Soroban host provides to contracts, as contracts import it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFunction {
+    /// Import module, such as `l` for ledger functions
+    pub module: &'static str,
+    /// Import name within the module
+    pub export: &'static str,
+    /// Name of the function in the host interface
+    pub name: &'static str,
+    /// Parameter names and types
+    pub params: &'static [(&'static str, &'static str)],
+}
+
+impl HostFunction {
+    /// Whether a parameter takes a plain integer rather than a `Val`
+    pub fn is_raw(&self, param: usize) -> bool {
+        matches!(self.params.get(param), Some((_, "i64" | "u64")))
+    }
+}
+
+macro_rules! host_function_table {
+    {
+        $(
+            $(#[$mod_attr:meta])*
+            mod $mod_id:ident $mod_str:literal
+            {
+                $(
+                    $(#[$fn_attr:meta])*
+                    { $fn_str:literal, $($min_proto:literal)?, $($max_proto:literal)?, fn $fn_id:ident ($($arg:ident:$type:ty),*) -> $ret:ty }
+                )*
+            }
+        )*
+    }
+    =>
+    {
+        /// Every host function, in the order of the host interface
+        pub static HOST_FUNCTIONS: &[HostFunction] = &[
+            $(
+                $(
+                    HostFunction {
+                        module: $mod_str,
+                        export: $fn_str,
+                        name: stringify!($fn_id),
+                        params: &[$((stringify!($arg), stringify!($type))),*],
+                    },
+                )*
+            )*
+        ];
+    };
+}
+
+call_macro_with_all_host_functions! { host_function_table }
+
+/// Find a host function by its import, returning its index in
+/// [`HOST_FUNCTIONS`]
+pub fn lookup(module: &str, export: &str) -> Option<(u32, &'static HostFunction)> {
+    HOST_FUNCTIONS
+        .iter()
+        .enumerate()
+        .find(|(_, f)| f.module == module && f.export == export)
+        .map(|(index, f)| (index as u32, f))
+}
+
+/// The host function at an index of [`HOST_FUNCTIONS`], if there is one
+pub fn get(index: u32) -> Option<&'static HostFunction> {
+    HOST_FUNCTIONS.get(index as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let (index, put) = lookup("l", "_").unwrap();
+        assert_eq!(put.name, "put_contract_data");
+        assert_eq!(
+            put.params,
+            &[("k", "Val"), ("v", "Val"), ("t", "StorageType")]
+        );
+        assert_eq!(HOST_FUNCTIONS[index as usize], *put);
+
+        let (_, from_u64) = lookup("i", "_").unwrap();
+        assert_eq!(from_u64.name, "obj_from_u64");
+        assert!(from_u64.is_raw(0));
+        assert!(lookup("l", "~").is_none());
+    }
+}
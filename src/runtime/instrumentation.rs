@@ -1,19 +1,281 @@
-/// WASM instrumentation for adding debug hooks
-/// This will be implemented in Phase 3
+use crate::runtime::host_functions;
+use crate::{DebuggerError, Result};
+use soroban_env_common::{SymbolSmall, U32Val, Val};
+use std::collections::{HashMap, HashSet};
+use walrus::ir::{
+    BinaryOp, Block, Br, BrIf, BrTable, Call, Const, Instr, InstrLocId, InstrSeqId, InstrSeqType,
+    UnaryOp, Value,
+};
+use walrus::{
+    FunctionBuilder, FunctionId, GlobalId, InitExpr, LocalFunction, LocalId, Module, ValType,
+};
+
+/// Value the probe returns to have step hooks report every instruction
+pub(crate) const MODE_STEP: u32 = 1;
+/// Value the probe returns to have step hooks do nothing
+pub(crate) const MODE_RUN: u32 = 0;
+
+/// A hook call, identified by the name of the probe function it calls: a
+/// kind letter followed by an id in base 62
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hook {
+    /// A function was entered, by index in the original module
+    Enter(u32),
+    /// A function is about to return
+    Exit(u32),
+    /// A basic block started, by id in [`InstrumentedModule::blocks`]
+    Block(u32),
+    /// An instruction is about to run, by id in [`InstrumentedModule::steps`]
+    Step(u32),
+    /// A host function is about to be called, by index in
+    /// [`HOST_FUNCTIONS`](host_functions::HOST_FUNCTIONS); the probe receives the call's arguments
+    Host(u32),
+    /// A function is about to trap with `unreachable`, which is how
+    /// contracts panic
+    Trap(u32),
+}
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl Hook {
+    /// Name of the probe function the hook calls
+    pub fn name(&self) -> String {
+        let (kind, mut id) = match *self {
+            Hook::Enter(id) => ('e', id),
+            Hook::Exit(id) => ('x', id),
+            Hook::Block(id) => ('b', id),
+            Hook::Step(id) => ('s', id),
+            Hook::Host(id) => ('h', id),
+            Hook::Trap(id) => ('t', id),
+        };
+        let mut digits = Vec::new();
+        loop {
+            digits.push(DIGITS[(id % 62) as usize] as char);
+            id /= 62;
+            if id == 0 {
+                break;
+            }
+        }
+        std::iter::once(kind)
+            .chain(digits.into_iter().rev())
+            .collect()
+    }
+
+    /// Recognize the name of a probe function
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        let kind = chars.next()?;
+        let digits = chars.as_str();
+        if digits.is_empty() {
+            return None;
+        }
+        let mut id: u32 = 0;
+        for c in digits.bytes() {
+            let digit = DIGITS.iter().position(|&d| d == c)? as u32;
+            id = id.checked_mul(62)?.checked_add(digit)?;
+        }
+        match kind {
+            'e' => Some(Hook::Enter(id)),
+            'x' => Some(Hook::Exit(id)),
+            'b' => Some(Hook::Block(id)),
+            's' => Some(Hook::Step(id)),
+            'h' => Some(Hook::Host(id)),
+            't' => Some(Hook::Trap(id)),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a hook reports entering or leaving a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    Enter,
+    Exit,
+}
+
+/// A function entry or exit reported by instrumented code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionEvent {
+    /// Alias of the contract the function belongs to
+    pub contract: String,
+    /// Function name from the name section or exports, or `func[N]`
+    pub function: String,
+    /// Index of the function in the original module
+    pub index: u32,
+    pub kind: HookKind,
+}
+
+/// How often a basic block ran during an invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHits {
+    /// Alias of the contract the block belongs to
+    pub contract: String,
+    /// Id of the block in the contract's [`InstrumentedModule::blocks`]
+    pub block: u32,
+    pub hits: u64,
+}
+
+/// A basic block instrumented for coverage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Index of the function the block belongs to, in the original module
+    pub function: u32,
+    /// Offset of the block's first instruction in the original module
+    pub offset: Option<usize>,
+}
+
+/// An instruction instrumented for stepping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepPoint {
+    /// Index of the function the instruction belongs to, in the original module
+    pub function: u32,
+    /// Offset of the instruction in the original module
+    pub offset: usize,
+}
+
+/// A rewritten module, together with the names of its functions and the
+/// basic blocks and instructions its hooks report
+#[derive(Debug, Clone)]
+pub struct InstrumentedModule {
+    pub wasm: Vec<u8>,
+    names: HashMap<u32, String>,
+    blocks: Vec<BasicBlock>,
+    steps: Vec<StepPoint>,
+}
+
+impl InstrumentedModule {
+    /// A module left as it was, for code that cannot be instrumented
+    pub(crate) fn original(wasm: &[u8]) -> Self {
+        Self {
+            wasm: wasm.to_vec(),
+            names: HashMap::new(),
+            blocks: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Names of all functions that have one, by index
+    pub fn function_names(&self) -> &HashMap<u32, String> {
+        &self.names
+    }
+
+    /// Basic blocks by the id their hooks report
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Instructions by the id their step hooks report
+    pub fn steps(&self) -> &[StepPoint] {
+        &self.steps
+    }
+}
+
+/// WASM instrumentation for adding debug hooks.
+///
+/// A contract can only call functions the host provides, so hooks call a
+/// probe contract the debugger registers next to every instrumented contract,
+/// at the address the contract would deploy to with the SHA-256 of nothing as
+/// salt. The name of the called function identifies the [`Hook`]. The probe
+/// address and the empty argument vector are created by the first hook of
+/// each invocation and kept in globals.
+///
+/// Every function defined in the module calls a hook when it is entered and
+/// when it returns, and before any `unreachable`; exported functions pass
+/// their arguments to the entry hook and their result to the exit hook. With
+/// coverage enabled, every basic block also calls one
+/// when it starts; blocks begin at the start of a function or structured
+/// block, and after a branch or the end of a nested block. With stepping
+/// enabled, every instruction calls one before it runs, and calls to host
+/// functions go through wrappers that pass the arguments to a hook first, as
+/// long as the last hook call returned [`MODE_STEP`].
 pub struct Instrumenter {
-    // TODO: Implement WASM instrumentation
+    coverage: bool,
+    stepping: bool,
+    host_calls: bool,
 }
 
 impl Instrumenter {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            coverage: false,
+            stepping: false,
+            host_calls: false,
+        }
+    }
+
+    /// Also instrument basic blocks for coverage
+    pub fn with_coverage(mut self) -> Self {
+        self.coverage = true;
+        self
+    }
+
+    /// Also instrument every instruction for stepping
+    pub fn with_stepping(mut self) -> Self {
+        self.stepping = true;
+        self
+    }
+
+    /// Also report each host function call, as stepping does
+    pub fn with_host_calls(mut self) -> Self {
+        self.host_calls = true;
+        self
     }
 
     /// Instrument WASM bytecode with debugging hooks
-    pub fn instrument(&self, _wasm: &[u8]) -> Vec<u8> {
-        // TODO: Use wasmparser/walrus to add hooks
-        // For now, return original WASM
-        vec![]
+    pub fn instrument(&self, wasm: &[u8]) -> Result<InstrumentedModule> {
+        let mut module = Module::from_buffer(wasm)
+            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e)))?;
+
+        // Indices are taken before imports are added, which shifts them
+        let locals: Vec<(FunctionId, u32)> = module
+            .funcs
+            .iter_local()
+            .map(|(id, _)| (id, id.index() as u32))
+            .collect();
+        let names = function_names(&module);
+        let host_imports = host_imports(&module);
+        let exports: HashSet<FunctionId> = module
+            .exports
+            .iter()
+            .filter_map(|export| match export.item {
+                walrus::ExportItem::Function(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        let mut hooks = HookFunctions::add(&mut module);
+        if self.stepping || self.host_calls {
+            hooks.wrap_host_calls(&mut module, &host_imports);
+        }
+        let mut tables = Tables {
+            blocks: self.coverage.then(Vec::new),
+            steps: self.stepping.then(Vec::new),
+        };
+        for (id, index) in locals {
+            let (params, results) = {
+                let ty = module.types.get(module.funcs.get(id).ty());
+                (ty.params().to_vec(), ty.results().to_vec())
+            };
+            // Contract functions take nothing but `Val`s
+            let entry_args = exports.contains(&id) && params.iter().all(|p| *p == ValType::I64);
+            let result =
+                (entry_args && results == [ValType::I64]).then(|| module.locals.add(ValType::I64));
+            let body_ty = InstrSeqType::new(&mut module.types, &[], &results);
+            let func = module.funcs.get_mut(id).kind.unwrap_local_mut();
+            let frame = Frame {
+                index,
+                entry_args,
+                result,
+            };
+            instrument_function(func, body_ty, &hooks, frame, &mut tables);
+        }
+
+        Ok(InstrumentedModule {
+            wasm: module.emit_wasm(),
+            names,
+            blocks: tables.blocks.unwrap_or_default(),
+            steps: tables.steps.unwrap_or_default(),
+        })
     }
 }
 
@@ -22,3 +284,611 @@ impl Default for Instrumenter {
         Self::new()
     }
 }
+
+/// Names from the name section, falling back to export names
+fn function_names(module: &Module) -> HashMap<u32, String> {
+    let mut names: HashMap<u32, String> = module
+        .funcs
+        .iter()
+        .filter_map(|f| Some((f.id().index() as u32, f.name.clone()?)))
+        .collect();
+    for export in module.exports.iter() {
+        if let walrus::ExportItem::Function(id) = export.item {
+            names
+                .entry(id.index() as u32)
+                .or_insert_with(|| export.name.clone());
+        }
+    }
+    names
+}
+
+/// Functions added to the module that hooks call with the `Val` of the
+/// probe function name
+struct HookFunctions {
+    /// Calls the probe
+    hook: FunctionId,
+    /// Calls the probe with an argument vector
+    hook_args: FunctionId,
+    /// Calls the probe if the last call asked for steps
+    step: FunctionId,
+    mode: GlobalId,
+    vec_new: FunctionId,
+    push_back: FunctionId,
+    /// Wrappers to call instead of imported host functions
+    host_calls: HashMap<FunctionId, FunctionId>,
+}
+
+impl HookFunctions {
+    fn add(module: &mut Module) -> Self {
+        let current_contract = import(module, "x", "7", 0);
+        let bytes_new = import(module, "b", "4", 0);
+        let sha256 = import(module, "c", "_", 1);
+        let contract_id = import(module, "l", "a", 2);
+        let vec_new = import(module, "v", "_", 0);
+        let push_back = import(module, "v", "6", 2);
+        let call = import(module, "d", "_", 3);
+
+        let zero = InitExpr::Value(Value::I64(0));
+        let probe = module.globals.add_local(ValType::I64, true, zero);
+        let args = module.globals.add_local(ValType::I64, true, zero);
+        let mode = module.globals.add_local(ValType::I64, true, zero);
+
+        let mut builder = FunctionBuilder::new(&mut module.types, &[], &[]);
+        builder
+            .func_body()
+            .global_get(probe)
+            .unop(UnaryOp::I64Eqz)
+            .if_else(
+                None,
+                |then| {
+                    then.call(current_contract)
+                        .call(bytes_new)
+                        .call(sha256)
+                        .call(contract_id)
+                        .global_set(probe)
+                        .call(vec_new)
+                        .global_set(args);
+                },
+                |_| {},
+            );
+        let init = builder.finish(vec![], &mut module.funcs);
+
+        let name = module.locals.add(ValType::I64);
+        let call_args = module.locals.add(ValType::I64);
+        let mut builder =
+            FunctionBuilder::new(&mut module.types, &[ValType::I64, ValType::I64], &[]);
+        builder
+            .func_body()
+            .call(init)
+            .global_get(probe)
+            .local_get(name)
+            .local_get(call_args)
+            .call(call)
+            .global_set(mode);
+        let hook_args = builder.finish(vec![name, call_args], &mut module.funcs);
+
+        let name = module.locals.add(ValType::I64);
+        let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[]);
+        builder
+            .func_body()
+            .call(init)
+            .local_get(name)
+            .global_get(args)
+            .call(hook_args);
+        let hook = builder.finish(vec![name], &mut module.funcs);
+
+        Self {
+            hook,
+            hook_args,
+            step: step_function(module, hook, mode),
+            mode,
+            vec_new,
+            push_back,
+            host_calls: HashMap::new(),
+        }
+    }
+
+    /// Add a wrapper for each imported host function that, in step mode,
+    /// calls a hook with the arguments before calling the host. Plain integer
+    /// arguments are not `Val`s and are passed as void, except storage types,
+    /// which are passed as `U32Val`s.
+    fn wrap_host_calls(&mut self, module: &mut Module, imports: &[(FunctionId, u32)]) {
+        let push_back = self.push_back;
+        let step_mode = Val::from(U32Val::from(MODE_STEP)).get_payload() as i64;
+        let void = Val::VOID.to_val().get_payload() as i64;
+        let u32_tag = Val::from(U32Val::from(0)).get_payload() as i64;
+        for &(func, index) in imports {
+            let Some(host) = host_functions::get(index) else {
+                continue;
+            };
+            let ty = module.types.get(module.funcs.get(func).ty());
+            let (params, results) = (ty.params().to_vec(), ty.results().to_vec());
+            if params.iter().any(|p| *p != ValType::I64) || params.len() != host.params.len() {
+                continue;
+            }
+            let name = SymbolSmall::try_from_str(&Hook::Host(index).name())
+                .expect("hook names are small symbols");
+            let locals: Vec<_> = params.iter().map(|t| module.locals.add(*t)).collect();
+
+            let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+            let mut body = builder.func_body();
+            body.global_get(self.mode)
+                .i64_const(step_mode)
+                .binop(BinaryOp::I64Eq)
+                .if_else(
+                    None,
+                    |then| {
+                        then.i64_const(Val::from(name).get_payload() as i64)
+                            .call(self.vec_new);
+                        for (param, &local) in locals.iter().enumerate() {
+                            if host.params[param].1 == "StorageType" {
+                                // Passed as a plain integer too, but small
+                                then.local_get(local)
+                                    .i64_const(32)
+                                    .binop(BinaryOp::I64Shl)
+                                    .i64_const(u32_tag)
+                                    .binop(BinaryOp::I64Or);
+                            } else if host.is_raw(param) {
+                                then.i64_const(void);
+                            } else {
+                                then.local_get(local);
+                            }
+                            then.call(push_back);
+                        }
+                        then.call(self.hook_args);
+                    },
+                    |_| {},
+                );
+            for &local in &locals {
+                body.local_get(local);
+            }
+            body.call(func);
+            let wrapper = builder.finish(locals, &mut module.funcs);
+            self.host_calls.insert(func, wrapper);
+        }
+    }
+
+    /// Instructions calling a hook, leaving the stack as it was
+    fn call(&self, hook: Hook) -> Vec<Instr> {
+        let func = match hook {
+            Hook::Step(_) => self.step,
+            _ => self.hook,
+        };
+        let name = SymbolSmall::try_from_str(&hook.name()).expect("hook names are small symbols");
+        vec![
+            Instr::Const(Const {
+                value: Value::I64(Val::from(name).get_payload() as i64),
+            }),
+            Instr::Call(Call { func }),
+        ]
+    }
+
+    /// Instructions calling a hook with the values of locals as arguments,
+    /// leaving the stack as it was
+    fn call_with_args(&self, hook: Hook, args: &[LocalId]) -> Vec<Instr> {
+        let name = SymbolSmall::try_from_str(&hook.name()).expect("hook names are small symbols");
+        let mut instrs = vec![
+            Instr::Const(Const {
+                value: Value::I64(Val::from(name).get_payload() as i64),
+            }),
+            Instr::Call(Call { func: self.vec_new }),
+        ];
+        for &local in args {
+            instrs.push(Instr::LocalGet(walrus::ir::LocalGet { local }));
+            instrs.push(Instr::Call(Call {
+                func: self.push_back,
+            }));
+        }
+        instrs.push(Instr::Call(Call {
+            func: self.hook_args,
+        }));
+        instrs
+    }
+}
+
+/// Imported functions the host provides, with their index in
+/// [`HOST_FUNCTIONS`](host_functions::HOST_FUNCTIONS)
+fn host_imports(module: &Module) -> Vec<(FunctionId, u32)> {
+    module
+        .imports
+        .iter()
+        .filter_map(|import| match import.kind {
+            walrus::ImportKind::Function(func) => {
+                let (index, _) = host_functions::lookup(&import.module, &import.name)?;
+                Some((func, index))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn step_function(module: &mut Module, hook: FunctionId, mode: GlobalId) -> FunctionId {
+    let step_mode = Val::from(U32Val::from(MODE_STEP)).get_payload() as i64;
+    let name = module.locals.add(ValType::I64);
+    let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[]);
+    builder
+        .func_body()
+        .global_get(mode)
+        .i64_const(step_mode)
+        .binop(BinaryOp::I64Eq)
+        .if_else(
+            None,
+            |then| {
+                then.local_get(name).call(hook);
+            },
+            |_| {},
+        );
+    builder.finish(vec![name], &mut module.funcs)
+}
+
+/// Reuse the module's import of a host function, or add one. Every host
+/// function takes and returns `i64` values.
+fn import(module: &mut Module, namespace: &str, name: &str, params: usize) -> FunctionId {
+    if let Some(id) = module.imports.find(namespace, name) {
+        if let walrus::ImportKind::Function(func) = module.imports.get(id).kind {
+            return func;
+        }
+    }
+    let ty = module
+        .types
+        .add(&vec![ValType::I64; params], &[ValType::I64]);
+    module.add_import_func(namespace, name, ty).0
+}
+
+/// Blocks and instructions given hooks so far, when instrumented
+struct Tables {
+    blocks: Option<Vec<BasicBlock>>,
+    steps: Option<Vec<StepPoint>>,
+}
+
+/// How a function's hooks report it
+struct Frame {
+    /// Index of the function in the original module
+    index: u32,
+    /// Whether the entry hook gets the arguments
+    entry_args: bool,
+    /// Local to pass the result to the exit hook through, if it gets it
+    result: Option<LocalId>,
+}
+
+/// Move the body into a block so every way out of it passes the exit hook:
+/// falling off the end and branches to the body now end up after the block,
+/// and `return`s get the hook right before them.
+fn instrument_function(
+    func: &mut LocalFunction,
+    body_ty: InstrSeqType,
+    hooks: &HookFunctions,
+    frame: Frame,
+    tables: &mut Tables,
+) {
+    let Frame {
+        index,
+        entry_args,
+        result,
+    } = frame;
+    let entry = func.entry_block();
+    let body = func.builder_mut().dangling_instr_seq(body_ty).id();
+    let instrs = std::mem::take(&mut func.block_mut(entry).instrs);
+    func.block_mut(body).instrs = instrs;
+
+    // The result is on top of the stack wherever the function returns
+    let exit = match result {
+        Some(local) => std::iter::once(Instr::LocalTee(walrus::ir::LocalTee { local }))
+            .chain(hooks.call_with_args(Hook::Exit(index), &[local]))
+            .collect(),
+        None => hooks.call(Hook::Exit(index)),
+    };
+    let trap = hooks.call(Hook::Trap(index));
+    let mut pending = vec![body];
+    while let Some(seq) = pending.pop() {
+        let old = std::mem::take(&mut func.block_mut(seq).instrs);
+        let mut new = Vec::with_capacity(old.len());
+        let mut leader = true;
+        for (mut instr, loc) in old {
+            if let Some(blocks) = tables.blocks.as_mut().filter(|_| leader) {
+                let id = blocks.len() as u32;
+                blocks.push(BasicBlock {
+                    function: index,
+                    offset: original_offset(loc),
+                });
+                new.extend(hooks.call(Hook::Block(id)).into_iter().map(|i| (i, loc)));
+            }
+            leader = matches!(
+                instr,
+                Instr::Block(_) | Instr::Loop(_) | Instr::IfElse(_) | Instr::BrIf(_)
+            );
+            if let (Some(steps), Some(offset)) = (tables.steps.as_mut(), original_offset(loc)) {
+                let id = steps.len() as u32;
+                steps.push(StepPoint {
+                    function: index,
+                    offset,
+                });
+                new.extend(hooks.call(Hook::Step(id)).into_iter().map(|i| (i, loc)));
+            }
+            match &mut instr {
+                Instr::Block(b) => pending.push(b.seq),
+                Instr::Loop(l) => pending.push(l.seq),
+                Instr::IfElse(i) => {
+                    pending.push(i.consequent);
+                    pending.push(i.alternative);
+                }
+                Instr::Br(Br { block }) | Instr::BrIf(BrIf { block }) => {
+                    retarget(block, entry, body);
+                }
+                Instr::BrTable(BrTable { blocks, default }) => {
+                    blocks.iter_mut().for_each(|b| retarget(b, entry, body));
+                    retarget(default, entry, body);
+                }
+                Instr::Call(Call { func }) => {
+                    if let Some(wrapper) = hooks.host_calls.get(func) {
+                        *func = *wrapper;
+                    }
+                }
+                Instr::Return(_) => new.extend(exit.iter().cloned().map(|i| (i, loc))),
+                Instr::Unreachable(_) => new.extend(trap.iter().cloned().map(|i| (i, loc))),
+                _ => {}
+            }
+            new.push((instr, loc));
+        }
+        func.block_mut(seq).instrs = new;
+    }
+
+    let loc = Default::default();
+    let enter = if entry_args {
+        hooks.call_with_args(Hook::Enter(index), &func.args)
+    } else {
+        hooks.call(Hook::Enter(index))
+    };
+    let entry_instrs = &mut func.block_mut(entry).instrs;
+    entry_instrs.extend(enter.into_iter().map(|i| (i, loc)));
+    entry_instrs.push((Instr::Block(Block { seq: body }), loc));
+    entry_instrs.extend(exit.into_iter().map(|i| (i, loc)));
+}
+
+fn original_offset(loc: InstrLocId) -> Option<usize> {
+    (!loc.is_default()).then(|| loc.data() as usize)
+}
+
+fn retarget(block: &mut InstrSeqId, from: InstrSeqId, to: InstrSeqId) {
+    if *block == from {
+        *block = to;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_names() {
+        for hook in [
+            Hook::Enter(0),
+            Hook::Exit(61),
+            Hook::Block(62),
+            Hook::Step(u32::MAX),
+            Hook::Host(160),
+            Hook::Trap(3),
+        ] {
+            let name = hook.name();
+            assert!(name.len() <= 9, "{} is not a small symbol", name);
+            assert_eq!(Hook::parse(&name), Some(hook));
+        }
+        assert_eq!(Hook::Exit(61).name(), "xZ");
+        assert_eq!(Hook::Block(62).name(), "b10");
+        assert_eq!(Hook::parse("transfer"), None);
+        assert_eq!(Hook::parse("e"), None);
+    }
+
+    /// (func (export "pick") (param i64) (result i64)
+    ///   local.get 0  i64.eqz  if  i64.const 1  return  end  i64.const 2)
+    fn pick_module() -> Vec<u8> {
+        let mut module = Module::default();
+        let param = module.locals.add(ValType::I64);
+        let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I64]);
+        builder
+            .func_body()
+            .local_get(param)
+            .unop(UnaryOp::I64Eqz)
+            .if_else(
+                None,
+                |then| {
+                    then.i64_const(1).return_();
+                },
+                |_| {},
+            )
+            .i64_const(2);
+        let pick = builder.finish(vec![param], &mut module.funcs);
+        module.exports.add("pick", pick);
+        module.emit_wasm()
+    }
+
+    /// Hook calls in the instrumented `pick`, by the probe function named
+    fn hook_calls(wasm: &[u8]) -> Vec<Hook> {
+        let mut hooks = Vec::new();
+        let mut imported = 0;
+        let mut pick = None;
+        let mut index = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            match payload.unwrap() {
+                wasmparser::Payload::ImportSection(reader) => imported = reader.count(),
+                wasmparser::Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.unwrap();
+                        if export.name == "pick" {
+                            pick = Some(export.index);
+                        }
+                    }
+                }
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    index += 1;
+                    if Some(imported + index - 1) != pick {
+                        continue;
+                    }
+                    // Hooks are called with the symbol of their name
+                    for op in body.get_operators_reader().unwrap() {
+                        if let wasmparser::Operator::I64Const { value } = op.unwrap() {
+                            let symbol = SymbolSmall::try_from(Val::from_payload(value as u64));
+                            if let Some(hook) =
+                                symbol.ok().and_then(|s| Hook::parse(&s.to_string()))
+                            {
+                                hooks.push(hook);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        hooks
+    }
+
+    #[test]
+    fn test_instrument_module() {
+        let instrumented = Instrumenter::new().instrument(&pick_module()).unwrap();
+        wasmparser::validate(&instrumented.wasm).unwrap();
+        assert_eq!(instrumented.function_names().get(&0).unwrap(), "pick");
+        assert!(instrumented.blocks().is_empty());
+        assert!(instrumented.steps().is_empty());
+
+        let module = Module::from_buffer(&instrumented.wasm).unwrap();
+        assert_eq!(module.imports.iter().count(), 7);
+        // `pick` is exported, so its entry hook gets its argument and its
+        // exit hooks its result
+        let push_back = module.imports.find("v", "6").unwrap();
+        let walrus::ImportKind::Function(push_back) = module.imports.get(push_back).kind else {
+            panic!("not a function import");
+        };
+        let pick = module.funcs.get(module.exports.get_func("pick").unwrap());
+        let pick = pick.kind.unwrap_local();
+        assert!(pick
+            .block(pick.entry_block())
+            .instrs
+            .iter()
+            .any(|(instr, _)| matches!(instr, Instr::Call(Call { func }) if *func == push_back)));
+        assert!(pick
+            .block(pick.entry_block())
+            .instrs
+            .iter()
+            .any(|(instr, _)| matches!(instr, Instr::LocalTee(_))));
+        // Entry, exit before the early return and exit after the body
+        assert_eq!(
+            hook_calls(&instrumented.wasm),
+            vec![Hook::Enter(0), Hook::Exit(0), Hook::Exit(0)]
+        );
+    }
+
+    #[test]
+    fn test_instrument_blocks() {
+        let wasm = pick_module();
+        let instrumented = Instrumenter::new()
+            .with_coverage()
+            .instrument(&wasm)
+            .unwrap();
+        wasmparser::validate(&instrumented.wasm).unwrap();
+
+        // The body, the `then` branch and the code after the `if`; the empty
+        // `else` branch has no block
+        let blocks = instrumented.blocks();
+        assert_eq!(blocks.len(), 3);
+        assert!(blocks.iter().all(|b| b.function == 0));
+        assert!(blocks
+            .iter()
+            .all(|b| b.offset.is_some_and(|o| o < wasm.len())));
+        let calls = hook_calls(&instrumented.wasm);
+        assert_eq!(
+            calls.iter().filter(|h| matches!(h, Hook::Block(_))).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_instrument_steps() {
+        let wasm = pick_module();
+        let instrumented = Instrumenter::new()
+            .with_stepping()
+            .instrument(&wasm)
+            .unwrap();
+        wasmparser::validate(&instrumented.wasm).unwrap();
+
+        // local.get, i64.eqz, if, i64.const 1, return, i64.const 2
+        let steps = instrumented.steps();
+        assert_eq!(steps.len(), 6);
+        let offsets: Vec<usize> = steps.iter().map(|s| s.offset).collect();
+        let mut sorted = offsets.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 6);
+        let calls = hook_calls(&instrumented.wasm);
+        assert_eq!(
+            calls.iter().filter(|h| matches!(h, Hook::Step(_))).count(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_wrap_host_calls() {
+        // (func (export "put") (result i64)
+        //   i64.const 0  i64.const 0  i64.const 1  call $put_contract_data)
+        let mut module = Module::default();
+        let ty = module
+            .types
+            .add(&[ValType::I64, ValType::I64, ValType::I64], &[ValType::I64]);
+        let put_contract_data = module.add_import_func("l", "_", ty).0;
+        let mut builder = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+        builder
+            .func_body()
+            .i64_const(0)
+            .i64_const(0)
+            .i64_const(1)
+            .call(put_contract_data);
+        let put = builder.finish(vec![], &mut module.funcs);
+        module.exports.add("put", put);
+
+        let instrumented = Instrumenter::new()
+            .with_stepping()
+            .instrument(&module.emit_wasm())
+            .unwrap();
+        wasmparser::validate(&instrumented.wasm).unwrap();
+
+        // `put` calls a wrapper, which reports the call and calls the host
+        let module = Module::from_buffer(&instrumented.wasm).unwrap();
+        let import = module.imports.find("l", "_").unwrap();
+        let walrus::ImportKind::Function(import) = module.imports.get(import).kind else {
+            panic!("not a function import");
+        };
+        let put = module.exports.get_func("put").unwrap();
+        let calls = |func: FunctionId| -> Vec<FunctionId> {
+            let local = module.funcs.get(func).kind.unwrap_local();
+            let mut calls = Vec::new();
+            let mut pending = vec![local.entry_block()];
+            while let Some(seq) = pending.pop() {
+                for (instr, _) in &local.block(seq).instrs {
+                    match instr {
+                        Instr::Call(Call { func }) => calls.push(*func),
+                        Instr::Block(b) => pending.push(b.seq),
+                        Instr::IfElse(i) => {
+                            pending.push(i.consequent);
+                            pending.push(i.alternative);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            calls
+        };
+        assert!(!calls(put).contains(&import));
+        let wrapper = calls(put)
+            .into_iter()
+            .filter(|&f| matches!(module.funcs.get(f).kind, walrus::FunctionKind::Local(_)))
+            .find(|&f| calls(f).contains(&import))
+            .expect("no wrapper calls the host function");
+        assert_eq!(
+            module
+                .types
+                .get(module.funcs.get(wrapper).ty())
+                .params()
+                .len(),
+            3
+        );
+    }
+}
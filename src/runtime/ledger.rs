@@ -0,0 +1,132 @@
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::testutils::{Ledger as _, LedgerInfo};
+use soroban_sdk::{Bytes, Env};
+use std::ops::RangeInclusive;
+
+/// Ledger context applied to the environment before contracts are registered.
+/// Unset fields keep the test environment defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LedgerConfig {
+    pub sequence_number: Option<u32>,
+    pub timestamp: Option<u64>,
+    pub protocol_version: Option<u32>,
+    pub network_passphrase: Option<String>,
+    pub base_reserve: Option<u32>,
+    pub min_persistent_entry_ttl: Option<u32>,
+    pub min_temp_entry_ttl: Option<u32>,
+    pub max_entry_ttl: Option<u32>,
+}
+
+impl LedgerConfig {
+    /// Fill unset fields from another config
+    pub fn merge(&mut self, other: &LedgerConfig) {
+        self.sequence_number = self.sequence_number.or(other.sequence_number);
+        self.timestamp = self.timestamp.or(other.timestamp);
+        self.protocol_version = self.protocol_version.or(other.protocol_version);
+        if self.network_passphrase.is_none() {
+            self.network_passphrase = other.network_passphrase.clone();
+        }
+        self.base_reserve = self.base_reserve.or(other.base_reserve);
+        self.min_persistent_entry_ttl = self
+            .min_persistent_entry_ttl
+            .or(other.min_persistent_entry_ttl);
+        self.min_temp_entry_ttl = self.min_temp_entry_ttl.or(other.min_temp_entry_ttl);
+        self.max_entry_ttl = self.max_entry_ttl.or(other.max_entry_ttl);
+    }
+
+    /// Apply the configured values to the environment's ledger
+    pub fn apply(&self, env: &Env) -> Result<()> {
+        let mut info = env.ledger().get();
+
+        if let Some(version) = self.protocol_version {
+            let supported = supported_protocols();
+            if !supported.contains(&version) {
+                return Err(DebuggerError::ConfigError(format!(
+                    "Protocol version {} is not supported (this host runs protocols {} to {})",
+                    version,
+                    supported.start(),
+                    supported.end()
+                ))
+                .into());
+            }
+            info.protocol_version = version;
+        }
+        if let Some(passphrase) = &self.network_passphrase {
+            info.network_id = network_id(env, passphrase);
+        }
+        if let Some(sequence) = self.sequence_number {
+            info.sequence_number = sequence;
+        }
+        if let Some(timestamp) = self.timestamp {
+            info.timestamp = timestamp;
+        }
+        if let Some(reserve) = self.base_reserve {
+            info.base_reserve = reserve;
+        }
+        if let Some(ttl) = self.min_persistent_entry_ttl {
+            info.min_persistent_entry_ttl = ttl;
+        }
+        if let Some(ttl) = self.min_temp_entry_ttl {
+            info.min_temp_entry_ttl = ttl;
+        }
+        if let Some(ttl) = self.max_entry_ttl {
+            info.max_entry_ttl = ttl;
+        }
+
+        validate_ttls(&info)?;
+        env.ledger().set(info);
+        Ok(())
+    }
+}
+
+/// Oldest ledger protocol the host runs contracts on
+const MIN_PROTOCOL_VERSION: u32 = 22;
+
+/// Ledger protocol versions the host can run, up to the one it implements
+pub(crate) fn supported_protocols() -> RangeInclusive<u32> {
+    MIN_PROTOCOL_VERSION..=soroban_env_common::VERSION.interface.protocol
+}
+
+/// Network ID is the SHA-256 hash of the network passphrase
+fn network_id(env: &Env, passphrase: &str) -> [u8; 32] {
+    env.crypto()
+        .sha256(&Bytes::from_slice(env, passphrase.as_bytes()))
+        .to_array()
+}
+
+fn validate_ttls(info: &LedgerInfo) -> Result<()> {
+    let min_ttl = info.min_persistent_entry_ttl.max(info.min_temp_entry_ttl);
+    if min_ttl > info.max_entry_ttl {
+        return Err(DebuggerError::ConfigError(format!(
+            "Minimum entry TTL {} exceeds maximum entry TTL {}",
+            min_ttl, info.max_entry_ttl
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_protocol_version() {
+        let env = Env::default();
+        let latest = *supported_protocols().end();
+        let config = LedgerConfig {
+            protocol_version: Some(latest),
+            ..LedgerConfig::default()
+        };
+        config.apply(&env).unwrap();
+        assert_eq!(env.ledger().get().protocol_version, latest);
+
+        let config = LedgerConfig {
+            protocol_version: Some(latest + 1),
+            ..LedgerConfig::default()
+        };
+        assert!(config.apply(&env).is_err());
+    }
+}
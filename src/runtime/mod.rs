@@ -1,7 +1,25 @@
+pub mod assets;
+pub mod auth;
+pub mod contracts;
 pub mod env;
+pub mod events;
 pub mod executor;
+pub mod host_functions;
 pub mod instrumentation;
+pub mod ledger;
+pub mod probe;
+pub mod snapshot;
+pub mod storage;
+pub mod values;
 
+pub use assets::AssetConfig;
+pub use auth::{AuthConfig, AuthEntry, AuthInvocation};
+pub use contracts::{ContractRegistry, ContractUsage, RegisteredContract};
 pub use env::DebugEnv;
-pub use executor::ContractExecutor;
+pub use events::ContractEvent;
+pub use executor::{ContractExecutor, InvocationResult};
 pub use instrumentation::Instrumenter;
+pub use ledger::LedgerConfig;
+pub use snapshot::LedgerSnapshot;
+pub use storage::{Durability, StorageEntry};
+pub use values::ValueCodec;
@@ -0,0 +1,248 @@
+use crate::inspector::BudgetInfo;
+use crate::runtime::instrumentation::{Hook, MODE_RUN, MODE_STEP};
+use soroban_env_host::Host;
+use soroban_sdk::testutils::ContractFunctionSet;
+use soroban_sdk::xdr::{ScAddress, ScVal};
+use soroban_sdk::{Address, Bytes, BytesN, Env, IntoVal, TryFromVal, Val};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A hook call, with the contract it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookEvent {
+    pub contract: ScAddress,
+    pub hook: Hook,
+    /// Arguments of the host function about to be called, for
+    /// [`Hook::Host`]
+    pub args: Vec<ScVal>,
+}
+
+/// Sees every hook call while an invocation runs, and may hold it there
+pub trait HookHandler {
+    /// Called with each hook call once it is recorded. Blocking pauses the
+    /// invocation; returning `false` aborts it.
+    fn on_hook(&self, env: &Env, event: &HookEvent) -> bool;
+}
+
+/// What the probes of a session have seen, and what they tell hooks to do
+#[derive(Default)]
+pub(crate) struct ProbeState {
+    /// Hook calls of the current invocation, in order
+    pub timeline: Vec<HookEvent>,
+    /// Ask step hooks to report instructions, and host call wrappers the
+    /// calls they make
+    pub stepping: bool,
+    pub handler: Option<Rc<dyn HookHandler>>,
+    pub budget: HookBudget,
+}
+
+/// CPU cost of the `i64.const` and `call` each hook adds, which the VM
+/// charges when their block starts rather than with the hook call: 68 fuel
+/// at 4 instructions each
+const HOOK_CPU: u64 = 272;
+
+/// Budget an invocation used, telling what its hook calls cost from what the
+/// contract did, so the host holds the contract to the limits it would have
+/// without hooks.
+///
+/// The host meters a hook call like any call into another contract. Each one
+/// lifts the limits while the probe handles it, then sets them to what the
+/// contract has left. Setting the limits also resets the host's counts, so
+/// the budget counted before is kept here.
+#[derive(Default)]
+pub(crate) struct HookBudget {
+    /// Limits of the invocation, once it started
+    limits: Option<(u64, u64)>,
+    /// Budget counted by the host before it was last reset
+    counted: (u64, u64),
+    /// Budget used while the probe handled hook calls
+    handled: (u64, u64),
+    /// Hook calls made, by kind
+    calls: [u64; HOOK_KINDS],
+    /// Cheapest stretch seen in the session before a hook call of each
+    /// kind, which is what calling the hook and returning from it costs, as
+    /// hooks in the same block have no instructions charged between them.
+    /// Each kind gets to the probe its own way.
+    dispatch: [Option<(u64, u64)>; HOOK_KINDS],
+}
+
+impl HookBudget {
+    /// Hold an invocation starting now to limits
+    pub fn start(&mut self, limits: (u64, u64)) {
+        *self = Self {
+            limits: Some(limits),
+            dispatch: self.dispatch,
+            ..Self::default()
+        };
+    }
+
+    /// A hook call reached the probe
+    fn enter(&mut self, host: &Host, hook: Hook) {
+        let stretch = consumed(host);
+        let kind = hook_kind(hook);
+        // The stretch before the first hook call includes starting the call
+        if self.calls.iter().any(|&calls| calls > 0) {
+            self.dispatch[kind] = Some(match self.dispatch[kind] {
+                Some(cheapest) => (cheapest.0.min(stretch.0), cheapest.1.min(stretch.1)),
+                None => stretch,
+            });
+        }
+        self.counted = add(self.counted, stretch);
+        self.calls[kind] += 1;
+        let _ = host.budget_cloned().reset_unlimited();
+    }
+
+    /// The probe is returning from a hook call
+    fn leave(&mut self, host: &Host) {
+        let handled = consumed(host);
+        self.counted = add(self.counted, handled);
+        self.handled = add(self.handled, handled);
+        let Some((cpu_limit, mem_limit)) = self.limits else {
+            return;
+        };
+        let (cpu, mem) = self.used(host);
+        let _ = host
+            .budget_cloned()
+            .reset_limits(cpu_limit.saturating_sub(cpu), mem_limit.saturating_sub(mem));
+    }
+
+    /// CPU instructions and memory bytes the hook calls so far used,
+    /// estimated. Kinds not seen after another hook call yet are taken to
+    /// cost as little as the cheapest kind.
+    pub fn hooks(&self) -> (u64, u64) {
+        let cheapest = self.dispatch.iter().flatten().min().copied();
+        (0..HOOK_KINDS).fold(self.handled, |total, kind| {
+            let (cpu, mem) = self.dispatch[kind].or(cheapest).unwrap_or_default();
+            let calls = self.calls[kind];
+            add(
+                total,
+                (
+                    (cpu + HOOK_CPU).saturating_mul(calls),
+                    mem.saturating_mul(calls),
+                ),
+            )
+        })
+    }
+
+    /// CPU instructions and memory bytes the invocation used, without what
+    /// its hook calls did
+    pub fn used(&self, host: &Host) -> (u64, u64) {
+        let (cpu, mem) = add(self.counted, consumed(host));
+        let (hooks_cpu, hooks_mem) = self.hooks();
+        (cpu.saturating_sub(hooks_cpu), mem.saturating_sub(hooks_mem))
+    }
+}
+
+const HOOK_KINDS: usize = 6;
+
+fn hook_kind(hook: Hook) -> usize {
+    match hook {
+        Hook::Enter(_) => 0,
+        Hook::Exit(_) => 1,
+        Hook::Block(_) => 2,
+        Hook::Step(_) => 3,
+        Hook::Host(_) => 4,
+        Hook::Trap(_) => 5,
+    }
+}
+
+/// What the host counted since it last reset its budget
+fn consumed(host: &Host) -> (u64, u64) {
+    let budget = host.budget_cloned();
+    (
+        budget.get_cpu_insns_consumed().unwrap_or(0),
+        budget.get_mem_bytes_consumed().unwrap_or(0),
+    )
+}
+
+fn add(a: (u64, u64), b: (u64, u64)) -> (u64, u64) {
+    (a.0.saturating_add(b.0), a.1.saturating_add(b.1))
+}
+
+/// Reads the budget of the running or last invocation, leaving out what
+/// its hook calls used, against the limits it is held to
+#[derive(Clone)]
+pub(crate) struct BudgetMeter {
+    pub(crate) state: Rc<RefCell<ProbeState>>,
+    pub(crate) limits: (u64, u64),
+}
+
+impl BudgetMeter {
+    pub fn read(&self, host: &Host) -> BudgetInfo {
+        let (cpu_instructions, memory_bytes) = self.state.borrow().budget.used(host);
+        BudgetInfo {
+            cpu_instructions,
+            cpu_limit: self.limits.0,
+            memory_bytes,
+            memory_limit: self.limits.1,
+        }
+    }
+}
+
+/// Native contract receiving the hook calls of one instrumented contract.
+///
+/// Every instrumented contract gets its own probe, since hooks can only find
+/// the probe's address from their contract's address.
+pub(crate) struct Probe {
+    contract: ScAddress,
+    state: Rc<RefCell<ProbeState>>,
+}
+
+impl Probe {
+    pub fn new(contract: ScAddress, state: Rc<RefCell<ProbeState>>) -> Self {
+        Self { contract, state }
+    }
+}
+
+impl ContractFunctionSet for Probe {
+    fn call(&self, func: &str, env: Env, args: &[Val]) -> Option<Val> {
+        let hook = Hook::parse(func)?;
+        self.state.borrow_mut().budget.enter(env.host(), hook);
+        let mode = self.handle(hook, &env, args);
+        let result = mode.map(|mode| mode.into_val(&env));
+        self.state.borrow_mut().budget.leave(env.host());
+        result
+    }
+}
+
+impl Probe {
+    /// Record a hook call and have the handler see it, giving the mode to
+    /// run in, or nothing to abort the invocation
+    fn handle(&self, hook: Hook, env: &Env, args: &[Val]) -> Option<u32> {
+        let args = args
+            .iter()
+            .map(|arg| ScVal::try_from_val(env, arg).unwrap_or(ScVal::Void))
+            .collect();
+        let event = HookEvent {
+            contract: self.contract.clone(),
+            hook,
+            args,
+        };
+        let handler = {
+            let mut state = self.state.borrow_mut();
+            state.timeline.push(event.clone());
+            state.handler.clone()
+        };
+        // Not holding the state, which handlers may inspect while paused
+        if let Some(handler) = handler {
+            if !handler.on_hook(env, &event) {
+                return None;
+            }
+        }
+        let mode = if self.state.borrow().stepping {
+            MODE_STEP
+        } else {
+            MODE_RUN
+        };
+        Some(mode)
+    }
+}
+
+/// Address of the probe for a contract: where the contract would deploy a
+/// contract with the SHA-256 of nothing as salt
+pub(crate) fn probe_address(env: &Env, contract: &Address) -> Address {
+    let salt: BytesN<32> = env.crypto().sha256(&Bytes::new(env)).into();
+    env.deployer()
+        .with_address(contract.clone(), salt)
+        .deployed_address()
+}
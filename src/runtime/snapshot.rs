@@ -0,0 +1,174 @@
+use crate::runtime::ledger;
+use crate::utils::spec::ContractSpec;
+use crate::{DebuggerError, Result};
+use anyhow::Context;
+use soroban_env_host::budget::AsBudget;
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::token::StellarAssetSpec;
+use soroban_sdk::xdr::{
+    ContractDataDurability, ContractExecutable, Hash, LedgerEntryData, LedgerKey,
+    LedgerKeyContractData, ScAddress, ScVal,
+};
+use soroban_sdk::{Address, Env};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use tracing::warn;
+
+pub use soroban_ledger_snapshot::LedgerSnapshot;
+
+/// Read a `LedgerSnapshot` JSON file, as written by `stellar snapshot create`
+/// or the SDK test utilities
+pub fn load_snapshot(path: &Path) -> Result<LedgerSnapshot> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read snapshot file: {:?}", path))?;
+    let snapshot = serde_json::from_str(&json).map_err(|e| {
+        DebuggerError::StorageError(format!("Invalid ledger snapshot {:?}: {}", path, e))
+    })?;
+    Ok(snapshot)
+}
+
+/// Write a snapshot as pretty-printed JSON, loadable with `--snapshot` or
+/// `Env::from_ledger_snapshot_file`
+pub fn save_snapshot(snapshot: &LedgerSnapshot, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(snapshot).map_err(|e| {
+        DebuggerError::StorageError(format!("Failed to serialize ledger snapshot: {}", e))
+    })?;
+    fs::write(path, json).with_context(|| format!("Failed to write snapshot file: {:?}", path))?;
+    Ok(())
+}
+
+/// Create an environment whose ledger and storage start from a snapshot
+pub(crate) fn env_from_snapshot(mut snapshot: LedgerSnapshot) -> Env {
+    let supported = ledger::supported_protocols();
+    if !supported.contains(&snapshot.protocol_version) {
+        warn!(
+            "Snapshot was taken at protocol {}; running it on protocol {}",
+            snapshot.protocol_version,
+            supported.end()
+        );
+        snapshot.protocol_version = *supported.end();
+    }
+    Env::from_ledger_snapshot(snapshot)
+}
+
+/// An address no contract in the environment's storage is deployed at.
+///
+/// The test environment derives contract addresses from a counter that
+/// restarts with every session, so addresses it hands out may already be
+/// taken by contracts from a snapshot written by another session.
+pub(crate) fn unused_contract_address(env: &Env) -> Result<Address> {
+    loop {
+        let address = Address::generate(env);
+        let key = Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::from(&address),
+            key: ScVal::LedgerKeyContractInstance,
+            durability: ContractDataDurability::Persistent,
+        }));
+        let taken = env
+            .host()
+            .with_mut_storage(|storage| storage.has(&key, env.host().as_budget()))
+            .map_err(|e| DebuggerError::StorageError(format!("{:?}", e)))?;
+        if !taken {
+            return Ok(address);
+        }
+    }
+}
+
+/// A contract instance found in a snapshot
+#[derive(Debug, Clone)]
+pub(crate) struct SnapshotContract {
+    pub address: ScAddress,
+    pub spec: ContractSpec,
+}
+
+/// Find the contract instances in a snapshot, with the spec of their code
+pub(crate) fn snapshot_contracts(snapshot: &LedgerSnapshot) -> Result<Vec<SnapshotContract>> {
+    let code: HashMap<&Hash, &[u8]> = snapshot
+        .ledger_entries
+        .iter()
+        .filter_map(|(_, (entry, _))| match &entry.data {
+            LedgerEntryData::ContractCode(code) => Some((&code.hash, code.code.as_slice())),
+            _ => None,
+        })
+        .collect();
+
+    let mut contracts = Vec::new();
+    for (key, (entry, _)) in &snapshot.ledger_entries {
+        let LedgerKey::ContractData(key) = key.as_ref() else {
+            continue;
+        };
+        if key.key != ScVal::LedgerKeyContractInstance
+            || key.durability != ContractDataDurability::Persistent
+        {
+            continue;
+        }
+        let LedgerEntryData::ContractData(data) = &entry.data else {
+            continue;
+        };
+        let ScVal::ContractInstance(instance) = &data.val else {
+            continue;
+        };
+        let spec = match &instance.executable {
+            ContractExecutable::Wasm(hash) => match code.get(hash) {
+                Some(wasm) => ContractSpec::from_wasm(wasm)?,
+                None => {
+                    warn!(
+                        "Snapshot has no code for contract {}; argument types will be inferred",
+                        key.contract
+                    );
+                    ContractSpec::default()
+                }
+            },
+            ContractExecutable::StellarAsset => {
+                ContractSpec::from_xdr(&StellarAssetSpec::spec_xdr())?
+            }
+        };
+        contracts.push(SnapshotContract {
+            address: key.contract.clone(),
+            spec,
+        });
+    }
+    Ok(contracts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::assets::{deploy_asset_contract, issued_asset, named_account};
+
+    #[test]
+    fn test_snapshot_contracts() {
+        let env = Env::default();
+        let issuer = named_account(&env, "issuer");
+        let token = deploy_asset_contract(&env, issued_asset("USDC", issuer)).unwrap();
+
+        let mut snapshot = env.to_ledger_snapshot();
+        snapshot.protocol_version += 1;
+        let contracts = snapshot_contracts(&snapshot).unwrap();
+        assert_eq!(contracts.len(), 1);
+        assert_eq!(contracts[0].address, ScAddress::from(&token));
+        assert!(contracts[0].spec.function("transfer").is_some());
+
+        let restored = env_from_snapshot(snapshot);
+        assert_eq!(
+            restored.ledger().protocol_version(),
+            env.ledger().protocol_version()
+        );
+        assert_ne!(unused_contract_address(&restored).unwrap(), token);
+    }
+
+    #[test]
+    fn test_save_and_load_snapshot() {
+        let env = Env::default();
+        let issuer = named_account(&env, "issuer");
+        deploy_asset_contract(&env, issued_asset("USDC", issuer)).unwrap();
+        let snapshot = env.to_ledger_snapshot();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        save_snapshot(&snapshot, &path).unwrap();
+        assert_eq!(load_snapshot(&path).unwrap(), snapshot);
+    }
+}
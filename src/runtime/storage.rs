@@ -0,0 +1,320 @@
+use crate::runtime::host_functions::HostFunction;
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use soroban_env_host::budget::Budget;
+use soroban_env_host::storage::StorageMap;
+use soroban_env_host::Host;
+use soroban_sdk::xdr::{
+    ContractDataDurability, LedgerEntryData, LedgerKey, LedgerKeyContractData, ScAddress, ScVal,
+};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Storage durability class of a contract data entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    Instance,
+    #[default]
+    Persistent,
+    Temporary,
+}
+
+impl fmt::Display for Durability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Durability::Instance => write!(f, "instance"),
+            Durability::Persistent => write!(f, "persistent"),
+            Durability::Temporary => write!(f, "temporary"),
+        }
+    }
+}
+
+impl FromStr for Durability {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "instance" => Ok(Durability::Instance),
+            "persistent" => Ok(Durability::Persistent),
+            "temporary" => Ok(Durability::Temporary),
+            other => Err(DebuggerError::StorageError(format!(
+                "Unknown durability '{}' (expected instance, persistent or temporary)",
+                other
+            ))),
+        }
+    }
+}
+
+/// How a contract accesses one of its storage entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Checked,
+    Read,
+    Written,
+    Removed,
+    Extended,
+}
+
+impl AccessKind {
+    /// Whether the access may change the stored value
+    pub fn changes_value(self) -> bool {
+        matches!(self, AccessKind::Written | AccessKind::Removed)
+    }
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AccessKind::Checked => "checked",
+            AccessKind::Read => "read",
+            AccessKind::Written => "written",
+            AccessKind::Removed => "removed",
+            AccessKind::Extended => "extended",
+        })
+    }
+}
+
+/// A storage access a contract makes through a host function call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageAccess {
+    pub kind: AccessKind,
+    pub durability: Durability,
+    pub key: ScVal,
+    /// Value being written
+    pub value: Option<ScVal>,
+}
+
+impl StorageAccess {
+    /// The access a host function call makes, given its arguments, if it
+    /// accesses a storage entry
+    pub fn of_host_call(host: &HostFunction, args: &[ScVal]) -> Option<Self> {
+        let kind = match host.name {
+            "has_contract_data" => AccessKind::Checked,
+            "get_contract_data" => AccessKind::Read,
+            "put_contract_data" => AccessKind::Written,
+            "del_contract_data" => AccessKind::Removed,
+            "extend_contract_data_ttl" => AccessKind::Extended,
+            _ => return None,
+        };
+        let storage_type = host
+            .params
+            .iter()
+            .position(|&(_, ty)| ty == "StorageType")?;
+        let durability = match args.get(storage_type)? {
+            ScVal::U32(0) => Durability::Temporary,
+            ScVal::U32(1) => Durability::Persistent,
+            ScVal::U32(2) => Durability::Instance,
+            _ => return None,
+        };
+        let value = match kind {
+            AccessKind::Written => Some(args.get(1)?.clone()),
+            _ => None,
+        };
+        Some(Self {
+            kind,
+            durability,
+            key: args.first()?.clone(),
+            value,
+        })
+    }
+}
+
+/// A single storage entry to seed before invocation.
+///
+/// `key_type` and `value_type` are Rust-style type names (`i128`,
+/// `Vec<Address>`, `DataKey`). When omitted the type is inferred, and keys
+/// naming a contract enum variant are encoded as that variant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageEntry {
+    /// Alias or address of the owning contract; defaults to the invoked one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    pub key: Json,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<String>,
+    pub value: Json,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<String>,
+    #[serde(default)]
+    pub durability: Durability,
+    /// Minimum time-to-live in ledgers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StorageFile {
+    List(Vec<StorageEntry>),
+    Wrapped { entries: Vec<StorageEntry> },
+}
+
+/// Parse the `--storage` JSON: either an array of entries or `{"entries": [...]}`
+pub fn parse_storage_entries(json: &str) -> Result<Vec<StorageEntry>> {
+    let file: StorageFile = serde_json::from_str(json)
+        .map_err(|e| DebuggerError::StorageError(format!("Invalid storage JSON: {}", e)))?;
+
+    Ok(match file {
+        StorageFile::List(entries) | StorageFile::Wrapped { entries } => entries,
+    })
+}
+
+/// Value a contract stores under a key, as the ledger has it. Instance
+/// storage is read from the contract's instance entry, which the host only
+/// updates when the contract's invocation frame ends. Reading is not charged
+/// to the host's budget.
+pub fn read_entry(
+    host: &Host,
+    contract: &ScAddress,
+    durability: Durability,
+    key: &ScVal,
+) -> Result<Option<ScVal>> {
+    let (ledger_key, ledger_durability) = match durability {
+        Durability::Instance => (
+            ScVal::LedgerKeyContractInstance,
+            ContractDataDurability::Persistent,
+        ),
+        Durability::Persistent => (key.clone(), ContractDataDurability::Persistent),
+        Durability::Temporary => (key.clone(), ContractDataDurability::Temporary),
+    };
+    let ledger_key = Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+        contract: contract.clone(),
+        key: ledger_key,
+        durability: ledger_durability,
+    }));
+    let error = |e| DebuggerError::StorageError(format!("Failed to read storage: {:?}", e));
+    let budget = Budget::default();
+    budget.reset_unlimited().map_err(error)?;
+    let entry = host
+        .with_mut_storage(|storage| {
+            if storage.has(&ledger_key, &budget)? {
+                storage.get(&ledger_key, &budget).map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+        .map_err(error)?;
+
+    let Some(entry) = entry else {
+        return Ok(None);
+    };
+    let LedgerEntryData::ContractData(data) = &entry.data else {
+        return Ok(None);
+    };
+    Ok(match (durability, &data.val) {
+        (Durability::Instance, ScVal::ContractInstance(instance)) => instance
+            .storage
+            .as_ref()
+            .and_then(|map| map.iter().find(|e| e.key == *key))
+            .map(|e| e.val.clone()),
+        (Durability::Instance, _) => None,
+        (_, value) => Some(value.clone()),
+    })
+}
+
+/// Every entry a contract stores, by durability, key and value. Reading
+/// them is not charged to the host's budget.
+pub fn read_all(host: &Host, contract: &ScAddress) -> Result<Vec<(Durability, ScVal, ScVal)>> {
+    contract_entries(&snapshot(host)?, contract)
+}
+
+/// The host's storage as it is now, to read from later
+pub fn snapshot(host: &Host) -> Result<StorageMap> {
+    let map = host
+        .with_mut_storage(|storage| Ok(storage.map.clone()))
+        .map_err(|e| DebuggerError::StorageError(format!("Failed to read storage: {:?}", e)))?;
+    Ok(map)
+}
+
+/// Every entry a contract stores in a snapshot, as [`read_all`] gives them
+pub fn contract_entries(
+    map: &StorageMap,
+    contract: &ScAddress,
+) -> Result<Vec<(Durability, ScVal, ScVal)>> {
+    let error = |e| DebuggerError::StorageError(format!("Failed to read storage: {:?}", e));
+    let budget = Budget::default();
+    budget.reset_unlimited().map_err(error)?;
+
+    let mut entries = Vec::new();
+    for (key, entry) in map.iter(&budget).map_err(error)? {
+        let (LedgerKey::ContractData(key), Some((entry, _))) = (key.as_ref(), entry) else {
+            continue;
+        };
+        let LedgerEntryData::ContractData(data) = &entry.data else {
+            continue;
+        };
+        if key.contract != *contract {
+            continue;
+        }
+        match (&data.val, key.durability) {
+            (ScVal::ContractInstance(instance), _) => {
+                let storage = instance.storage.iter().flat_map(|map| map.iter());
+                entries
+                    .extend(storage.map(|e| (Durability::Instance, e.key.clone(), e.val.clone())));
+            }
+            (value, ContractDataDurability::Persistent) => {
+                entries.push((Durability::Persistent, key.key.clone(), value.clone()))
+            }
+            (value, ContractDataDurability::Temporary) => {
+                entries.push((Durability::Temporary, key.key.clone(), value.clone()))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::host_functions::HOST_FUNCTIONS;
+
+    #[test]
+    fn test_parse_storage_entries() {
+        let entries = parse_storage_entries(
+            r#"[
+                {"key": {"Balance": "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF"},
+                 "value": 100, "value_type": "i128", "ttl": 500},
+                {"key": "Counter", "value": 1, "value_type": "u32", "durability": "instance"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].durability, Durability::Persistent);
+        assert_eq!(entries[0].ttl, Some(500));
+        assert_eq!(entries[1].durability, Durability::Instance);
+
+        let wrapped = parse_storage_entries(r#"{"entries": []}"#).unwrap();
+        assert!(wrapped.is_empty());
+        assert!(
+            parse_storage_entries(r#"[{"key": 1, "value": 2, "durability": "forever"}]"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_storage_access_of_host_call() {
+        let host = |name| HOST_FUNCTIONS.iter().find(|f| f.name == name).unwrap();
+        let key = ScVal::Symbol("Counter".try_into().unwrap());
+        let put = StorageAccess::of_host_call(
+            host("put_contract_data"),
+            &[key.clone(), ScVal::U32(7), ScVal::U32(2)],
+        )
+        .unwrap();
+        assert_eq!(put.kind, AccessKind::Written);
+        assert_eq!(put.durability, Durability::Instance);
+        assert_eq!(put.value, Some(ScVal::U32(7)));
+
+        let del =
+            StorageAccess::of_host_call(host("del_contract_data"), &[key.clone(), ScVal::U32(0)])
+                .unwrap();
+        assert_eq!(
+            (del.kind, del.durability),
+            (AccessKind::Removed, Durability::Temporary)
+        );
+        assert_eq!(del.key, key);
+        assert!(StorageAccess::of_host_call(host("require_auth"), &[key]).is_none());
+    }
+}
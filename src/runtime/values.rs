@@ -0,0 +1,1220 @@
+use crate::utils::spec::{parse_type, type_name, ContractSpec, UdtDef};
+use crate::{DebuggerError, Result};
+use serde_json::Value as Json;
+use soroban_sdk::xdr::{
+    Int128Parts, Int256Parts, ScAddress, ScBytes, ScError, ScMap, ScMapEntry, ScSpecEntry,
+    ScSpecFunctionV0, ScSpecTypeDef, ScSpecUdtUnionCaseV0, ScString, ScSymbol, ScVal, ScVec,
+    UInt128Parts, UInt256Parts,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Converts JSON values to `ScVal`s guided by the contract spec
+pub struct ValueCodec<'a> {
+    spec: &'a ContractSpec,
+    aliases: Option<&'a HashMap<String, ScAddress>>,
+}
+
+impl<'a> ValueCodec<'a> {
+    pub fn new(spec: &'a ContractSpec) -> Self {
+        Self {
+            spec,
+            aliases: None,
+        }
+    }
+
+    /// Accept these names wherever an `Address` is expected
+    pub fn with_aliases(mut self, aliases: &'a HashMap<String, ScAddress>) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
+    /// Encode a JSON argument list for a function.
+    ///
+    /// Arguments may be given as an array in declaration order or as an
+    /// object keyed by parameter name.
+    pub fn encode_args(&self, function: &ScSpecFunctionV0, args: &Json) -> Result<Vec<ScVal>> {
+        let inputs = function.inputs.as_slice();
+        let values: Vec<&Json> = match args {
+            Json::Array(items) => {
+                if items.len() != inputs.len() {
+                    return Err(DebuggerError::InvalidArguments(format!(
+                        "function '{}' expects {} argument(s), got {}",
+                        function.name.to_utf8_string_lossy(),
+                        inputs.len(),
+                        items.len()
+                    ))
+                    .into());
+                }
+                items.iter().collect()
+            }
+            Json::Object(map) => {
+                if let Some(unknown) = map.keys().find(|key| {
+                    !inputs
+                        .iter()
+                        .any(|input| input.name.to_utf8_string_lossy() == **key)
+                }) {
+                    return Err(DebuggerError::InvalidArguments(format!(
+                        "function '{}' has no parameter named '{}'",
+                        function.name.to_utf8_string_lossy(),
+                        unknown
+                    ))
+                    .into());
+                }
+                inputs
+                    .iter()
+                    .map(|input| {
+                        let name = input.name.to_utf8_string_lossy();
+                        map.get(&name).ok_or_else(|| {
+                            DebuggerError::InvalidArguments(format!(
+                                "missing argument '{}' of type {}",
+                                name,
+                                type_name(&input.type_)
+                            ))
+                        })
+                    })
+                    .collect::<std::result::Result<_, _>>()?
+            }
+            other => {
+                return Err(DebuggerError::InvalidArguments(format!(
+                    "arguments must be a JSON array or object, got {}",
+                    other
+                ))
+                .into())
+            }
+        };
+
+        inputs
+            .iter()
+            .zip(values)
+            .map(|(input, value)| {
+                self.to_scval(&input.type_, value).map_err(|e| {
+                    DebuggerError::InvalidArguments(format!(
+                        "parameter '{}': {}",
+                        input.name.to_utf8_string_lossy(),
+                        e
+                    ))
+                    .into()
+                })
+            })
+            .collect()
+    }
+
+    /// Encode a JSON argument array without type information
+    pub fn infer_args(&self, args: &Json) -> Result<Vec<ScVal>> {
+        match args {
+            Json::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    self.to_scval(&ScSpecTypeDef::Val, item).map_err(|e| {
+                        DebuggerError::InvalidArguments(format!("argument {}: {}", i, e)).into()
+                    })
+                })
+                .collect(),
+            other => Err(DebuggerError::InvalidArguments(format!(
+                "arguments must be a JSON array, got {}",
+                other
+            ))
+            .into()),
+        }
+    }
+
+    /// Convert a JSON value to an `ScVal` of the given spec type
+    pub fn to_scval(&self, ty: &ScSpecTypeDef, value: &Json) -> std::result::Result<ScVal, String> {
+        let mismatch = || format!("expected {}, got {}", type_name(ty), value);
+
+        match ty {
+            ScSpecTypeDef::Val => self.infer(value),
+            ScSpecTypeDef::Bool => value.as_bool().map(ScVal::Bool).ok_or_else(mismatch),
+            ScSpecTypeDef::Void => match value {
+                Json::Null => Ok(ScVal::Void),
+                _ => Err(mismatch()),
+            },
+            ScSpecTypeDef::Error => json_u128(value)
+                .and_then(|n| u32::try_from(n).ok())
+                .map(|code| ScVal::Error(ScError::Contract(code)))
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::U32 => json_u128(value)
+                .and_then(|n| u32::try_from(n).ok())
+                .map(ScVal::U32)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::I32 => json_i128(value)
+                .and_then(|n| i32::try_from(n).ok())
+                .map(ScVal::I32)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::U64 => json_u128(value)
+                .and_then(|n| u64::try_from(n).ok())
+                .map(ScVal::U64)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::I64 => json_i128(value)
+                .and_then(|n| i64::try_from(n).ok())
+                .map(ScVal::I64)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Timepoint => json_u128(value)
+                .and_then(|n| u64::try_from(n).ok())
+                .map(|n| ScVal::Timepoint(n.into()))
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Duration => json_u128(value)
+                .and_then(|n| u64::try_from(n).ok())
+                .map(|n| ScVal::Duration(n.into()))
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::U128 => json_u128(value)
+                .map(|n| {
+                    ScVal::U128(UInt128Parts {
+                        hi: (n >> 64) as u64,
+                        lo: n as u64,
+                    })
+                })
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::I128 => json_i128(value)
+                .map(|n| {
+                    ScVal::I128(Int128Parts {
+                        hi: (n >> 64) as i64,
+                        lo: n as u64,
+                    })
+                })
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::U256 => json_str_or_number(value)
+                .and_then(|s| parse_u256(&s))
+                .map(|[hi_hi, hi_lo, lo_hi, lo_lo]| {
+                    ScVal::U256(UInt256Parts {
+                        hi_hi,
+                        hi_lo,
+                        lo_hi,
+                        lo_lo,
+                    })
+                })
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::I256 => json_str_or_number(value)
+                .and_then(|s| parse_i256(&s))
+                .map(|[hi_hi, hi_lo, lo_hi, lo_lo]| {
+                    ScVal::I256(Int256Parts {
+                        hi_hi: hi_hi as i64,
+                        hi_lo,
+                        lo_hi,
+                        lo_lo,
+                    })
+                })
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Bytes => json_bytes(value)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(|bytes| ScVal::Bytes(ScBytes(bytes)))
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::BytesN(n) => {
+                let bytes = json_bytes(value).ok_or_else(mismatch)?;
+                if bytes.len() != n.n as usize {
+                    return Err(format!(
+                        "expected {} ({} bytes), got {} bytes",
+                        type_name(ty),
+                        n.n,
+                        bytes.len()
+                    ));
+                }
+                Ok(ScVal::Bytes(ScBytes(
+                    bytes.try_into().map_err(|_| mismatch())?,
+                )))
+            }
+            ScSpecTypeDef::String => value
+                .as_str()
+                .and_then(|s| s.as_bytes().to_vec().try_into().ok())
+                .map(|s| ScVal::String(ScString(s)))
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Symbol => value
+                .as_str()
+                .and_then(parse_symbol)
+                .map(ScVal::Symbol)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Address => value
+                .as_str()
+                .and_then(|s| self.parse_address(s))
+                .map(ScVal::Address)
+                .ok_or_else(mismatch),
+            ScSpecTypeDef::Option(o) => match value {
+                Json::Null => Ok(ScVal::Void),
+                inner => self.to_scval(&o.value_type, inner),
+            },
+            ScSpecTypeDef::Result(_) => Err(format!(
+                "{} values cannot be passed as arguments",
+                type_name(ty)
+            )),
+            ScSpecTypeDef::Vec(v) => {
+                let items = value.as_array().ok_or_else(mismatch)?;
+                let items = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        self.to_scval(&v.element_type, item)
+                            .map_err(|e| format!("element {}: {}", i, e))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                make_vec(items)
+            }
+            ScSpecTypeDef::Map(m) => {
+                let pairs: Vec<(Json, &Json)> = match value {
+                    Json::Object(map) => map
+                        .iter()
+                        .map(|(k, v)| (Json::String(k.clone()), v))
+                        .collect(),
+                    Json::Array(items) => items
+                        .iter()
+                        .map(|item| match item.as_array().map(Vec::as_slice) {
+                            Some([k, v]) => Ok((k.clone(), v)),
+                            _ => Err(format!("expected [key, value] pair, got {}", item)),
+                        })
+                        .collect::<std::result::Result<_, _>>()?,
+                    _ => return Err(mismatch()),
+                };
+                let entries = pairs
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = self
+                            .to_scval(&m.key_type, k)
+                            .map_err(|e| format!("map key {}: {}", k, e))?;
+                        let val = self
+                            .to_scval(&m.value_type, v)
+                            .map_err(|e| format!("map value for {}: {}", k, e))?;
+                        Ok(ScMapEntry { key, val })
+                    })
+                    .collect::<std::result::Result<Vec<_>, String>>()?;
+                make_map(entries)
+            }
+            ScSpecTypeDef::Tuple(t) => {
+                let items = value.as_array().ok_or_else(mismatch)?;
+                if items.len() != t.value_types.len() {
+                    return Err(mismatch());
+                }
+                let items = t
+                    .value_types
+                    .iter()
+                    .zip(items)
+                    .enumerate()
+                    .map(|(i, (ty, item))| {
+                        self.to_scval(ty, item)
+                            .map_err(|e| format!("tuple element {}: {}", i, e))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                make_vec(items)
+            }
+            ScSpecTypeDef::Udt(udt) => {
+                let name = udt.name.to_utf8_string_lossy();
+                match self.spec.udt(&name) {
+                    Some(def) => self.udt_to_scval(&name, def, value),
+                    None => Err(format!("type {} is not defined in the contract spec", name)),
+                }
+            }
+        }
+    }
+
+    fn udt_to_scval(
+        &self,
+        name: &str,
+        def: UdtDef<'_>,
+        value: &Json,
+    ) -> std::result::Result<ScVal, String> {
+        match def {
+            UdtDef::Struct(s) => {
+                let is_tuple_struct = s
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .all(|(i, f)| f.name.to_utf8_string_lossy() == i.to_string());
+
+                if is_tuple_struct && !s.fields.is_empty() {
+                    let items = value
+                        .as_array()
+                        .filter(|items| items.len() == s.fields.len())
+                        .ok_or_else(|| {
+                            format!("expected {} as a JSON array, got {}", name, value)
+                        })?;
+                    let items = s
+                        .fields
+                        .iter()
+                        .zip(items)
+                        .map(|(f, item)| {
+                            self.to_scval(&f.type_, item).map_err(|e| {
+                                format!("field '{}.{}': {}", name, f.name.to_utf8_string_lossy(), e)
+                            })
+                        })
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    return make_vec(items);
+                }
+
+                let object = value
+                    .as_object()
+                    .ok_or_else(|| format!("expected {} as a JSON object, got {}", name, value))?;
+                if let Some(unknown) = object.keys().find(|key| {
+                    !s.fields
+                        .iter()
+                        .any(|f| f.name.to_utf8_string_lossy() == **key)
+                }) {
+                    return Err(format!("{} has no field named '{}'", name, unknown));
+                }
+                let entries = s
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        let field = f.name.to_utf8_string_lossy();
+                        let item = object
+                            .get(&field)
+                            .ok_or_else(|| format!("missing field '{}.{}'", name, field))?;
+                        let val = self
+                            .to_scval(&f.type_, item)
+                            .map_err(|e| format!("field '{}.{}': {}", name, field, e))?;
+                        let key = parse_symbol(&field)
+                            .map(ScVal::Symbol)
+                            .ok_or_else(|| format!("invalid field name '{}'", field))?;
+                        Ok(ScMapEntry { key, val })
+                    })
+                    .collect::<std::result::Result<Vec<_>, String>>()?;
+                make_map(entries)
+            }
+            UdtDef::Union(u) => {
+                // Accept "Case", {"Case": value}, {"Case": [values]} or ["Case", values...]
+                let (case_name, payload): (&str, Vec<&Json>) = match value {
+                    Json::String(s) => (s.as_str(), vec![]),
+                    Json::Object(map) if map.len() == 1 => {
+                        let (k, v) = map.iter().next().unwrap();
+                        (k.as_str(), vec![v])
+                    }
+                    Json::Array(items) if !items.is_empty() => match items[0].as_str() {
+                        Some(s) => (s, items[1..].iter().collect()),
+                        None => return Err(format!("expected {} variant, got {}", name, value)),
+                    },
+                    _ => return Err(format!("expected {} variant, got {}", name, value)),
+                };
+
+                let case = u
+                    .cases
+                    .iter()
+                    .find(|c| union_case_name(c) == case_name)
+                    .ok_or_else(|| {
+                        let known: Vec<String> = u.cases.iter().map(union_case_name).collect();
+                        format!(
+                            "{} has no variant '{}' (expected one of: {})",
+                            name,
+                            case_name,
+                            known.join(", ")
+                        )
+                    })?;
+
+                let mut items =
+                    vec![ScVal::Symbol(parse_symbol(case_name).ok_or_else(|| {
+                        format!("invalid variant name '{}'", case_name)
+                    })?)];
+                match case {
+                    ScSpecUdtUnionCaseV0::VoidV0(_) => {
+                        if payload.iter().any(|p| !p.is_null()) {
+                            return Err(format!("variant {}::{} takes no value", name, case_name));
+                        }
+                    }
+                    ScSpecUdtUnionCaseV0::TupleV0(t) => {
+                        // A single-element payload may be given bare or wrapped in an array
+                        let payload: Vec<&Json> = match payload.as_slice() {
+                            [Json::Array(inner)] if t.type_.len() != 1 => inner.iter().collect(),
+                            _ => payload,
+                        };
+                        if payload.len() != t.type_.len() {
+                            return Err(format!(
+                                "variant {}::{} expects {} value(s), got {}",
+                                name,
+                                case_name,
+                                t.type_.len(),
+                                payload.len()
+                            ));
+                        }
+                        for (ty, item) in t.type_.iter().zip(payload) {
+                            items.push(
+                                self.to_scval(ty, item).map_err(|e| {
+                                    format!("variant {}::{}: {}", name, case_name, e)
+                                })?,
+                            );
+                        }
+                    }
+                }
+                make_vec(items)
+            }
+            UdtDef::Enum(e) => {
+                let known = || {
+                    e.cases
+                        .iter()
+                        .map(|c| c.name.to_utf8_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let case = match value {
+                    Json::String(s) if json_u128(value).is_none() => {
+                        e.cases.iter().find(|c| c.name.to_utf8_string_lossy() == *s)
+                    }
+                    _ => {
+                        json_u128(value).and_then(|n| e.cases.iter().find(|c| c.value as u128 == n))
+                    }
+                };
+                case.map(|c| ScVal::U32(c.value)).ok_or_else(|| {
+                    format!(
+                        "expected {} variant (one of: {}), got {}",
+                        name,
+                        known(),
+                        value
+                    )
+                })
+            }
+            UdtDef::ErrorEnum(e) => {
+                let case = match value {
+                    Json::String(s) if json_u128(value).is_none() => {
+                        e.cases.iter().find(|c| c.name.to_utf8_string_lossy() == *s)
+                    }
+                    _ => {
+                        json_u128(value).and_then(|n| e.cases.iter().find(|c| c.value as u128 == n))
+                    }
+                };
+                case.map(|c| ScVal::Error(ScError::Contract(c.value)))
+                    .ok_or_else(|| format!("expected {} error code, got {}", name, value))
+            }
+        }
+    }
+
+    /// Convert a JSON value annotated with an optional Rust-style type name
+    pub fn encode_typed(
+        &self,
+        type_name: Option<&str>,
+        value: &Json,
+    ) -> std::result::Result<ScVal, String> {
+        match type_name {
+            Some(name) => {
+                let ty = parse_type(name).ok_or_else(|| format!("unknown type '{}'", name))?;
+                self.to_scval(&ty, value)
+            }
+            None => self.to_scval_untyped(value),
+        }
+    }
+
+    /// Convert a JSON value whose type was not declared.
+    ///
+    /// Strings and single-key objects naming a variant of a contract enum or
+    /// union are encoded as that variant, so storage keys such as
+    /// `{"Balance": "G..."}` need no type annotation.
+    pub fn to_scval_untyped(&self, value: &Json) -> std::result::Result<ScVal, String> {
+        let case_name = match value {
+            Json::String(s) => Some(s.as_str()),
+            Json::Object(map) if map.len() == 1 => map.keys().next().map(String::as_str),
+            _ => None,
+        };
+
+        if let Some(case_name) = case_name {
+            let owners: Vec<String> = self
+                .spec
+                .entries()
+                .iter()
+                .filter_map(|entry| match entry {
+                    ScSpecEntry::UdtUnionV0(u)
+                        if u.cases.iter().any(|c| union_case_name(c) == case_name) =>
+                    {
+                        Some(u.name.to_utf8_string_lossy())
+                    }
+                    ScSpecEntry::UdtEnumV0(e)
+                        if value.is_string()
+                            && e.cases
+                                .iter()
+                                .any(|c| c.name.to_utf8_string_lossy() == case_name) =>
+                    {
+                        Some(e.name.to_utf8_string_lossy())
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            match owners.as_slice() {
+                [owner] => {
+                    let def = self.spec.udt(owner).expect("owner comes from the spec");
+                    return self.udt_to_scval(owner, def, value);
+                }
+                [] => {}
+                _ => {
+                    return Err(format!(
+                        "variant '{}' is ambiguous between {}; specify a type",
+                        case_name,
+                        owners.join(", ")
+                    ))
+                }
+            }
+        }
+
+        self.infer(value)
+    }
+
+    /// Parse a strkey or a session alias
+    fn parse_address(&self, s: &str) -> Option<ScAddress> {
+        self.aliases
+            .and_then(|aliases| aliases.get(s).cloned())
+            .or_else(|| ScAddress::from_str(s).ok())
+    }
+
+    /// Best-effort conversion for untyped (`Val`) positions
+    fn infer(&self, value: &Json) -> std::result::Result<ScVal, String> {
+        match value {
+            Json::Null => Ok(ScVal::Void),
+            Json::Bool(b) => Ok(ScVal::Bool(*b)),
+            Json::Number(_) => self.to_scval(&ScSpecTypeDef::I128, value),
+            Json::String(s) => {
+                if let Some(address) = self.parse_address(s) {
+                    Ok(ScVal::Address(address))
+                } else if let Some(symbol) = parse_symbol(s) {
+                    Ok(ScVal::Symbol(symbol))
+                } else {
+                    self.to_scval(&ScSpecTypeDef::String, value)
+                }
+            }
+            Json::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| self.infer(item))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                make_vec(items)
+            }
+            Json::Object(map) => {
+                let entries = map
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = parse_symbol(k)
+                            .map(ScVal::Symbol)
+                            .ok_or_else(|| format!("invalid map key '{}'", k))?;
+                        Ok(ScMapEntry {
+                            key,
+                            val: self.infer(v)?,
+                        })
+                    })
+                    .collect::<std::result::Result<Vec<_>, String>>()?;
+                make_map(entries)
+            }
+        }
+    }
+
+    /// Render an `ScVal` as JSON, using the spec type to name struct fields
+    /// and enum variants. Values that do not match the type are rendered
+    /// without type information.
+    pub fn to_json(&self, ty: &ScSpecTypeDef, value: &ScVal) -> Json {
+        match (ty, value) {
+            (ScSpecTypeDef::Option(o), ScVal::Void)
+                if !matches!(*o.value_type, ScSpecTypeDef::Void) =>
+            {
+                Json::Null
+            }
+            (ScSpecTypeDef::Option(o), v) => self.to_json(&o.value_type, v),
+            (ScSpecTypeDef::Result(r), ScVal::Error(_)) => self.to_json(&r.error_type, value),
+            (ScSpecTypeDef::Result(r), v) => self.to_json(&r.ok_type, v),
+            (ScSpecTypeDef::Vec(v), ScVal::Vec(Some(items))) => Json::Array(
+                items
+                    .iter()
+                    .map(|item| self.to_json(&v.element_type, item))
+                    .collect(),
+            ),
+            (ScSpecTypeDef::Map(m), ScVal::Map(Some(entries))) => {
+                self.map_to_json(entries.iter().map(|e| {
+                    (
+                        self.to_json(&m.key_type, &e.key),
+                        self.to_json(&m.value_type, &e.val),
+                    )
+                }))
+            }
+            (ScSpecTypeDef::Tuple(t), ScVal::Vec(Some(items)))
+                if t.value_types.len() == items.len() =>
+            {
+                Json::Array(
+                    t.value_types
+                        .iter()
+                        .zip(items.iter())
+                        .map(|(ty, item)| self.to_json(ty, item))
+                        .collect(),
+                )
+            }
+            (ScSpecTypeDef::Udt(udt), v) => {
+                let name = udt.name.to_utf8_string_lossy();
+                self.spec
+                    .udt(&name)
+                    .and_then(|def| self.udt_to_json(def, v))
+                    .unwrap_or_else(|| self.to_json_untyped(v))
+            }
+            (_, v) => self.to_json_untyped(v),
+        }
+    }
+
+    fn udt_to_json(&self, def: UdtDef<'_>, value: &ScVal) -> Option<Json> {
+        match (def, value) {
+            (UdtDef::Struct(s), ScVal::Map(Some(entries))) => {
+                let mut object = serde_json::Map::new();
+                for field in s.fields.iter() {
+                    let name = field.name.to_utf8_string_lossy();
+                    let entry = entries.iter().find(|e| match &e.key {
+                        ScVal::Symbol(sym) => sym.to_utf8_string_lossy() == name,
+                        _ => false,
+                    })?;
+                    object.insert(name, self.to_json(&field.type_, &entry.val));
+                }
+                Some(Json::Object(object))
+            }
+            (UdtDef::Struct(s), ScVal::Vec(Some(items))) if s.fields.len() == items.len() => {
+                Some(Json::Array(
+                    s.fields
+                        .iter()
+                        .zip(items.iter())
+                        .map(|(f, item)| self.to_json(&f.type_, item))
+                        .collect(),
+                ))
+            }
+            (UdtDef::Union(u), ScVal::Vec(Some(items))) => {
+                let (ScVal::Symbol(tag), payload) = items.split_first()? else {
+                    return None;
+                };
+                let tag = tag.to_utf8_string_lossy();
+                let case = u.cases.iter().find(|c| union_case_name(c) == tag)?;
+                match case {
+                    ScSpecUdtUnionCaseV0::VoidV0(_) if payload.is_empty() => {
+                        Some(Json::String(tag))
+                    }
+                    ScSpecUdtUnionCaseV0::TupleV0(t) if t.type_.len() == payload.len() => {
+                        let mut values: Vec<Json> = t
+                            .type_
+                            .iter()
+                            .zip(payload)
+                            .map(|(ty, item)| self.to_json(ty, item))
+                            .collect();
+                        let inner = if values.len() == 1 {
+                            values.remove(0)
+                        } else {
+                            Json::Array(values)
+                        };
+                        let mut object = serde_json::Map::new();
+                        object.insert(tag, inner);
+                        Some(Json::Object(object))
+                    }
+                    _ => None,
+                }
+            }
+            (UdtDef::Enum(e), ScVal::U32(n)) => e
+                .cases
+                .iter()
+                .find(|c| c.value == *n)
+                .map(|c| Json::String(c.name.to_utf8_string_lossy())),
+            (UdtDef::ErrorEnum(e), ScVal::Error(ScError::Contract(n))) => e
+                .cases
+                .iter()
+                .find(|c| c.value == *n)
+                .map(|c| Json::String(c.name.to_utf8_string_lossy())),
+            _ => None,
+        }
+    }
+
+    /// Render an `ScVal` as JSON without type information
+    pub fn to_json_untyped(&self, value: &ScVal) -> Json {
+        match value {
+            ScVal::Bool(b) => Json::Bool(*b),
+            ScVal::Void => Json::Null,
+            ScVal::Error(e) => Json::String(format_error(e)),
+            ScVal::U32(n) => Json::from(*n),
+            ScVal::I32(n) => Json::from(*n),
+            ScVal::U64(n) => Json::from(*n),
+            ScVal::I64(n) => Json::from(*n),
+            ScVal::Timepoint(t) => Json::from(t.0),
+            ScVal::Duration(d) => Json::from(d.0),
+            ScVal::U128(parts) => {
+                number_or_string(((parts.hi as u128) << 64 | parts.lo as u128).to_string())
+            }
+            ScVal::I128(parts) => {
+                number_or_string((((parts.hi as i128) << 64) | parts.lo as i128).to_string())
+            }
+            ScVal::U256(p) => number_or_string(format_u256([p.hi_hi, p.hi_lo, p.lo_hi, p.lo_lo])),
+            ScVal::I256(p) => {
+                let limbs = [p.hi_hi as u64, p.hi_lo, p.lo_hi, p.lo_lo];
+                if p.hi_hi < 0 {
+                    number_or_string(format!("-{}", format_u256(negate_u256(limbs))))
+                } else {
+                    number_or_string(format_u256(limbs))
+                }
+            }
+            ScVal::Bytes(b) => Json::String(encode_hex(b.as_slice())),
+            ScVal::String(s) => Json::String(s.to_utf8_string_lossy()),
+            ScVal::Symbol(s) => Json::String(s.to_utf8_string_lossy()),
+            ScVal::Vec(Some(items)) => Json::Array(
+                items
+                    .iter()
+                    .map(|item| self.to_json_untyped(item))
+                    .collect(),
+            ),
+            ScVal::Vec(None) => Json::Array(vec![]),
+            ScVal::Map(Some(entries)) => self.map_to_json(
+                entries
+                    .iter()
+                    .map(|e| (self.to_json_untyped(&e.key), self.to_json_untyped(&e.val))),
+            ),
+            ScVal::Map(None) => Json::Object(serde_json::Map::new()),
+            ScVal::Address(address) => Json::String(address.to_string()),
+            ScVal::LedgerKeyContractInstance => Json::String("LedgerKeyContractInstance".into()),
+            ScVal::LedgerKeyNonce(nonce) => Json::String(format!("Nonce({})", nonce.nonce)),
+            ScVal::ContractInstance(_) => Json::String("ContractInstance".into()),
+        }
+    }
+
+    /// Maps with string keys become objects, anything else a list of pairs
+    fn map_to_json(&self, entries: impl Iterator<Item = (Json, Json)>) -> Json {
+        let entries: Vec<(Json, Json)> = entries.collect();
+        if entries.iter().all(|(k, _)| k.is_string()) {
+            Json::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.as_str().unwrap_or_default().to_string(), v))
+                    .collect(),
+            )
+        } else {
+            Json::Array(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| Json::Array(vec![k, v]))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Render a host error the way the host prints it, e.g. `Error(Contract, #3)`
+pub(crate) fn format_error(error: &ScError) -> String {
+    match error {
+        ScError::Contract(code) => format!("Error(Contract, #{})", code),
+        other => {
+            let code = match other {
+                ScError::WasmVm(c)
+                | ScError::Context(c)
+                | ScError::Storage(c)
+                | ScError::Object(c)
+                | ScError::Crypto(c)
+                | ScError::Events(c)
+                | ScError::Budget(c)
+                | ScError::Value(c)
+                | ScError::Auth(c) => c.name(),
+                ScError::Contract(_) => unreachable!(),
+            };
+            format!("Error({}, {})", other.name(), code)
+        }
+    }
+}
+
+/// Small integers render as JSON numbers, wide ones as decimal strings
+fn number_or_string(decimal: String) -> Json {
+    decimal
+        .parse::<i64>()
+        .map(Json::from)
+        .or_else(|_| decimal.parse::<u64>().map(Json::from))
+        .unwrap_or(Json::String(decimal))
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Format big-endian 64-bit limbs as a decimal string
+fn format_u256(mut limbs: [u64; 4]) -> String {
+    if limbs == [0; 4] {
+        return "0".to_string();
+    }
+    let mut digits = Vec::new();
+    while limbs != [0; 4] {
+        let mut remainder = 0u128;
+        for limb in limbs.iter_mut() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / 10) as u64;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Two's complement negation of big-endian limbs
+fn negate_u256(limbs: [u64; 4]) -> [u64; 4] {
+    let mut limbs = limbs.map(|limb| !limb);
+    for limb in limbs.iter_mut().rev() {
+        let (sum, overflow) = limb.overflowing_add(1);
+        *limb = sum;
+        if !overflow {
+            break;
+        }
+    }
+    limbs
+}
+
+/// Name of a union case regardless of its shape
+pub(crate) fn union_case_name(case: &ScSpecUdtUnionCaseV0) -> String {
+    match case {
+        ScSpecUdtUnionCaseV0::VoidV0(v) => v.name.to_utf8_string_lossy(),
+        ScSpecUdtUnionCaseV0::TupleV0(t) => t.name.to_utf8_string_lossy(),
+    }
+}
+
+fn make_vec(items: Vec<ScVal>) -> std::result::Result<ScVal, String> {
+    let items = items
+        .try_into()
+        .map_err(|_| "too many elements".to_string())?;
+    Ok(ScVal::Vec(Some(ScVec(items))))
+}
+
+/// Build a map, sorting keys the way the host requires
+fn make_map(mut entries: Vec<ScMapEntry>) -> std::result::Result<ScVal, String> {
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    if entries.windows(2).any(|w| w[0].key == w[1].key) {
+        return Err("duplicate map key".to_string());
+    }
+    let entries = entries
+        .try_into()
+        .map_err(|_| "too many map entries".to_string())?;
+    Ok(ScVal::Map(Some(ScMap(entries))))
+}
+
+/// Parse a symbol, enforcing the host's character set
+fn parse_symbol(s: &str) -> Option<ScSymbol> {
+    if s.len() > 32 || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    ScSymbol::try_from(s).ok()
+}
+
+/// Integers may be given as JSON numbers or as decimal strings
+fn json_str_or_number(value: &Json) -> Option<String> {
+    match value {
+        Json::Number(n) if n.is_i64() || n.is_u64() => Some(n.to_string()),
+        Json::String(s) => Some(s.replace('_', "")),
+        _ => None,
+    }
+}
+
+/// 128-bit integers are given in decimal or `0x` hex, like 256-bit ones
+fn json_i128(value: &Json) -> Option<i128> {
+    let s = json_str_or_number(value)?;
+    match s.strip_prefix('-') {
+        Some(rest) => 0i128.checked_sub_unsigned(parse_u128(rest)?),
+        None => parse_u128(&s)?.try_into().ok(),
+    }
+}
+
+fn json_u128(value: &Json) -> Option<u128> {
+    parse_u128(&json_str_or_number(value)?)
+}
+
+fn parse_u128(s: &str) -> Option<u128> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Bytes are given as a hex string (optionally `0x`-prefixed) or an array of octets
+fn json_bytes(value: &Json) -> Option<Vec<u8>> {
+    match value {
+        Json::String(s) => decode_hex(s.strip_prefix("0x").unwrap_or(s)),
+        Json::Array(items) => items
+            .iter()
+            .map(|item| item.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect(),
+        _ => None,
+    }
+}
+
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parse an unsigned 256-bit integer into big-endian 64-bit limbs
+fn parse_u256(s: &str) -> Option<[u64; 4]> {
+    let (digits, radix) = match s.strip_prefix("0x") {
+        Some(hex) => (hex, 16u64),
+        None => (s, 10u64),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+
+    let mut limbs = [0u64; 4];
+    for c in digits.chars() {
+        let digit = c.to_digit(radix as u32)? as u64;
+        let mut carry = digit as u128;
+        for limb in limbs.iter_mut().rev() {
+            let product = (*limb as u128) * radix as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(limbs)
+}
+
+/// Parse a signed 256-bit integer into two's complement big-endian limbs
+fn parse_i256(s: &str) -> Option<[u64; 4]> {
+    let (negative, magnitude) = match s.strip_prefix('-') {
+        Some(rest) => (true, parse_u256(rest)?),
+        None => (false, parse_u256(s)?),
+    };
+
+    let top_bit_set = magnitude[0] >> 63 == 1;
+    if !negative {
+        return (!top_bit_set).then_some(magnitude);
+    }
+    // The only negative value with the top bit set is -2^255
+    if top_bit_set && magnitude != [1 << 63, 0, 0, 0] {
+        return None;
+    }
+
+    Some(negate_u256(magnitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use soroban_sdk::xdr::{
+        ScSpecFunctionInputV0, ScSpecFunctionV0, ScSpecTypeUdt, ScSpecTypeVec,
+        ScSpecUdtStructFieldV0, ScSpecUdtStructV0, ScSpecUdtUnionCaseTupleV0,
+        ScSpecUdtUnionCaseVoidV0, ScSpecUdtUnionV0,
+    };
+
+    fn udt(name: &str) -> ScSpecTypeDef {
+        ScSpecTypeDef::Udt(ScSpecTypeUdt {
+            name: name.try_into().unwrap(),
+        })
+    }
+
+    fn test_spec() -> ContractSpec {
+        ContractSpec::new(vec![
+            ScSpecEntry::UdtStructV0(ScSpecUdtStructV0 {
+                doc: "".try_into().unwrap(),
+                lib: "".try_into().unwrap(),
+                name: "Info".try_into().unwrap(),
+                fields: vec![
+                    ScSpecUdtStructFieldV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "name".try_into().unwrap(),
+                        type_: ScSpecTypeDef::String,
+                    },
+                    ScSpecUdtStructFieldV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "count".try_into().unwrap(),
+                        type_: ScSpecTypeDef::U32,
+                    },
+                ]
+                .try_into()
+                .unwrap(),
+            }),
+            ScSpecEntry::UdtUnionV0(ScSpecUdtUnionV0 {
+                doc: "".try_into().unwrap(),
+                lib: "".try_into().unwrap(),
+                name: "DataKey".try_into().unwrap(),
+                cases: vec![
+                    ScSpecUdtUnionCaseV0::VoidV0(ScSpecUdtUnionCaseVoidV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "Admin".try_into().unwrap(),
+                    }),
+                    ScSpecUdtUnionCaseV0::TupleV0(ScSpecUdtUnionCaseTupleV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "Balance".try_into().unwrap(),
+                        type_: vec![ScSpecTypeDef::Address].try_into().unwrap(),
+                    }),
+                ]
+                .try_into()
+                .unwrap(),
+            }),
+            ScSpecEntry::FunctionV0(ScSpecFunctionV0 {
+                doc: "".try_into().unwrap(),
+                name: "transfer".try_into().unwrap(),
+                inputs: vec![
+                    ScSpecFunctionInputV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "to".try_into().unwrap(),
+                        type_: ScSpecTypeDef::Address,
+                    },
+                    ScSpecFunctionInputV0 {
+                        doc: "".try_into().unwrap(),
+                        name: "amount".try_into().unwrap(),
+                        type_: ScSpecTypeDef::I128,
+                    },
+                ]
+                .try_into()
+                .unwrap(),
+                outputs: vec![].try_into().unwrap(),
+            }),
+        ])
+    }
+
+    const ACCOUNT: &str = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+
+    #[test]
+    fn test_encode_args_by_position_and_name() {
+        let spec = test_spec();
+        let codec = ValueCodec::new(&spec);
+        let function = spec.function("transfer").unwrap();
+
+        let positional = codec
+            .encode_args(function, &json!([ACCOUNT, "-5"]))
+            .unwrap();
+        let named = codec
+            .encode_args(function, &json!({"amount": -5, "to": ACCOUNT}))
+            .unwrap();
+        assert_eq!(positional, named);
+        assert_eq!(
+            positional[1],
+            ScVal::I128(Int128Parts {
+                hi: -1,
+                lo: u64::MAX - 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_address_aliases() {
+        let spec = test_spec();
+        let aliases = HashMap::from([("alice".to_string(), ScAddress::from_str(ACCOUNT).unwrap())]);
+        let function = spec.function("transfer").unwrap();
+
+        let by_alias = ValueCodec::new(&spec)
+            .with_aliases(&aliases)
+            .encode_args(function, &json!(["alice", 1]))
+            .unwrap();
+        let by_strkey = ValueCodec::new(&spec)
+            .encode_args(function, &json!([ACCOUNT, 1]))
+            .unwrap();
+        assert_eq!(by_alias, by_strkey);
+        assert!(ValueCodec::new(&spec)
+            .encode_args(function, &json!(["alice", 1]))
+            .is_err());
+
+        // Untyped positions resolve aliases too, rather than taking them as symbols
+        let inferred = ValueCodec::new(&spec)
+            .with_aliases(&aliases)
+            .infer_args(&json!(["alice", "bob"]))
+            .unwrap();
+        assert_eq!(inferred[0], by_strkey[0]);
+        assert!(matches!(inferred[1], ScVal::Symbol(_)));
+    }
+
+    #[test]
+    fn test_mismatch_names_parameter_and_type() {
+        let spec = test_spec();
+        let codec = ValueCodec::new(&spec);
+        let function = spec.function("transfer").unwrap();
+
+        let err = codec
+            .encode_args(function, &json!([ACCOUNT, "lots"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'amount'"), "{}", err);
+        assert!(err.contains("i128"), "{}", err);
+    }
+
+    #[test]
+    fn test_struct_and_union_encoding() {
+        let spec = test_spec();
+        let codec = ValueCodec::new(&spec);
+
+        let info = codec
+            .to_scval(&udt("Info"), &json!({"name": "x", "count": 3}))
+            .unwrap();
+        let ScVal::Map(Some(map)) = info else {
+            panic!("expected map");
+        };
+        // Keys are sorted for the host
+        assert_eq!(map[0].key, ScVal::Symbol("count".try_into().unwrap()));
+
+        let key = codec
+            .to_scval(&udt("DataKey"), &json!({"Balance": ACCOUNT}))
+            .unwrap();
+        let ScVal::Vec(Some(items)) = key else {
+            panic!("expected vec");
+        };
+        assert_eq!(items.len(), 2);
+        assert!(codec.to_scval(&udt("DataKey"), &json!("Admin")).is_ok());
+        assert!(codec.to_scval(&udt("DataKey"), &json!("Nope")).is_err());
+    }
+
+    #[test]
+    fn test_decode_uses_field_and_variant_names() {
+        let spec = test_spec();
+        let codec = ValueCodec::new(&spec);
+
+        for (ty, input) in [
+            (udt("Info"), json!({"count": 3, "name": "x"})),
+            (udt("DataKey"), json!({"Balance": ACCOUNT})),
+            (udt("DataKey"), json!("Admin")),
+            (ScSpecTypeDef::I256, json!("-57896044618658097711785492504343953926634992332820282019728792003956564819968")),
+            (ScSpecTypeDef::U128, json!(42)),
+        ] {
+            let value = codec.to_scval(&ty, &input).unwrap();
+            assert_eq!(codec.to_json(&ty, &value), input);
+        }
+    }
+
+    #[test]
+    fn test_nested_and_wide_integers() {
+        let spec = test_spec();
+        let codec = ValueCodec::new(&spec);
+        let ty = ScSpecTypeDef::Vec(Box::new(ScSpecTypeVec {
+            element_type: Box::new(ScSpecTypeDef::I256),
+        }));
+
+        let value = codec.to_scval(&ty, &json!(["-1", "0x10"])).unwrap();
+        let ScVal::Vec(Some(items)) = value else {
+            panic!("expected vec");
+        };
+        assert_eq!(
+            items[0],
+            ScVal::I256(Int256Parts {
+                hi_hi: -1,
+                hi_lo: u64::MAX,
+                lo_hi: u64::MAX,
+                lo_lo: u64::MAX
+            })
+        );
+        assert_eq!(
+            items[1],
+            ScVal::I256(Int256Parts {
+                hi_hi: 0,
+                hi_lo: 0,
+                lo_hi: 0,
+                lo_lo: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_i128_accepts_hex() {
+        assert_eq!(json_i128(&json!("0x10")), Some(16));
+        assert_eq!(json_i128(&json!("-0xff")), Some(-255));
+        assert_eq!(json_i128(&json!(-7)), Some(-7));
+        assert_eq!(
+            json_i128(&json!("-0x80000000000000000000000000000000")),
+            Some(i128::MIN)
+        );
+        assert_eq!(
+            json_i128(&json!("0x80000000000000000000000000000000")),
+            None
+        );
+        assert_eq!(json_i128(&json!("0x")), None);
+    }
+
+    #[test]
+    fn test_u128_accepts_hex() {
+        assert_eq!(json_u128(&json!("0xFF")), Some(255));
+        assert_eq!(json_u128(&json!("1_000")), Some(1000));
+        assert_eq!(
+            json_u128(&json!(format!("0x{}", "f".repeat(32)))),
+            Some(u128::MAX)
+        );
+        assert_eq!(json_u128(&json!(format!("0x1{}", "0".repeat(32)))), None);
+        assert_eq!(json_u128(&json!("-0x1")), None);
+    }
+}
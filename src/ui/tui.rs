@@ -1,24 +1,50 @@
-use crate::debugger::engine::DebuggerEngine;
+use crate::debugger::breakpoint::{Breakpoint, BreakpointKind};
+use crate::debugger::engine::{DebugContext, PauseReason};
+use crate::debugger::observer::StdoutObserver;
+use crate::debugger::stepper::{StepGranularity, StepKind};
+use crate::debugger::watch::Watch;
+use crate::debugger::worker::{Command, EngineWorker, Event};
 use crate::inspector::{BudgetInspector, CallStackInspector, StorageInspector};
+use crate::runtime::events::ContractEvent;
+use crate::runtime::executor::ContractExecutor;
+use crate::runtime::instrumentation::{FunctionEvent, HookKind};
+use crate::runtime::snapshot::save_snapshot;
+use crate::runtime::values::ValueCodec;
+use crate::utils::spec::ContractSpec;
 use crate::Result;
 use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
 
 /// Terminal user interface for interactive debugging
 pub struct DebuggerUI {
-    engine: DebuggerEngine,
-    storage_inspector: StorageInspector,
-    stack_inspector: CallStackInspector,
+    worker: EngineWorker,
+    /// Whether a call is paused on the engine thread
+    paused: bool,
+    events: Vec<ContractEvent>,
+    trace: Vec<FunctionEvent>,
 }
 
 impl DebuggerUI {
-    pub fn new(engine: DebuggerEngine) -> Result<Self> {
+    pub fn new(worker: EngineWorker) -> Result<Self> {
+        // Pauses are shown as the worker reports them
+        worker.with(|ctx| match ctx.engine() {
+            Some(engine) => engine.add_observer(Rc::new(StdoutObserver::new())),
+            None => Ok(()),
+        })??;
         Ok(Self {
-            engine,
-            storage_inspector: StorageInspector::new(),
-            stack_inspector: CallStackInspector::new(),
+            worker,
+            paused: false,
+            events: Vec::new(),
+            trace: Vec::new(),
         })
     }
 
+    /// The engine thread driving this session
+    pub fn worker(&self) -> &EngineWorker {
+        &self.worker
+    }
+
     /// Run the interactive UI loop
     pub fn run(&mut self) -> Result<()> {
         self.print_help();
@@ -28,7 +54,10 @@ impl DebuggerUI {
             io::stdout().flush()?;
 
             let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
+            if io::stdin().read_line(&mut input)? == 0 {
+                self.handle_command("quit")?;
+                break;
+            }
 
             let command = input.trim();
             if command.is_empty() {
@@ -58,58 +87,287 @@ impl DebuggerUI {
         }
 
         match parts[0] {
-            "s" | "step" => {
-                self.engine.step()?;
-                println!("Stepped");
+            "s" | "step" => self.resume(Command::Step(StepKind::Into))?,
+            "n" | "next" => self.resume(Command::Step(StepKind::Over))?,
+            "finish" | "out" => self.resume(Command::Step(StepKind::Out))?,
+            "c" | "continue" => self.resume(Command::Continue)?,
+            "back" => self.resume(Command::Back)?,
+            "rc" | "reverse-continue" => self.resume(Command::ReverseContinue)?,
+            "goto" => match parts.get(1).and_then(|s| s.parse().ok()) {
+                Some(step) => self.resume(Command::Goto(step))?,
+                None => println!("Usage: goto <step>"),
+            },
+            "abort" => {
+                if self.paused {
+                    self.worker.send(Command::Abort)?;
+                    self.wait()?;
+                } else {
+                    println!("No call is paused");
+                }
             }
-            "c" | "continue" => {
-                self.engine.continue_execution()?;
-                println!("Continuing...");
+            "step-mode" => {
+                let granularity = match parts.get(1).map(|mode| mode.parse()) {
+                    Some(Ok(granularity)) => Some(granularity),
+                    Some(Err(_)) => {
+                        println!("Usage: step-mode [instruction|function|host]");
+                        return Ok(false);
+                    }
+                    None => None,
+                };
+                self.worker.with(move |ctx| match granularity {
+                    Some(granularity) => {
+                        ctx.set_step_granularity(granularity);
+                        match granularity {
+                            StepGranularity::Instruction => {
+                                println!("Steps now stop at every instruction")
+                            }
+                            StepGranularity::Function => {
+                                println!("Steps now stop at every function entry")
+                            }
+                            StepGranularity::HostCall => {
+                                println!("Steps now stop before every host function call")
+                            }
+                        }
+                    }
+                    None => println!("Step mode: {}", ctx.step_granularity()),
+                })?;
+            }
+            "call" | "start" => {
+                if parts.len() < 2 {
+                    println!("Usage: {} <function> [args_json]", parts[0]);
+                } else if self.paused {
+                    println!("A call is paused; continue it with 'c' or end it with 'abort'");
+                } else {
+                    let args = command
+                        .splitn(3, char::is_whitespace)
+                        .nth(2)
+                        .map(str::trim)
+                        .filter(|a| !a.is_empty())
+                        .map(str::to_string);
+                    self.worker.send(Command::Call {
+                        function: parts[1].to_string(),
+                        args,
+                        step: parts[0] == "start",
+                    })?;
+                    self.wait()?;
+                }
             }
+            "contracts" => {
+                self.worker.with(list_contracts)?;
+            }
+            "trace" => {
+                self.print_trace();
+            }
+            "events" => {
+                let contract = parts.get(1).copied().filter(|c| *c != "*");
+                let topic = parts.get(2).copied();
+                self.list_events(contract, topic);
+            }
+            "use" => match parts.get(1) {
+                Some(name) => {
+                    let name = name.to_string();
+                    self.with_executor(move |executor| {
+                        executor.select_contract(&name)?;
+                        println!("Calls now go to: {}", name);
+                        Ok(())
+                    })?;
+                }
+                None => println!("Usage: use <contract>"),
+            },
+            "ledger" => {
+                self.with_executor(|executor| {
+                    let info = executor.ledger_info();
+                    println!("Ledger:");
+                    println!("  Sequence: {}", info.sequence_number);
+                    println!("  Timestamp: {}", info.timestamp);
+                    println!("  Protocol: {}", info.protocol_version);
+                    println!("  Base reserve: {}", info.base_reserve);
+                    println!(
+                        "  TTL: min persistent {}, min temporary {}, max {}",
+                        info.min_persistent_entry_ttl, info.min_temp_entry_ttl, info.max_entry_ttl
+                    );
+                    Ok(())
+                })?;
+            }
+            "advance-time" => match parts.get(1).and_then(|n| n.parse::<u64>().ok()) {
+                Some(seconds) => {
+                    self.with_executor(move |executor| {
+                        executor.advance_time(seconds);
+                        println!("Ledger timestamp: {}", executor.ledger_info().timestamp);
+                        Ok(())
+                    })?;
+                }
+                None => println!("Usage: advance-time <seconds>"),
+            },
+            "advance-seq" => match parts.get(1).and_then(|n| n.parse::<u32>().ok()) {
+                Some(ledgers) => {
+                    self.with_executor(move |executor| {
+                        executor.advance_sequence(ledgers);
+                        println!(
+                            "Ledger sequence: {}",
+                            executor.ledger_info().sequence_number
+                        );
+                        Ok(())
+                    })?;
+                }
+                None => println!("Usage: advance-seq <ledgers>"),
+            },
+            "export-snapshot" => match parts.get(1) {
+                Some(path) => {
+                    let path = path.to_string();
+                    self.with_executor(move |executor| {
+                        let snapshot = executor.ledger_snapshot();
+                        save_snapshot(&snapshot, Path::new(&path))?;
+                        println!(
+                            "Wrote {} ledger entries to {}",
+                            snapshot.ledger_entries.len(),
+                            path
+                        );
+                        Ok(())
+                    })?;
+                }
+                None => println!("Usage: export-snapshot <file>"),
+            },
             "i" | "inspect" => {
-                self.inspect();
+                self.worker.with(inspect)?;
             }
             "storage" => {
-                self.storage_inspector.display();
+                let contract = parts.get(1).map(|c| c.to_string());
+                self.worker
+                    .with(move |ctx| show_storage(ctx, contract.as_deref()))??;
             }
             "stack" => {
-                self.stack_inspector.display();
+                self.worker.with(|ctx| {
+                    let mut inspector = CallStackInspector::new();
+                    for frame in ctx.call_stack() {
+                        inspector.push(frame.to_string());
+                    }
+                    inspector.display();
+                })?;
             }
             "budget" => {
-                BudgetInspector::display(self.engine.executor().host());
+                self.worker.with(|ctx| {
+                    BudgetInspector::display_info(&ctx.budget());
+                    if ctx.contracts().iter().count() > 1 {
+                        list_contracts(ctx);
+                    }
+                })?;
             }
             "break" => {
                 if parts.len() < 2 {
-                    println!("Usage: break <function_name>");
+                    println!("Usage: break <function_name|kind:target> [if <condition>]");
                 } else {
-                    self.engine.breakpoints_mut().add(parts[1]);
-                    println!("Breakpoint set at: {}", parts[1]);
+                    let breakpoint: Breakpoint = command["break".len()..].parse()?;
+                    self.worker.with(move |ctx| {
+                        println!("Breakpoint set at: {}", breakpoint);
+                        if let BreakpointKind::Source { file, line } = &breakpoint.kind {
+                            report_line(ctx, file, *line);
+                        }
+                        ctx.breakpoints_mut().insert(breakpoint);
+                    })?;
                 }
             }
-            "list-breaks" => {
-                let breakpoints = self.engine.breakpoints_mut().list();
-                if breakpoints.is_empty() {
-                    println!("No breakpoints set");
+            "log" => {
+                if parts.len() < 2 {
+                    println!("Usage: log <function_name> [if <condition>]: <message>");
                 } else {
-                    println!("Breakpoints:");
-                    for bp in breakpoints {
-                        println!("  - {}", bp);
-                    }
+                    let logpoint = Breakpoint::logpoint(&command["log".len()..])?;
+                    self.worker.with(move |ctx| {
+                        println!("Logpoint set at: {}", logpoint);
+                        if let BreakpointKind::Source { file, line } = &logpoint.kind {
+                            report_line(ctx, file, *line);
+                        }
+                        ctx.breakpoints_mut().insert(logpoint);
+                    })?;
                 }
             }
+            "ignore" => match (
+                parts.get(1),
+                parts.get(2).and_then(|n| n.parse::<u32>().ok()),
+            ) {
+                (Some(function), Some(count)) => {
+                    let function = function.to_string();
+                    self.worker.with(move |ctx| {
+                        if ctx.breakpoints_mut().set_ignore(&function, count) {
+                            println!("Ignoring next {} hits of: {}", count, function);
+                        } else {
+                            println!("No breakpoint at: {}", function);
+                        }
+                    })?;
+                }
+                _ => println!("Usage: ignore <function_name> <count>"),
+            },
+            "watch" => {
+                if parts.len() < 2 {
+                    self.worker.with(|ctx| {
+                        let watches = ctx.watches_mut().list();
+                        if watches.is_empty() {
+                            println!("No storage keys watched");
+                        } else {
+                            println!("Watches:");
+                            for watch in watches {
+                                println!("  - {}", watch);
+                            }
+                        }
+                    })?;
+                } else {
+                    let watch: Watch = command["watch".len()..].parse()?;
+                    self.worker.with(move |ctx| {
+                        println!("Watching: {}", watch);
+                        ctx.watches_mut().insert(watch);
+                    })?;
+                }
+            }
+            "unwatch" => {
+                if parts.len() < 2 {
+                    println!("Usage: unwatch [durability] <key>");
+                } else {
+                    let watch: Watch = command["unwatch".len()..].parse()?;
+                    self.worker.with(move |ctx| {
+                        if ctx.watches_mut().remove(&watch) {
+                            println!("Watch removed: {}", watch);
+                        } else {
+                            println!("No watch on: {}", watch);
+                        }
+                    })?;
+                }
+            }
+            "list-breaks" => {
+                self.worker.with(|ctx| {
+                    let breakpoints = ctx.breakpoints_mut().list();
+                    if breakpoints.is_empty() {
+                        println!("No breakpoints set");
+                    } else {
+                        println!("Breakpoints:");
+                        for bp in breakpoints {
+                            println!("  - {}", bp);
+                        }
+                    }
+                })?;
+            }
             "clear" => {
                 if parts.len() < 2 {
-                    println!("Usage: clear <function_name>");
-                } else if self.engine.breakpoints_mut().remove(parts[1]) {
-                    println!("Breakpoint removed: {}", parts[1]);
+                    println!("Usage: clear <function_name|kind:target>");
                 } else {
-                    println!("No breakpoint at: {}", parts[1]);
+                    let function = parts[1].to_string();
+                    self.worker.with(move |ctx| {
+                        if ctx.breakpoints_mut().remove(&function) {
+                            println!("Breakpoint removed: {}", function);
+                        } else {
+                            println!("No breakpoint at: {}", function);
+                        }
+                    })?;
                 }
             }
             "help" => {
                 self.print_help();
             }
             "q" | "quit" | "exit" => {
+                if self.paused {
+                    self.worker.send(Command::Abort)?;
+                    // The aborted call's failure is expected
+                    let _ = self.wait();
+                }
                 println!("Exiting debugger");
                 return Ok(true);
             }
@@ -124,31 +382,266 @@ impl DebuggerUI {
         Ok(false)
     }
 
-    /// Display current state
-    fn inspect(&self) {
-        println!("\n=== Current State ===");
-        if let Some(func) = self.engine.state().current_function() {
-            println!("Function: {}", func);
-        } else {
-            println!("Function: (none)");
+    /// Let the paused call go on, and wait for it to pause again or finish
+    fn resume(&mut self, command: Command) -> Result<()> {
+        if !self.paused {
+            println!("No call is paused; run a function with 'call' or 'start' first");
+            return Ok(());
+        }
+        self.worker.send(command)?;
+        self.wait()
+    }
+
+    /// Wait for the engine to pause or finish the running call
+    fn wait(&mut self) -> Result<()> {
+        match self.worker.recv()? {
+            Event::Paused {
+                reason,
+                point,
+                step,
+                replaying,
+            } => {
+                self.paused = true;
+                let history = if replaying { " (history)" } else { "" };
+                match reason {
+                    PauseReason::Breakpoint => {
+                        println!("[BREAKPOINT] Paused at {}{}", point, history)
+                    }
+                    PauseReason::Step => println!("Step {}: {}{}", step, point, history),
+                    PauseReason::Failure(failure) => {
+                        println!("[CATCH] {} at {}{}", failure, point, history)
+                    }
+                    PauseReason::Watch(access) => println!("[WATCH] {}{}", access, history),
+                }
+            }
+            Event::Finished(result) => {
+                self.paused = false;
+                let result = result?;
+                println!("Result ({}): {}", result.contract, result.pretty());
+                for event in &result.events {
+                    println!("Event: {}", event);
+                }
+                for auth in &result.auths {
+                    print!("Authorized: {}", auth);
+                }
+                self.events.extend(result.events);
+                self.trace = result.trace;
+            }
+            Event::Rejected(reason) => println!("{}", reason),
+        }
+        Ok(())
+    }
+
+    /// Run code with the executor, which a paused call holds on to
+    fn with_executor<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut ContractExecutor) -> Result<()> + Send + 'static,
+    {
+        self.worker.with(|ctx| match ctx.engine() {
+            Some(engine) => f(engine.executor_mut()),
+            None => {
+                println!("Not available while a call is paused; continue it or 'abort' it first");
+                Ok(())
+            }
+        })?
+    }
+
+    /// Print the session's events, optionally only those of one contract or
+    /// with a given topic
+    fn list_events(&self, contract: Option<&str>, topic: Option<&str>) {
+        let matching: Vec<_> = self
+            .events
+            .iter()
+            .filter(|e| contract.is_none_or(|c| e.emitted_by(c)))
+            .filter(|e| topic.is_none_or(|t| e.has_topic(t)))
+            .collect();
+        if matching.is_empty() {
+            println!("No events");
+            return;
+        }
+        println!("Events:");
+        for event in matching {
+            println!("  {}", event);
+        }
+    }
+
+    /// Print the WASM function calls of the last invocation as a tree
+    fn print_trace(&self) {
+        if self.trace.is_empty() {
+            println!("No function calls traced");
+            return;
+        }
+        println!("Function calls:");
+        let mut depth = 0;
+        for event in &self.trace {
+            match event.kind {
+                HookKind::Enter => {
+                    println!(
+                        "  {}{}::{}",
+                        "  ".repeat(depth),
+                        event.contract,
+                        event.function
+                    );
+                    depth += 1;
+                }
+                HookKind::Exit => depth = depth.saturating_sub(1),
+            }
         }
-        println!("Steps: {}", self.engine.state().step_count());
-        println!("Paused: {}", self.engine.is_paused());
     }
 
     /// Print help message
     fn print_help(&self) {
         println!("\nAvailable commands:");
-        println!("  s, step              Execute next instruction");
+        println!("  s, step              Step into the next instruction of the paused call");
+        println!("  n, next              Step over calls to the next instruction");
+        println!("  finish, out          Step out of the current function");
+        println!("  step-mode [mode]     Step by instruction, function or host call");
         println!("  c, continue          Run until breakpoint or completion");
+        println!("  back                 Step back through the paused call's history");
+        println!("  rc, reverse-continue Go back to the previous breakpoint hit");
+        println!("  goto <step>          Go to a step of the paused call's history");
+        println!("  abort                End the paused call with an error");
+        println!("  call <fn> [args]     Invoke a function with JSON arguments");
+        println!("  start <fn> [args]    Invoke a function, pausing at its first step");
+        println!("  contracts            List registered contracts and their usage");
+        println!("  use <contract>       Direct calls to another contract");
+        println!("  events [c|*] [topic] List events, by contract and topic");
+        println!("  trace                Show the WASM function calls of the last call");
         println!("  i, inspect           Show current execution state");
-        println!("  storage              Display contract storage");
-        println!("  stack                Show call stack");
+        println!("  ledger               Show ledger sequence, time and TTL settings");
+        println!("  advance-time <secs>  Move the ledger timestamp forward");
+        println!("  advance-seq <n>      Move the ledger sequence number forward");
+        println!("  export-snapshot <f>  Write the ledger state to a snapshot file");
+        println!("  storage [contract]   Display the storage of the current contract");
+        println!("  stack                Show the call stack of the paused call");
         println!("  budget               Show resource usage (CPU/memory)");
-        println!("  break <function>     Set breakpoint at function");
-        println!("  list-breaks          List all breakpoints");
-        println!("  clear <function>     Remove breakpoint");
+        println!("  break <fn> [if cond] Set breakpoint at function, optionally conditional");
+        println!("  break host:<name>    Break on a host function call; also auth:<address>,");
+        println!("                       event:<topic> and call:<contract>[::<fn>]");
+        println!("  break <file>:<line>  Break at a source line of a contract with debug info");
+        println!("  break catch:<what>   Break before a call fails: error, panic or budget");
+        println!("  log <fn> [if c]: msg Print a message with {{expressions}} at function");
+        println!("  ignore <fn> <n>      Let the next n hits of a breakpoint pass");
+        println!("  list-breaks          List all breakpoints with hit counts");
+        println!(
+            "  watch [dur] <key>    Pause when a storage key is accessed; alone, list watches"
+        );
+        println!("  unwatch [dur] <key>  Remove a watch");
+        println!("  clear <fn|kind:tgt>  Remove breakpoint");
         println!("  help                 Show this help message");
         println!("  q, quit              Exit debugger");
     }
 }
+
+/// Say where a source line breakpoint resolves to in the contracts' code
+fn report_line(ctx: &mut dyn DebugContext, file: &str, line: usize) {
+    let offsets = ctx.contracts().resolve_line(file, line);
+    if offsets.is_empty() {
+        println!(
+            "No code found at {}:{}; is the contract built with debug info?",
+            file, line
+        );
+    }
+    for (contract, offset) in offsets {
+        println!("  {} at offset 0x{:x}", contract, offset);
+    }
+}
+
+/// Display the storage of a contract: the one named, else the one the paused
+/// call is in, else the one calls go to
+fn show_storage(ctx: &mut dyn DebugContext, name: Option<&str>) -> Result<()> {
+    let name = match (name, ctx.current_point()) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(point)) => point.contract,
+        (None, None) => match ctx.engine().map(|engine| engine.executor().target()) {
+            Some(target) => target?.alias().to_string(),
+            None => return Ok(()),
+        },
+    };
+    let Some(address) = ctx.contracts().resolve(&name) else {
+        println!("Unknown contract: {}", name);
+        return Ok(());
+    };
+    let default_spec = ContractSpec::default();
+    let spec = ctx
+        .contracts()
+        .by_address(&address)
+        .map_or(&default_spec, |c| c.spec());
+    let codec = ValueCodec::new(spec);
+    let mut inspector = StorageInspector::new();
+    for (durability, key, value) in ctx.storage(&address)? {
+        inspector.insert(
+            format!("{} {}", durability, codec.to_json_untyped(&key)),
+            codec.to_json_untyped(&value).to_string(),
+        );
+    }
+    inspector.display();
+    Ok(())
+}
+
+/// Display current state
+fn inspect(ctx: &mut dyn DebugContext) {
+    println!("\n=== Current State ===");
+    let state = ctx.state();
+    if let Some(func) = state.current_function() {
+        println!("Function: {}", func);
+    } else {
+        println!("Function: (none)");
+    }
+    if let Some(contract) = state.contract() {
+        println!(
+            "Contract: {} ({})",
+            ctx.contracts().display_name(contract),
+            contract
+        );
+        println!("Depth: {}", state.depth());
+    }
+    if !state.args().is_empty() {
+        println!("Arguments:");
+        for (name, value) in state.args() {
+            println!("  {} = {}", name, value);
+        }
+    }
+    if let Some(point) = ctx.current_point() {
+        println!("At: {}", point);
+    }
+    println!("Step: {}", state.step_count());
+    match state.pause_reason() {
+        Some(reason) => println!("Paused: yes ({})", reason),
+        None => println!("Paused: no"),
+    }
+    if let Some(result) = state.last_result() {
+        println!("Last result: {}", result);
+    }
+    if let Some(error) = state.last_error() {
+        println!("Last error: {}", error);
+    }
+}
+
+/// List registered contracts with the resources attributed to each
+fn list_contracts(ctx: &mut dyn DebugContext) {
+    let target = ctx
+        .engine()
+        .and_then(|engine| engine.executor().target().ok())
+        .map(|c| c.alias().to_string());
+    println!("Contracts:");
+    for contract in ctx.contracts().iter() {
+        let usage = contract.usage();
+        let marker = if Some(contract.alias()) == target.as_deref() {
+            "*"
+        } else {
+            " "
+        };
+        // Contracts from a snapshot are named by their strkey
+        let id = contract.contract_id();
+        let name = if contract.alias() == id {
+            id
+        } else {
+            format!("{} {}", contract.alias(), id)
+        };
+        println!(
+            "{} {} ({} calls, {} CPU, {} bytes)",
+            marker, name, usage.invocations, usage.cpu_instructions, usage.memory_bytes
+        );
+    }
+}
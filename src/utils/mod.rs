@@ -1,5 +1,7 @@
 pub mod source_map;
+pub mod spec;
 pub mod wasm;
 
 pub use source_map::{SourceLocation, SourceMap};
+pub use spec::ContractSpec;
 pub use wasm::{get_module_info, parse_functions, ModuleInfo};
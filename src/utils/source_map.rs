@@ -1,37 +1,224 @@
-/// Source map handling for mapping WASM to source code
-/// This will be implemented in Phase 3
+use gimli::{ColumnType, Dwarf, EndianSlice, LittleEndian, SectionId};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use wasmparser::{Parser, Payload};
+
+/// Maps WASM code offsets to source locations using the DWARF line tables
+/// that `cargo build` keeps in debug builds (`.debug_line` and friends).
+///
+/// DWARF addresses in WASM are relative to the start of the code section;
+/// lookups take module offsets, as reported by `wasmparser` and `walrus`.
+#[derive(Debug, Clone, Default)]
 pub struct SourceMap {
-    // TODO: Implement source map parsing and lookup
+    /// Offset of the code section's contents in the module
+    code_offset: usize,
+    /// Line table rows by address; `None` marks the end of a sequence
+    rows: BTreeMap<u64, Option<SourceLocation>>,
 }
 
 impl SourceMap {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
+    }
+
+    /// Load the line tables from a module's DWARF custom sections. Returns
+    /// `None` if the module has no usable debug info.
+    pub fn from_wasm(wasm: &[u8]) -> Option<Self> {
+        let mut sections: HashMap<&str, &[u8]> = HashMap::new();
+        let mut code_offset = None;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.ok()? {
+                Payload::CodeSectionStart { range, .. } => code_offset = Some(range.start),
+                Payload::CustomSection(reader) if reader.name().starts_with(".debug_") => {
+                    sections.insert(reader.name(), reader.data());
+                }
+                _ => {}
+            }
+        }
+        if !sections.contains_key(".debug_line") {
+            return None;
+        }
+
+        let load = |id: SectionId| -> Result<EndianSlice<LittleEndian>, gimli::Error> {
+            let data = sections.get(id.name()).copied().unwrap_or_default();
+            Ok(EndianSlice::new(data, LittleEndian))
+        };
+        let dwarf = Dwarf::load(load).ok()?;
+        let rows = line_rows(&dwarf).ok()?;
+        if rows.is_empty() {
+            return None;
+        }
+        Some(Self {
+            code_offset: code_offset?,
+            rows,
+        })
     }
 
-    /// Load source map from WASM custom section
-    pub fn from_wasm(_wasm: &[u8]) -> Option<Self> {
-        // TODO: Parse source map custom section
-        None
+    /// Get source location for a WASM instruction at a module offset
+    pub fn get_location(&self, offset: usize) -> Option<SourceLocation> {
+        let address = offset.checked_sub(self.code_offset)? as u64;
+        self.rows.range(..=address).next_back()?.1.clone()
     }
 
-    /// Get source location for a WASM instruction
-    pub fn get_location(&self, _offset: usize) -> Option<SourceLocation> {
-        // TODO: Implement
-        None
+    /// Module offsets where a source line starts, for a file given by its
+    /// path or by trailing components of it
+    pub fn resolve(&self, file: &str, line: usize) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut previous: Option<&SourceLocation> = None;
+        for (&address, location) in &self.rows {
+            let location = location.as_ref();
+            if let Some(location) = location {
+                let continued = previous.is_some_and(|p| p.is_at(&location.file, location.line));
+                if location.is_at(file, line) && !continued {
+                    offsets.push(self.code_offset + address as usize);
+                }
+            }
+            previous = location;
+        }
+        offsets
+    }
+
+    /// Whether the map has any line information
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
     }
 }
 
-impl Default for SourceMap {
-    fn default() -> Self {
-        Self::new()
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+fn line_rows(dwarf: &Dwarf<Reader>) -> gimli::Result<BTreeMap<u64, Option<SourceLocation>>> {
+    let mut rows = BTreeMap::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+        let mut program_rows = program.rows();
+        while let Some((header, row)) = program_rows.next_row()? {
+            if row.end_sequence() {
+                rows.entry(row.address()).or_insert(None);
+                continue;
+            }
+            let Some(line) = row.line() else {
+                continue;
+            };
+            let file = match row.file(header) {
+                Some(entry) => {
+                    let name = dwarf.attr_string(&unit, entry.path_name())?;
+                    let dir = match entry.directory(header) {
+                        Some(dir) => Some(dwarf.attr_string(&unit, dir)?),
+                        None => None,
+                    };
+                    let path = join_path(dir.map(|d| d.to_string_lossy()), name.to_string_lossy());
+                    match &unit.comp_dir {
+                        Some(comp_dir) => join_path(Some(comp_dir.to_string_lossy()), path.into()),
+                        None => path,
+                    }
+                }
+                None => continue,
+            };
+            let column = match row.column() {
+                ColumnType::LeftEdge => 0,
+                ColumnType::Column(column) => column.get() as usize,
+            };
+            rows.insert(
+                row.address(),
+                Some(SourceLocation {
+                    file,
+                    line: line.get() as usize,
+                    column,
+                }),
+            );
+        }
+    }
+    Ok(rows)
+}
+
+fn join_path(dir: Option<Cow<str>>, name: Cow<str>) -> String {
+    match dir {
+        Some(dir) if !dir.is_empty() && !name.starts_with('/') => {
+            format!("{}/{}", dir.trim_end_matches('/'), name)
+        }
+        _ => name.into_owned(),
     }
 }
 
 /// A location in source code
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SourceLocation {
     pub file: String,
     pub line: usize,
     pub column: usize,
 }
+
+impl SourceLocation {
+    /// Whether the location is on a line of a file, given by its path or by
+    /// trailing components of it such as `src/lib.rs`
+    pub fn is_at(&self, file: &str, line: usize) -> bool {
+        let in_file = match self.file.strip_suffix(file) {
+            Some("") => true,
+            Some(rest) => rest.ends_with('/') || file.starts_with('/'),
+            None => false,
+        };
+        self.line == line && in_file
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CONTRACT: &[u8] = include_bytes!("../../tests/fixtures/test_contract.wasm");
+
+    #[test]
+    fn test_from_wasm() {
+        let map = SourceMap::from_wasm(TEST_CONTRACT).unwrap();
+        assert!(!map.is_empty());
+        // A module without custom sections has no debug info
+        assert!(SourceMap::from_wasm(b"\0asm\x01\0\0\0").is_none());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let map = SourceMap::from_wasm(TEST_CONTRACT).unwrap();
+        // `panic!("boom")` in the fixture's `boom`
+        let offsets = map.resolve("src/lib.rs", 74);
+        assert!(!offsets.is_empty());
+        for offset in offsets {
+            let location = map.get_location(offset).unwrap();
+            assert!(location.file.ends_with("test-contract/src/lib.rs"));
+            assert_eq!(location.line, 74);
+        }
+        assert_eq!(map.resolve("lib.rs", 74), map.resolve("src/lib.rs", 74));
+        assert!(map.resolve("src/lib.rs", 1000).is_empty());
+        assert!(map.resolve("b.rs", 74).is_empty());
+        // Offsets before the code section map nowhere
+        assert!(map.get_location(0).is_none());
+    }
+
+    #[test]
+    fn test_is_at() {
+        let location = SourceLocation {
+            file: "/work/token/src/lib.rs".to_string(),
+            line: 42,
+            column: 5,
+        };
+        assert!(location.is_at("/work/token/src/lib.rs", 42));
+        assert!(location.is_at("src/lib.rs", 42));
+        assert!(location.is_at("lib.rs", 42));
+        assert!(!location.is_at("src/lib.rs", 43));
+        assert!(!location.is_at("b.rs", 42));
+        assert!(!location.is_at("token/lib.rs", 42));
+        // A leading slash only marks where a component starts
+        assert!(location.is_at("/src/lib.rs", 42));
+        assert!(!location.is_at("/rc/lib.rs", 42));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{DebuggerError, Result};
+use soroban_sdk::xdr::{
+    Limited, Limits, ReadXdr, ScSpecEntry, ScSpecFunctionV0, ScSpecTypeBytesN, ScSpecTypeDef,
+    ScSpecTypeMap, ScSpecTypeOption, ScSpecTypeTuple, ScSpecTypeUdt, ScSpecTypeVec,
+    ScSpecUdtEnumV0, ScSpecUdtErrorEnumV0, ScSpecUdtStructV0, ScSpecUdtUnionV0,
+};
+use wasmparser::{Parser, Payload};
+
+/// Name of the custom section holding the contract interface
+const SPEC_SECTION: &str = "contractspecv0";
+
+/// Contract interface parsed from the `contractspecv0` custom section
+#[derive(Debug, Clone, Default)]
+pub struct ContractSpec {
+    entries: Vec<ScSpecEntry>,
+}
+
+/// A user-defined type declared in the contract spec
+#[derive(Debug, Clone, Copy)]
+pub enum UdtDef<'a> {
+    Struct(&'a ScSpecUdtStructV0),
+    Union(&'a ScSpecUdtUnionV0),
+    Enum(&'a ScSpecUdtEnumV0),
+    ErrorEnum(&'a ScSpecUdtErrorEnumV0),
+}
+
+impl ContractSpec {
+    /// Create a spec from already decoded entries
+    pub fn new(entries: Vec<ScSpecEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Parse the contract spec from WASM bytes.
+    /// Contracts without a spec section yield an empty spec.
+    pub fn from_wasm(wasm_bytes: &[u8]) -> Result<Self> {
+        let mut entries = Vec::new();
+
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CustomSection(reader) = payload? {
+                if reader.name() != SPEC_SECTION {
+                    continue;
+                }
+                entries.extend(Self::from_xdr(reader.data())?.entries);
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Parse a stream of XDR encoded spec entries
+    pub fn from_xdr(data: &[u8]) -> Result<Self> {
+        let mut limited = Limited::new(data, Limits::none());
+        let entries = ScSpecEntry::read_xdr_iter(&mut limited)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| DebuggerError::WasmLoadError(format!("Invalid contract spec: {}", e)))?;
+        Ok(Self { entries })
+    }
+
+    /// All spec entries in declaration order
+    pub fn entries(&self) -> &[ScSpecEntry] {
+        &self.entries
+    }
+
+    /// Check whether the contract carried no spec at all
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find a function definition by name
+    pub fn function(&self, name: &str) -> Option<&ScSpecFunctionV0> {
+        self.entries.iter().find_map(|entry| match entry {
+            ScSpecEntry::FunctionV0(f) if f.name.to_utf8_string_lossy() == name => Some(f),
+            _ => None,
+        })
+    }
+
+    /// List all function definitions
+    pub fn functions(&self) -> impl Iterator<Item = &ScSpecFunctionV0> {
+        self.entries.iter().filter_map(|entry| match entry {
+            ScSpecEntry::FunctionV0(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    /// Find a user-defined type by name
+    pub fn udt(&self, name: &str) -> Option<UdtDef<'_>> {
+        self.entries.iter().find_map(|entry| match entry {
+            ScSpecEntry::UdtStructV0(s) if s.name.to_utf8_string_lossy() == name => {
+                Some(UdtDef::Struct(s))
+            }
+            ScSpecEntry::UdtUnionV0(u) if u.name.to_utf8_string_lossy() == name => {
+                Some(UdtDef::Union(u))
+            }
+            ScSpecEntry::UdtEnumV0(e) if e.name.to_utf8_string_lossy() == name => {
+                Some(UdtDef::Enum(e))
+            }
+            ScSpecEntry::UdtErrorEnumV0(e) if e.name.to_utf8_string_lossy() == name => {
+                Some(UdtDef::ErrorEnum(e))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Render a spec type the way it appears in Rust contract source
+pub fn type_name(ty: &ScSpecTypeDef) -> String {
+    match ty {
+        ScSpecTypeDef::Val => "Val".to_string(),
+        ScSpecTypeDef::Bool => "bool".to_string(),
+        ScSpecTypeDef::Void => "()".to_string(),
+        ScSpecTypeDef::Error => "Error".to_string(),
+        ScSpecTypeDef::U32 => "u32".to_string(),
+        ScSpecTypeDef::I32 => "i32".to_string(),
+        ScSpecTypeDef::U64 => "u64".to_string(),
+        ScSpecTypeDef::I64 => "i64".to_string(),
+        ScSpecTypeDef::Timepoint => "Timepoint".to_string(),
+        ScSpecTypeDef::Duration => "Duration".to_string(),
+        ScSpecTypeDef::U128 => "u128".to_string(),
+        ScSpecTypeDef::I128 => "i128".to_string(),
+        ScSpecTypeDef::U256 => "U256".to_string(),
+        ScSpecTypeDef::I256 => "I256".to_string(),
+        ScSpecTypeDef::Bytes => "Bytes".to_string(),
+        ScSpecTypeDef::String => "String".to_string(),
+        ScSpecTypeDef::Symbol => "Symbol".to_string(),
+        ScSpecTypeDef::Address => "Address".to_string(),
+        ScSpecTypeDef::Option(o) => format!("Option<{}>", type_name(&o.value_type)),
+        ScSpecTypeDef::Result(r) => format!(
+            "Result<{}, {}>",
+            type_name(&r.ok_type),
+            type_name(&r.error_type)
+        ),
+        ScSpecTypeDef::Vec(v) => format!("Vec<{}>", type_name(&v.element_type)),
+        ScSpecTypeDef::Map(m) => format!(
+            "Map<{}, {}>",
+            type_name(&m.key_type),
+            type_name(&m.value_type)
+        ),
+        ScSpecTypeDef::Tuple(t) => {
+            let items: Vec<String> = t.value_types.iter().map(type_name).collect();
+            format!("({})", items.join(", "))
+        }
+        ScSpecTypeDef::BytesN(b) => format!("BytesN<{}>", b.n),
+        ScSpecTypeDef::Udt(u) => u.name.to_utf8_string_lossy(),
+    }
+}
+
+/// Parse a Rust-style type name such as `Vec<Address>` or `DataKey`.
+/// Unknown names are treated as user-defined types.
+pub fn parse_type(s: &str) -> Option<ScSpecTypeDef> {
+    let s = s.trim();
+
+    if let Some(inner) = s.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+        if inner.trim().is_empty() {
+            return Some(ScSpecTypeDef::Void);
+        }
+        let items = split_type_list(inner)?
+            .into_iter()
+            .map(parse_type)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(ScSpecTypeDef::Tuple(Box::new(ScSpecTypeTuple {
+            value_types: items.try_into().ok()?,
+        })));
+    }
+
+    if let Some((outer, inner)) = s.split_once('<') {
+        let inner = inner.strip_suffix('>')?;
+        let params = split_type_list(inner)?;
+        return match (outer.trim(), params.as_slice()) {
+            ("Option", [t]) => Some(ScSpecTypeDef::Option(Box::new(ScSpecTypeOption {
+                value_type: Box::new(parse_type(t)?),
+            }))),
+            ("Vec", [t]) => Some(ScSpecTypeDef::Vec(Box::new(ScSpecTypeVec {
+                element_type: Box::new(parse_type(t)?),
+            }))),
+            ("Map", [k, v]) => Some(ScSpecTypeDef::Map(Box::new(ScSpecTypeMap {
+                key_type: Box::new(parse_type(k)?),
+                value_type: Box::new(parse_type(v)?),
+            }))),
+            ("BytesN", [n]) => Some(ScSpecTypeDef::BytesN(ScSpecTypeBytesN {
+                n: n.trim().parse().ok()?,
+            })),
+            _ => None,
+        };
+    }
+
+    let ty = match s {
+        "Val" => ScSpecTypeDef::Val,
+        "bool" => ScSpecTypeDef::Bool,
+        "Error" => ScSpecTypeDef::Error,
+        "u32" => ScSpecTypeDef::U32,
+        "i32" => ScSpecTypeDef::I32,
+        "u64" => ScSpecTypeDef::U64,
+        "i64" => ScSpecTypeDef::I64,
+        "Timepoint" => ScSpecTypeDef::Timepoint,
+        "Duration" => ScSpecTypeDef::Duration,
+        "u128" => ScSpecTypeDef::U128,
+        "i128" => ScSpecTypeDef::I128,
+        "U256" | "u256" => ScSpecTypeDef::U256,
+        "I256" | "i256" => ScSpecTypeDef::I256,
+        "Bytes" => ScSpecTypeDef::Bytes,
+        "String" => ScSpecTypeDef::String,
+        "Symbol" => ScSpecTypeDef::Symbol,
+        "Address" => ScSpecTypeDef::Address,
+        name if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+            ScSpecTypeDef::Udt(ScSpecTypeUdt {
+                name: name.try_into().ok()?,
+            })
+        }
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// Split a comma separated type list, respecting nested brackets
+fn split_type_list(s: &str) -> Option<Vec<&str>> {
+    let mut items = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        if depth < 0 {
+            return None;
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    items.push(&s[start..]);
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_round_trip() {
+        for name in [
+            "u32",
+            "Vec<Address>",
+            "Map<Symbol, Vec<i128>>",
+            "Option<BytesN<32>>",
+            "(u32, bool, DataKey)",
+        ] {
+            let ty = parse_type(name).unwrap();
+            assert_eq!(type_name(&ty), name);
+        }
+        assert!(parse_type("Vec<u32").is_none());
+        assert!(parse_type("Map<u32>").is_none());
+    }
+}
@@ -0,0 +1,84 @@
+//! Contract the debugger's tests run against
+#![no_std]
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, symbol_short, Address, Env, Symbol, Vec};
+
+#[contracttype]
+#[derive(Clone)]
+pub enum DataKey {
+    Balance(Address),
+    Counter,
+}
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u32)]
+pub enum Error {
+    Insufficient = 1,
+}
+
+#[contract]
+pub struct TestContract;
+
+#[contractimpl]
+impl TestContract {
+    pub fn hello(env: Env, to: Symbol) -> Vec<Symbol> {
+        let mut v = Vec::new(&env);
+        v.push_back(symbol_short!("Hello"));
+        v.push_back(to);
+        v
+    }
+
+    pub fn add(_env: Env, a: i128, b: i128) -> i128 {
+        a + b
+    }
+
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let balance = Self::balance(env.clone(), to.clone());
+        env.storage()
+            .persistent()
+            .set(&DataKey::Balance(to), &(balance + amount));
+    }
+
+    pub fn balance(env: Env, id: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Balance(id))
+            .unwrap_or(0)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) -> Result<(), Error> {
+        from.require_auth();
+        let from_balance = Self::balance(env.clone(), from.clone());
+        if from_balance < amount {
+            return Err(Error::Insufficient);
+        }
+        let to_balance = Self::balance(env.clone(), to.clone());
+        env.storage()
+            .persistent()
+            .set(&DataKey::Balance(from.clone()), &(from_balance - amount));
+        env.storage()
+            .persistent()
+            .set(&DataKey::Balance(to.clone()), &(to_balance + amount));
+        env.events()
+            .publish((symbol_short!("transfer"), from, to), amount);
+        Ok(())
+    }
+
+    pub fn incr(env: Env) -> u32 {
+        let count: u32 = env.storage().instance().get(&DataKey::Counter).unwrap_or(0) + 1;
+        env.storage().instance().set(&DataKey::Counter, &count);
+        count
+    }
+
+    pub fn boom(_env: Env) {
+        panic!("boom");
+    }
+
+    pub fn spin(_env: Env, n: u64) -> u64 {
+        let mut x = 0u64;
+        for i in 0..n {
+            x = x.wrapping_mul(31).wrapping_add(i);
+        }
+        x
+    }
+}